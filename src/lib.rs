@@ -0,0 +1,13754 @@
+use std::borrow::Cow;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, IsTerminal, Read, Seek, SeekFrom, Stdout, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{
+    DisableFocusChange, DisableMouseCapture, EnableFocusChange, EnableMouseCapture,
+};
+use crossterm::style::{
+    Attribute, Color, Colored, Print, ResetColor, SetAttribute, SetBackgroundColor,
+    SetForegroundColor,
+};
+use crossterm::terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{execute, queue};
+use regex::Regex;
+
+/// Lines each tab keeps unless `--scrollback` says otherwise.
+const MAX_STORED_LINES_PER_TAB: usize = 5_000;
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+/// How long a badge stays bright after its tab gets a match, with `--flash-badges`.
+const BADGE_FLASH: Duration = Duration::from_millis(200);
+/// How long a line may wait for its newline before `--flush-partial` shows it.
+const PARTIAL_LINE_DELAY: Duration = Duration::from_millis(200);
+/// Reads `--flush-partial` may hold before cutting them into lines, so a
+/// blocked line channel stalls the reader as it would without the flag.
+const PARTIAL_CHUNK_QUEUE_LEN: usize = 16;
+const PAUSED_LABEL: &str = " (paused)";
+const INPUT_HELD_LABEL: &str = " (input held)";
+const UNREAD_ONLY_LABEL: &str = " (unread only)";
+const PAUSED_GLYPH: &str = "⏸";
+const INPUT_HELD_GLYPH: &str = "⏹";
+const UNREAD_ONLY_GLYPH: &str = "•";
+const EXITED_GLYPH: &str = "∎";
+/// How long the `--exec` exit status stays on the status row.
+const EXIT_STATUS_DURATION: Duration = Duration::from_secs(10);
+const DETAIL_PANE_MAX_TEXT_ROWS: usize = 6;
+const STATUS_MESSAGE_DURATION: Duration = Duration::from_secs(3);
+const QUIT_CONFIRM_WINDOW: Duration = Duration::from_secs(2);
+const WHEEL_SCROLL_ROWS: isize = 3;
+/// Below this the tab bar and a body row don't fit, so only a notice is drawn.
+const MIN_COLS: usize = 20;
+const MIN_ROWS: usize = 4;
+const RECENT_FILTERS_CAP: usize = 50;
+const MAX_REPEAT_COUNT: usize = 9999;
+/// Longer labels are cut with an ellipsis in the overview.
+const OVERVIEW_LABEL_WIDTH: usize = 24;
+/// Most palette entries listed at once.
+const PALETTE_ROWS: usize = 8;
+const DEFAULT_GUTTER_SEPARATOR: &str = "│ ";
+/// Background of the body line under the mouse.
+const HOVER_BACKGROUND: Color = Color::AnsiValue(236);
+/// Background of pinned body lines.
+const PINNED_BACKGROUND: Color = Color::AnsiValue(53);
+/// `--tint` body backgrounds, picked by the active tab's index and wrapping around.
+const TINT_BACKGROUNDS: [Color; 6] = [
+    Color::AnsiValue(17),
+    Color::AnsiValue(22),
+    Color::AnsiValue(52),
+    Color::AnsiValue(53),
+    Color::AnsiValue(23),
+    Color::AnsiValue(58),
+];
+const DEFAULT_GAP_THRESHOLD: Duration = Duration::from_secs(5);
+const HOOK_QUEUE_LEN: usize = 64;
+const HOOK_MIN_INTERVAL: Duration = Duration::from_millis(100);
+const HOOK_TIMEOUT: Duration = Duration::from_secs(10);
+const HOOK_MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+#[derive(Debug)]
+pub enum InputMessage {
+    /// A line, and which of several `--file`s it came from, if it did.
+    Line(String, Option<usize>),
+    /// This many lines were dropped under `--on-overflow drop` before the next.
+    Skipped(usize),
+    /// The start of a line still waiting for its newline, under
+    /// `--flush-partial`. The next `Line` completes it.
+    Partial(String),
+    Closed,
+    Error(String),
+}
+
+#[derive(Debug)]
+pub enum UiMessage {
+    NextTab,
+    PrevTab,
+    SelectTab(usize),
+    TogglePause,
+    ClearSelection,
+    SelectMiddleVisibleLine,
+    ToggleDetailPane,
+    ToggleUnreadOnly,
+    CycleMatchMode,
+    InvertTab,
+    ToggleInputHold,
+    TogglePreview,
+    StartSearch,
+    OpenPalette,
+    /// Moves the selected line by this many lines; positive is newer.
+    MoveSelection(isize),
+    ToggleOverview,
+    ToggleMarker,
+    CycleMarkerSlice,
+    CycleFrequencyView,
+    ToggleSplit,
+    /// Marks every tab read through the highlighted line.
+    MarkRead,
+    ToggleRelativeSeq,
+    /// Copies the highlighted line, without its colors, to the clipboard.
+    CopySelection,
+    /// Saves the active tab's stored lines to a file; see `dump_tab`.
+    DumpActiveTab,
+    /// Shows or hides the key hints on the bottom row.
+    ToggleHelp,
+    /// Scrolls the tab bar by this many tabs; positive shows later ones.
+    ScrollTabs(isize),
+    /// Stops or resumes scrolling live views to new lines, without pausing.
+    ToggleFollow,
+    /// Shows or hides the seq gutter, as `--seq-gutter` does at startup.
+    ToggleSeqGutter,
+    /// Switches long lines between clipped and wrapped onto several rows.
+    ToggleWrap,
+    /// Shows or hides each line's arrival time in the gutter.
+    ToggleClock,
+    /// Closes the active tab, unless it is `(all)` or the only one.
+    CloseTab,
+    /// Shows the active tab's lines in `$PAGER`.
+    OpenPager,
+    /// Moves scrolling and tab picks between the split panes.
+    SwitchSplitFocus,
+    /// Enter outside a prompt.
+    Activate,
+    /// `n`: the next older match of the active tab's search.
+    SearchNext,
+    /// `N`: the next newer match of the active tab's search.
+    SearchPrevious,
+    /// A bare Escape outside of a prompt.
+    Escape,
+    PromptInput(char),
+    PromptBackspace,
+    PromptSubmit,
+    PromptCancel,
+    MouseLeftDown {
+        column: u16,
+        row: u16,
+        shift: bool,
+    },
+    MouseLeftDrag {
+        column: u16,
+    },
+    MouseLeftUp {
+        column: u16,
+    },
+    /// Pins or unpins the line on `row`.
+    MouseRightDown {
+        row: u16,
+    },
+    /// Motion with no button held, for the hover highlight.
+    MouseMove {
+        row: u16,
+    },
+    Quit,
+    /// Ctrl+C, kept apart from `q` so it can skip quit confirmation.
+    Interrupt,
+    /// The terminal went away; quits without confirmation.
+    TerminalClosed,
+    HookFailed(String),
+    #[cfg(feature = "jsonl")]
+    ExportFailed(String),
+    ToggleGaps,
+    /// Rows to scroll the body by; negative scrolls up toward older lines.
+    Scroll(isize),
+    /// Like `Scroll`, in pages of the body's height.
+    ScrollPage(isize),
+    FocusGained,
+    FocusLost,
+    /// `Ctrl+L`: repaints the whole screen, for when something else drew over it.
+    Redraw,
+    Error(String),
+}
+
+#[derive(Debug, Clone)]
+pub enum MatchMode {
+    All,
+    Contains(String),
+    /// Holds the needle already lowercased.
+    ContainsCaseInsensitive(String),
+    Regex(Regex),
+    /// Matches the pattern literally, but only at word boundaries.
+    Word(Regex),
+    /// Matches every line the inner mode drops.
+    Not(Box<MatchMode>),
+    /// Matches lines containing any of the terms; each is highlighted in its color.
+    AnyOf(Vec<HighlightTerm>),
+    /// Matches lines containing every one of the terms, in any order.
+    AllOf(Vec<String>),
+    /// From `a&&b||c`: matches lines where every term of at least one group
+    /// appears, so `||` binds looser than `&&`.
+    AnyGroup(Vec<Vec<String>>),
+    /// Matches lines `detect_level` puts at this level.
+    Level(Level),
+    /// Under `--json`, from `path=value`: matches JSON lines whose field at the
+    /// dotted `path` equals `value`, and other lines containing the filter text.
+    JsonField {
+        path: String,
+        value: String,
+    },
+}
+
+/// A log level, as guessed from a line by `detect_level`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Error,
+    Warn,
+    Info,
+    Debug,
+}
+
+impl Level {
+    /// The tabs `--auto-levels` opens, in order.
+    const ALL: [Level; 4] = [Level::Error, Level::Warn, Level::Info, Level::Debug];
+
+    fn label(self) -> &'static str {
+        match self {
+            Level::Error => "ERROR",
+            Level::Warn => "WARN",
+            Level::Info => "INFO",
+            Level::Debug => "DEBUG",
+        }
+    }
+
+    fn from_word(word: &str) -> Option<Level> {
+        match word.to_ascii_lowercase().as_str() {
+            "error" | "err" | "fatal" | "crit" | "critical" | "panic" => Some(Level::Error),
+            "warn" | "warning" => Some(Level::Warn),
+            "info" | "notice" => Some(Level::Info),
+            "debug" | "dbg" | "trace" => Some(Level::Debug),
+            _ => None,
+        }
+    }
+}
+
+/// How many words into a line the level may appear, past timestamps and
+/// other numbers, so words in the message itself don't count.
+const LEVEL_SEARCH_WORDS: usize = 6;
+
+/// Guesses a line's level from the first level word near its start, as in
+/// `ERROR ...`, `[warn]`, `level=info` or `2024-05-01 12:00:00 DEBUG ...`.
+fn detect_level(line: &str) -> Option<Level> {
+    strip_ansi(line)
+        .split(|ch: char| !ch.is_ascii_alphanumeric())
+        .filter(|word| word.starts_with(|ch: char| ch.is_ascii_alphabetic()))
+        .take(LEVEL_SEARCH_WORDS)
+        .find_map(Level::from_word)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HighlightTerm {
+    text: String,
+    style: HighlightStyle,
+}
+
+/// How a highlighted span of a line is drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum HighlightStyle {
+    /// An `any:` term or a capture group, in its color.
+    Term(Color),
+    /// A search hit, in reverse video.
+    Search,
+}
+
+/// Colors handed to `any:` terms in order, wrapping around.
+const HIGHLIGHT_COLORS: [Color; 6] = [
+    Color::Red,
+    Color::Yellow,
+    Color::Magenta,
+    Color::Cyan,
+    Color::Green,
+    Color::Blue,
+];
+
+/// Splits `error|warn|timeout` into terms colored in order. Empty terms are
+/// dropped, since they would match every line.
+fn any_of_terms(patterns: &str) -> Vec<HighlightTerm> {
+    patterns
+        .split('|')
+        .filter(|term| !term.is_empty())
+        .zip(HIGHLIGHT_COLORS.iter().cycle())
+        .map(|(text, color)| HighlightTerm {
+            text: text.to_owned(),
+            style: HighlightStyle::Term(*color),
+        })
+        .collect()
+}
+
+impl MatchMode {
+    fn label_prefix(&self) -> String {
+        match self {
+            MatchMode::All
+            | MatchMode::Contains(_)
+            | MatchMode::AllOf(_)
+            | MatchMode::AnyGroup(_)
+            | MatchMode::Level(_)
+            | MatchMode::JsonField { .. } => String::new(),
+            MatchMode::ContainsCaseInsensitive(_) => "i:".to_owned(),
+            MatchMode::Regex(_) => "re:".to_owned(),
+            MatchMode::Word(_) => "w:".to_owned(),
+            MatchMode::Not(inner) => format!("!{}", inner.label_prefix()),
+            MatchMode::AnyOf(_) => "any:".to_owned(),
+        }
+    }
+
+    fn is_match(&self, line: &str) -> bool {
+        match self {
+            MatchMode::All => true,
+            MatchMode::Contains(filter) => line.contains(filter),
+            MatchMode::ContainsCaseInsensitive(needle) => contains_ignore_case(line, needle),
+            MatchMode::Regex(regex) | MatchMode::Word(regex) => regex.is_match(line),
+            MatchMode::Not(inner) => !inner.is_match(line),
+            MatchMode::AnyOf(terms) => terms.iter().any(|term| line.contains(&term.text)),
+            MatchMode::AllOf(terms) => terms.iter().all(|term| line.contains(term.as_str())),
+            MatchMode::AnyGroup(groups) => groups
+                .iter()
+                .any(|group| group.iter().all(|term| line.contains(term.as_str()))),
+            MatchMode::Level(level) => detect_level(line) == Some(*level),
+            MatchMode::JsonField { path, value } => json_field_matches(line, path, value),
+        }
+    }
+
+    /// The mode after this one in the `m` cycle for `pattern`, plus a warning if
+    /// regex mode had to be skipped. An inverted mode cycles its inner mode.
+    fn next(&self, pattern: &str) -> Option<(MatchMode, Option<String>)> {
+        let next = match self {
+            MatchMode::All
+            | MatchMode::AnyOf(_)
+            | MatchMode::AllOf(_)
+            | MatchMode::AnyGroup(_)
+            | MatchMode::Level(_)
+            | MatchMode::JsonField { .. } => return None,
+            MatchMode::Contains(_) => MatchMode::ContainsCaseInsensitive(pattern.to_lowercase()),
+            MatchMode::ContainsCaseInsensitive(_) => match Regex::new(pattern) {
+                Ok(regex) => MatchMode::Regex(regex),
+                Err(err) => {
+                    let warning = format!("skipped regex mode: {}", regex_error_summary(&err));
+                    return Some((MatchMode::Word(word_regex(pattern)), Some(warning)));
+                }
+            },
+            MatchMode::Regex(_) => MatchMode::Word(word_regex(pattern)),
+            MatchMode::Word(_) => MatchMode::Contains(pattern.to_owned()),
+            MatchMode::Not(inner) => {
+                let (next, warning) = inner.next(pattern)?;
+                return Some((MatchMode::Not(Box::new(next)), warning));
+            }
+        };
+        Some((next, None))
+    }
+}
+
+/// A line that doesn't parse as a JSON object is matched on its text, as if
+/// the filter were plain.
+fn json_field_matches(line: &str, path: &str, value: &str) -> bool {
+    #[cfg(feature = "json")]
+    if let Some(matched) = json_input::field_equals(line, path, value) {
+        return matched;
+    }
+    line.match_indices(path).any(|(start, _)| {
+        line[start + path.len()..]
+            .strip_prefix('=')
+            .is_some_and(|rest| rest.starts_with(value))
+    })
+}
+
+/// Whether `line` contains `needle`, which is already lowercase, ignoring
+/// case. ASCII lines, the common case, are compared without allocating.
+fn contains_ignore_case(line: &str, needle: &str) -> bool {
+    if !line.is_ascii() || !needle.is_ascii() {
+        return line.to_lowercase().contains(needle);
+    }
+    needle.is_empty()
+        || line
+            .as_bytes()
+            .windows(needle.len())
+            .any(|window| window.eq_ignore_ascii_case(needle.as_bytes()))
+}
+
+/// Parses `auth&&fail||refused` into groups of terms, splitting on `||`
+/// first so `&&` binds tighter. Terms are trimmed, and empty ones dropped.
+/// `None` unless the filter uses either operator and keeps a term.
+fn expression_mode(include: &str) -> Option<MatchMode> {
+    if !include.contains("&&") && !include.contains("||") {
+        return None;
+    }
+    let mut groups: Vec<Vec<String>> = include
+        .split("||")
+        .map(|group| {
+            group
+                .split("&&")
+                .map(str::trim)
+                .filter(|term| !term.is_empty())
+                .map(str::to_owned)
+                .collect::<Vec<_>>()
+        })
+        .filter(|group| !group.is_empty())
+        .collect();
+    match groups.len() {
+        0 => None,
+        1 => Some(MatchMode::AllOf(groups.remove(0))),
+        _ => Some(MatchMode::AnyGroup(groups)),
+    }
+}
+
+fn word_regex(pattern: &str) -> Regex {
+    let is_word_char = |ch: char| ch.is_alphanumeric() || ch == '_';
+    // `\b` only anchors next to word characters, so skip it on edges that aren't.
+    let start = if pattern.starts_with(is_word_char) {
+        r"\b"
+    } else {
+        ""
+    };
+    let end = if pattern.ends_with(is_word_char) {
+        r"\b"
+    } else {
+        ""
+    };
+    Regex::new(&format!("{}{}{}", start, regex::escape(pattern), end))
+        .expect("escaped pattern is always a valid regex")
+}
+
+/// Which tabs a line matched, one bit per tab index. Only as many words as
+/// the highest matching index needs are stored.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct TabSet(Vec<u64>);
+
+impl TabSet {
+    fn set(&mut self, index: usize, matched: bool) {
+        let (word, bit) = (index / 64, 1u64 << (index % 64));
+        if matched {
+            if self.0.len() <= word {
+                self.0.resize(word + 1, 0);
+            }
+            self.0[word] |= bit;
+        } else if let Some(bits) = self.0.get_mut(word) {
+            *bits &= !bit;
+        }
+    }
+
+    /// Drops `index`, moving the tabs after it down one, as `Vec::remove` does.
+    fn remove(&mut self, index: usize) {
+        let kept: Vec<usize> = self
+            .iter()
+            .filter(|&i| i != index)
+            .map(|i| if i > index { i - 1 } else { i })
+            .collect();
+        self.0.clear();
+        for i in kept {
+            self.set(i, true);
+        }
+    }
+
+    fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.0.iter().enumerate().flat_map(|(word, &bits)| {
+            (0..64)
+                .filter(move |bit| bits & (1 << bit) != 0)
+                .map(move |bit| word * 64 + bit)
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct LineRecord {
+    seq: u64,
+    text: String,
+    arrived: Instant,
+    /// The index of the `--file` it came from, when several are read.
+    source: Option<usize>,
+    /// Every tab the line matched. Only filled in for `(all)` tab records,
+    /// where it is kept current as tabs are added or re-moded.
+    matched_tabs: TabSet,
+}
+
+impl LineRecord {
+    fn new(seq: u64, text: &str, source: Option<usize>) -> Self {
+        Self {
+            seq,
+            text: text.to_owned(),
+            arrived: Instant::now(),
+            source,
+            matched_tabs: TabSet::default(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Tab {
+    label: String,
+    pattern: String,
+    mode: MatchMode,
+    /// Lines containing this are hidden even when `mode` matches them.
+    exclude: Option<String>,
+    lines: VecDeque<LineRecord>,
+    /// Oldest lines are dropped past this many, from `--scrollback`.
+    max_lines: usize,
+    /// Text bytes held in `lines`, kept up to date as lines come and go.
+    stored_bytes: usize,
+    total_matches: u64,
+    seen_matches: u64,
+    /// `seen_matches` from when the tab was last switched to, so the unread-only
+    /// view still knows what was new even after viewing marks it seen.
+    unread_view_start: u64,
+    search: Option<SearchState>,
+    on_match: Option<MatchHook>,
+    /// Kept only while the frequency view is open; see `count_lines`.
+    line_counts: Option<LineCounts>,
+    /// When a newly arrived line last matched, for `--flash-badges`.
+    last_match_at: Option<Instant>,
+    /// From a `color=name:` prefix; drawn on the title, and the border while active.
+    color: Option<Color>,
+    /// The top line of this tab's view when it was last switched away from
+    /// while scrolled, to go back to when it is shown again.
+    saved_scroll_top: Option<u64>,
+    #[cfg(feature = "jsonl")]
+    export: Option<jsonl_export::JsonlExport>,
+}
+
+/// Filters used in this and earlier sessions, most recent first, kept in a
+/// file under the XDG state directory so the palette can offer them again.
+#[derive(Debug, Default)]
+struct RecentFilters {
+    path: Option<PathBuf>,
+    entries: Vec<String>,
+}
+
+impl RecentFilters {
+    fn load() -> Self {
+        let path = recent_filters_path();
+        let entries = path
+            .as_deref()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .map(|text| {
+                text.lines()
+                    .filter(|line| !line.is_empty())
+                    .take(RECENT_FILTERS_CAP)
+                    .map(str::to_owned)
+                    .collect()
+            })
+            .unwrap_or_default();
+        Self { path, entries }
+    }
+
+    fn remember(&mut self, filter: &str) {
+        if filter.is_empty() || filter.contains('\n') {
+            return;
+        }
+        self.entries.retain(|entry| entry != filter);
+        self.entries.insert(0, filter.to_owned());
+        self.entries.truncate(RECENT_FILTERS_CAP);
+    }
+
+    /// Best effort: a read-only or missing state directory just means the
+    /// palette starts empty next time.
+    fn save(&self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        if let Some(dir) = path.parent() {
+            let _ = std::fs::create_dir_all(dir);
+        }
+        let mut text = self.entries.join("\n");
+        text.push('\n');
+        let _ = std::fs::write(path, text);
+    }
+}
+
+fn recent_filters_path() -> Option<PathBuf> {
+    let state_dir = std::env::var_os("XDG_STATE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| {
+            std::env::var_os("HOME")
+                .filter(|home| !home.is_empty())
+                .map(|home| PathBuf::from(home).join(".local/state"))
+        })?;
+    Some(state_dir.join("streamtabs").join("recent-filters"))
+}
+
+/// The recent-filters picker: typed text narrows the entries and the arrow
+/// keys move the cursor among those left.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Palette {
+    query: String,
+    entries: Vec<String>,
+    cursor: usize,
+}
+
+impl Palette {
+    fn new(entries: Vec<String>) -> Self {
+        Self {
+            query: String::new(),
+            entries,
+            cursor: 0,
+        }
+    }
+
+    fn matching(&self) -> Vec<&str> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.contains(&self.query))
+            .map(String::as_str)
+            .collect()
+    }
+
+    fn edit_query(&mut self, edit: impl FnOnce(&mut String)) {
+        edit(&mut self.query);
+        self.cursor = 0;
+    }
+
+    fn move_cursor(&mut self, delta: isize) {
+        let last = self.matching().len().saturating_sub(1);
+        self.cursor = self.cursor.saturating_add_signed(delta).min(last);
+    }
+
+    /// The entry under the cursor, or the typed text if nothing matches it.
+    fn choice(&self) -> Option<String> {
+        match self.matching().get(self.cursor) {
+            Some(entry) => Some((*entry).to_owned()),
+            None => Some(self.query.clone()).filter(|query| !query.is_empty()),
+        }
+    }
+}
+
+/// How often each distinct line (ANSI codes aside) matched a tab, with the
+/// `seq` it last matched at.
+#[derive(Debug, Default)]
+struct LineCounts {
+    by_text: HashMap<String, (u64, u64)>,
+}
+
+impl LineCounts {
+    fn add(&mut self, record: &LineRecord) {
+        let entry = self
+            .by_text
+            .entry(strip_ansi(&record.text))
+            .or_insert((0, record.seq));
+        entry.0 += 1;
+        entry.1 = record.seq;
+    }
+
+    /// The `limit` most frequent (or most recently seen) lines with their counts.
+    fn top(&self, sort: FrequencySort, limit: usize) -> Vec<(u64, &str)> {
+        let mut rows: Vec<_> = self.by_text.iter().collect();
+        match sort {
+            FrequencySort::Count => {
+                rows.sort_by_key(|&(_, &(count, last_seq))| Reverse((count, last_seq)))
+            }
+            FrequencySort::Recency => rows.sort_by_key(|&(_, &(_, last_seq))| Reverse(last_seq)),
+        }
+        rows.into_iter()
+            .take(limit)
+            .map(|(text, &(count, _))| (count, text.as_str()))
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FrequencySort {
+    Count,
+    Recency,
+}
+
+/// A tab's last search query and the match it is currently on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct SearchState {
+    query: String,
+    current_seq: Option<u64>,
+}
+
+impl SearchState {
+    fn new(query: String) -> Self {
+        Self {
+            query,
+            current_seq: None,
+        }
+    }
+
+    /// Finds the nearest match older (or newer) than the current one among the
+    /// first `visible_len` lines. Without a current match, older starts from the
+    /// bottom and there is nothing newer.
+    fn find<'a>(
+        &self,
+        lines: &'a VecDeque<LineRecord>,
+        visible_len: usize,
+        older: bool,
+    ) -> Option<&'a LineRecord> {
+        let visible = lines.range(..visible_len.min(lines.len()));
+        let is_match = |line: &&LineRecord| strip_ansi(&line.text).contains(&self.query);
+        if older {
+            visible
+                .rev()
+                .filter(|line| self.current_seq.is_none_or(|current| line.seq < current))
+                .find(is_match)
+        } else {
+            let current = self.current_seq?;
+            visible.filter(|line| line.seq > current).find(is_match)
+        }
+    }
+}
+
+/// Splits off a leading `color=name:`, giving the named color (or the name,
+/// if it isn't one) and the filter after it.
+fn split_tab_color(filter: &str) -> (Option<Result<Color, &str>>, &str) {
+    match filter
+        .strip_prefix("color=")
+        .and_then(|rest| rest.split_once(':'))
+    {
+        Some((name, rest)) => (Some(Color::try_from(name).map_err(|()| name)), rest),
+        None => (None, filter),
+    }
+}
+
+/// Splits off a `~text` exclusion, which must be non-empty, at the first `~`
+/// not written as `\~`. Either side reads `\~` as a plain `~`.
+fn split_exclude(filter: &str) -> (Cow<'_, str>, Option<String>) {
+    let split = filter
+        .match_indices('~')
+        .map(|(at, _)| at)
+        .find(|&at| !filter[..at].ends_with('\\'));
+    match split {
+        Some(at) if at + 1 < filter.len() => (
+            unescape_tildes(&filter[..at]),
+            Some(unescape_tildes(&filter[at + 1..]).into_owned()),
+        ),
+        _ => (unescape_tildes(filter), None),
+    }
+}
+
+fn unescape_tildes(text: &str) -> Cow<'_, str> {
+    if text.contains("\\~") {
+        Cow::Owned(text.replace("\\~", "~"))
+    } else {
+        Cow::Borrowed(text)
+    }
+}
+
+/// Writes `text` back the way `split_exclude` reads it, so a `~` in it stays
+/// part of the filter.
+fn escape_tildes(text: &str) -> Cow<'_, str> {
+    if text.contains('~') {
+        Cow::Owned(text.replace('~', "\\~"))
+    } else {
+        Cow::Borrowed(text)
+    }
+}
+
+/// Splits off a leading `!`, which inverts the rest; a lone `!` is just text.
+fn split_negation(include: &str) -> (bool, &str) {
+    match include.strip_prefix('!') {
+        Some(rest) if !rest.is_empty() => (true, rest),
+        _ => (false, include),
+    }
+}
+
+/// The compiled pattern of a `re:` filter, or `None` for other filters.
+fn filter_regex(filter: &str) -> Option<(String, Result<Regex, regex::Error>)> {
+    let (include, _) = split_exclude(filter);
+    let (_, include) = split_negation(&include);
+    let pattern = include.strip_prefix("re:")?;
+    Some((pattern.to_owned(), Regex::new(pattern)))
+}
+
+/// Rejects a `re:` filter whose pattern doesn't compile, or a color that
+/// isn't one, before it becomes a tab.
+fn check_filter(filter: &str) -> Result<(), String> {
+    let (color, rest) = split_tab_color(filter);
+    if let Some(Err(name)) = color {
+        return Err(format!(
+            "unknown color {:?} in filter {:?}; try red, dark_cyan, magenta, ...",
+            name, filter
+        ));
+    }
+    match filter_regex(rest) {
+        Some((_, Err(err))) => Err(format!(
+            "invalid regex in filter {:?}: {}",
+            filter,
+            regex_error_summary(&err)
+        )),
+        _ => Ok(()),
+    }
+}
+
+impl Tab {
+    /// A filter written as `any:a|b|c` matches any of the terms, `re:pattern`
+    /// matches the regex, `i:text` ignores case, `a&&b||c` needs both `a` and
+    /// `b`, or `c`, a leading `!` inverts any of these, and a `~text` suffix
+    /// hides matching lines that also contain `text`; `\~` is a literal `~`.
+    /// A `re:` filter that fails `check_filter` is matched literally instead.
+    /// A `color=name:` prefix colors the tab and is left out of its label.
+    pub fn new(filter: String) -> Self {
+        let (color, filter) = match split_tab_color(&filter) {
+            (Some(color), rest) => (color.ok(), rest.to_owned()),
+            (None, _) => (None, filter),
+        };
+        let (include, exclude) = split_exclude(&filter);
+        let (negated, include) = split_negation(&include);
+        let (pattern, mode) = match (include.strip_prefix("any:"), filter_regex(&filter)) {
+            (Some(terms), _) if !any_of_terms(terms).is_empty() => {
+                (terms.to_owned(), MatchMode::AnyOf(any_of_terms(terms)))
+            }
+            (_, Some((pattern, Ok(regex)))) => (pattern, MatchMode::Regex(regex)),
+            _ if let Some(text) = include.strip_prefix("i:") => (
+                text.to_owned(),
+                MatchMode::ContainsCaseInsensitive(text.to_lowercase()),
+            ),
+            _ if let Some(mode) = expression_mode(include) => (include.to_owned(), mode),
+            _ => (include.to_owned(), MatchMode::Contains(include.to_owned())),
+        };
+        let mode = if negated {
+            MatchMode::Not(Box::new(mode))
+        } else {
+            mode
+        };
+        Self {
+            label: filter.clone(),
+            pattern,
+            mode,
+            exclude,
+            lines: VecDeque::new(),
+            max_lines: MAX_STORED_LINES_PER_TAB,
+            stored_bytes: 0,
+            total_matches: 0,
+            seen_matches: 0,
+            unread_view_start: 0,
+            search: None,
+            on_match: None,
+            line_counts: None,
+            last_match_at: None,
+            color,
+            saved_scroll_top: None,
+            #[cfg(feature = "jsonl")]
+            export: None,
+        }
+    }
+
+    fn with_scrollback(mut self, max_lines: usize) -> Self {
+        self.max_lines = max_lines;
+        self
+    }
+
+    /// Under `--and-tokens`: a plain filter of several words matches lines
+    /// containing all of them, in any order, instead of the literal text.
+    fn with_and_tokens(mut self) -> Self {
+        if let MatchMode::Contains(pattern) = &self.mode {
+            let tokens = pattern
+                .split_whitespace()
+                .map(str::to_owned)
+                .collect::<Vec<_>>();
+            if tokens.len() > 1 {
+                self.mode = MatchMode::AllOf(tokens);
+            }
+        }
+        self
+    }
+
+    /// Under `--json`: a plain `path=value` filter, where the path is made of
+    /// letters, digits, `_`, `-` and `.`, compares that field of JSON lines.
+    fn with_json_fields(mut self) -> Self {
+        fn field_mode(mode: &MatchMode) -> Option<MatchMode> {
+            match mode {
+                MatchMode::Contains(pattern) => {
+                    let (path, value) = pattern.split_once('=')?;
+                    let is_path = !path.is_empty()
+                        && path
+                            .chars()
+                            .all(|ch| ch.is_ascii_alphanumeric() || matches!(ch, '_' | '-' | '.'));
+                    is_path.then(|| MatchMode::JsonField {
+                        path: path.to_owned(),
+                        value: value.to_owned(),
+                    })
+                }
+                MatchMode::Not(inner) => Some(MatchMode::Not(Box::new(field_mode(inner)?))),
+                _ => None,
+            }
+        }
+        if let Some(mode) = field_mode(&self.mode) {
+            self.mode = mode;
+        }
+        self
+    }
+
+    /// One of the `--auto-levels` tabs, labelled with its level.
+    fn for_level(level: Level) -> Self {
+        let mut tab = Tab::new(level.label().to_owned());
+        tab.mode = MatchMode::Level(level);
+        tab
+    }
+
+    /// The `(all)` tab, which takes every line.
+    pub fn unfiltered() -> Self {
+        Self {
+            label: "(all)".to_owned(),
+            pattern: String::new(),
+            mode: MatchMode::All,
+            exclude: None,
+            lines: VecDeque::new(),
+            max_lines: MAX_STORED_LINES_PER_TAB,
+            stored_bytes: 0,
+            total_matches: 0,
+            seen_matches: 0,
+            unread_view_start: 0,
+            search: None,
+            on_match: None,
+            line_counts: None,
+            last_match_at: None,
+            color: None,
+            saved_scroll_top: None,
+            #[cfg(feature = "jsonl")]
+            export: None,
+        }
+    }
+
+    /// Takes the saved scroll position, as long as its line is still held.
+    fn restore_scroll_top(&mut self) -> Option<u64> {
+        self.saved_scroll_top.take().filter(|&seq| {
+            self.lines
+                .binary_search_by_key(&seq, |line| line.seq)
+                .is_ok()
+        })
+    }
+
+    #[cfg(test)]
+    fn push_line(&mut self, seq: u64, line: &str) {
+        self.push_record(LineRecord::new(seq, line, None));
+    }
+
+    fn push_record(&mut self, record: LineRecord) {
+        if let Some(counts) = self.line_counts.as_mut() {
+            counts.add(&record);
+        }
+        self.stored_bytes += record.text.len();
+        self.lines.push_back(record);
+        self.total_matches += 1;
+
+        if self.lines.len() > self.max_lines
+            && let Some(evicted) = self.lines.pop_front()
+        {
+            self.stored_bytes -= evicted.text.len();
+        }
+    }
+
+    /// The name on the tab's title.
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    pub fn mode(&self) -> &MatchMode {
+        &self.mode
+    }
+
+    /// The lines the tab holds, oldest first, with their seqs.
+    pub fn lines(&self) -> impl Iterator<Item = (u64, &str)> {
+        self.lines.iter().map(|line| (line.seq, line.text.as_str()))
+    }
+
+    pub fn unread_matches(&self) -> u64 {
+        self.total_matches.saturating_sub(self.seen_matches)
+    }
+
+    /// Number of stored lines (from the front) that were already seen when the
+    /// tab was switched to.
+    fn lines_read_before_view(&self) -> usize {
+        let first_stored_match = self.total_matches - self.lines.len() as u64;
+        self.unread_view_start
+            .saturating_sub(first_stored_match)
+            .min(self.lines.len() as u64) as usize
+    }
+
+    /// Matches counted up to and including the line `seq`, or up to the last
+    /// older line when this tab doesn't hold `seq` itself.
+    fn match_index_through(&self, seq: u64) -> u64 {
+        let first_stored_match = self.total_matches - self.lines.len() as u64;
+        first_stored_match + self.lines.partition_point(|line| line.seq <= seq) as u64
+    }
+
+    /// When the badge flash from the latest match ends, if that's after `now`.
+    fn badge_flash_until(&self, now: Instant) -> Option<Instant> {
+        self.last_match_at
+            .map(|matched| matched + BADGE_FLASH)
+            .filter(|until| *until > now)
+    }
+
+    fn mark_seen_through(&mut self, max_match_index: u64) {
+        let capped = max_match_index.min(self.total_matches);
+        if capped > self.seen_matches {
+            self.seen_matches = capped;
+        }
+    }
+
+    /// Whether `line` belongs in this tab, exclusion included.
+    pub fn matches(&self, line: &str) -> bool {
+        self.mode.is_match(line)
+            && !self
+                .exclude
+                .as_ref()
+                .is_some_and(|exclude| line.contains(exclude.as_str()))
+    }
+
+    /// Moves this tab's search to its next older (or newer) match and returns
+    /// that line. Stays put if there is no search or no further match.
+    fn advance_search(&mut self, visible_len: usize, older: bool) -> Option<SelectedLine> {
+        let search = self.search.as_mut()?;
+        let line = search.find(&self.lines, visible_len, older)?;
+        search.current_seq = Some(line.seq);
+        Some(SelectedLine {
+            seq: line.seq,
+            text: line.text.clone(),
+        })
+    }
+
+    /// A filter made only of whitespace is explicitly looking for it.
+    fn targets_whitespace(&self) -> bool {
+        !matches!(self.mode, MatchMode::All)
+            && !self.pattern.is_empty()
+            && self.pattern.trim().is_empty()
+    }
+
+    fn set_mode(&mut self, mode: MatchMode) {
+        self.label = format!("{}{}", mode.label_prefix(), escape_tildes(&self.pattern));
+        if let Some(exclude) = &self.exclude {
+            self.label.push('~');
+            self.label.push_str(&escape_tildes(exclude));
+        }
+        self.mode = mode;
+    }
+
+    /// Advances contains → case-insensitive → regex → word → contains, keeping the
+    /// pattern. Returns a warning if regex mode had to be skipped.
+    fn cycle_mode(&mut self) -> Option<String> {
+        let (next, warning) = self.mode.next(&self.pattern)?;
+        self.set_mode(next);
+        warning
+    }
+
+    /// A fresh, empty tab matching exactly the lines this one drops; inverting an
+    /// inverted tab gives back the original filter. `(all)` has no inverse, and
+    /// neither do tabs with an exclude, whose inverse isn't a single mode.
+    fn inverted(&self) -> Option<Tab> {
+        if self.exclude.is_some() {
+            return None;
+        }
+        let mode = match &self.mode {
+            MatchMode::All => return None,
+            MatchMode::Not(inner) => (**inner).clone(),
+            mode => MatchMode::Not(Box::new(mode.clone())),
+        };
+        let mut tab =
+            Tab::new(escape_tildes(&self.pattern).into_owned()).with_scrollback(self.max_lines);
+        tab.set_mode(mode);
+        tab.color = self.color;
+        Some(tab)
+    }
+
+    /// Starts counting distinct lines from those stored now. Counts then keep
+    /// growing with new matches, even past what the tab still stores.
+    fn count_lines(&mut self) {
+        if self.line_counts.is_none() {
+            let mut counts = LineCounts::default();
+            for record in &self.lines {
+                counts.add(record);
+            }
+            self.line_counts = Some(counts);
+        }
+    }
+
+    /// Replaces the stored lines with those from `source` that match this tab.
+    /// Everything rebuilt starts unread; callers mark what is on screen as seen.
+    fn rebuild_from(&mut self, source: &VecDeque<LineRecord>, fold_case: bool) {
+        self.line_counts = None;
+        self.lines.clear();
+        self.stored_bytes = 0;
+        self.total_matches = 0;
+        self.seen_matches = 0;
+        self.unread_view_start = 0;
+        self.last_match_at = None;
+        for line in source {
+            if self.matches(&match_text(&line.text, fold_case)) {
+                self.push_record(line.clone());
+            }
+        }
+    }
+}
+
+/// Recent input kept apart from the tabs, so new and re-moded tabs can be
+/// backfilled whether or not there is an `(all)` tab.
+#[derive(Debug)]
+struct History {
+    lines: VecDeque<LineRecord>,
+    capacity: usize,
+    stored_bytes: usize,
+    /// Under `--transform lowercase`, rescanned lines are matched lowercased.
+    fold_case: bool,
+}
+
+impl History {
+    fn new(capacity: usize) -> Self {
+        Self {
+            lines: VecDeque::new(),
+            capacity,
+            stored_bytes: 0,
+            fold_case: false,
+        }
+    }
+
+    #[cfg(test)]
+    fn push_line(&mut self, seq: u64, line: &str) {
+        self.push_record(LineRecord::new(seq, line, None));
+    }
+
+    fn push_record(&mut self, record: LineRecord) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        self.stored_bytes += record.text.len();
+        self.lines.push_back(record);
+        if self.lines.len() > self.capacity
+            && let Some(evicted) = self.lines.pop_front()
+        {
+            self.stored_bytes -= evicted.text.len();
+        }
+    }
+}
+
+/// Rescans the history into `tabs[index]`. Returns `false` if history is
+/// turned off, in which case the tab keeps its current lines.
+fn rebuild_tab(tabs: &mut [Tab], history: &History, index: usize) -> bool {
+    if history.capacity == 0 || index >= tabs.len() || matches!(tabs[index].mode, MatchMode::All) {
+        return false;
+    }
+
+    tabs[index].rebuild_from(&history.lines, history.fold_case);
+    refresh_matched_tabs(tabs, index, history.fold_case);
+    true
+}
+
+/// Re-matches the `(all)` records against `tabs[index]` after it changed.
+fn refresh_matched_tabs(tabs: &mut [Tab], index: usize, fold_case: bool) {
+    for all in 0..tabs.len() {
+        if !matches!(tabs[all].mode, MatchMode::All) {
+            continue;
+        }
+        let mut lines = std::mem::take(&mut tabs[all].lines);
+        for line in &mut lines {
+            let matched = tabs[index].matches(&match_text(&line.text, fold_case));
+            line.matched_tabs.set(index, matched);
+        }
+        tabs[all].lines = lines;
+    }
+}
+
+/// Labels of the filter tabs that caught line `seq`, as recorded on the
+/// `(all)` tab. `None` without an `(all)` tab or once it has dropped the line.
+fn matched_tab_labels(tabs: &[Tab], seq: u64) -> Option<Vec<String>> {
+    let all = tabs.iter().find(|tab| matches!(tab.mode, MatchMode::All))?;
+    let position = all.lines.partition_point(|line| line.seq < seq);
+    let line = all.lines.get(position).filter(|line| line.seq == seq)?;
+    let offset = tab_number_offset(tabs);
+    Some(
+        line.matched_tabs
+            .iter()
+            .filter_map(|index| {
+                let tab = tabs.get(index)?;
+                (!matches!(tab.mode, MatchMode::All))
+                    .then(|| format!("{} {}", index + offset, tab.label))
+            })
+            .collect(),
+    )
+}
+
+/// Appends `tab`, backfilled from the history, and returns its index along
+/// with whether it was backfilled. Backfilled lines count as seen, except
+/// those that arrived after a pause, which stay unread until resuming.
+fn add_tab(
+    tabs: &mut Vec<Tab>,
+    history: &History,
+    tab: Tab,
+    pause_snapshot: Option<&mut PauseSnapshot>,
+) -> (usize, bool) {
+    tabs.push(tab);
+    let index = tabs.len() - 1;
+    let backfilled = rebuild_tab(tabs, history, index);
+
+    let tab = &mut tabs[index];
+    if let Some(snapshot) = pause_snapshot {
+        snapshot.line_cutoffs.push(0);
+        snapshot.match_cutoffs.push(0);
+        snapshot.refresh_tab(index, tab);
+        tab.mark_seen_through(snapshot.match_cutoffs[index]);
+    } else {
+        tab.mark_seen_through(tab.total_matches);
+    }
+    (index, backfilled)
+}
+
+/// Like `add_tab`, but reuses a tab with the same label if there is one;
+/// reused tabs count as backfilled.
+fn find_or_add_tab(
+    tabs: &mut Vec<Tab>,
+    history: &History,
+    tab: Tab,
+    pause_snapshot: Option<&mut PauseSnapshot>,
+) -> (usize, bool) {
+    match tabs.iter().position(|existing| existing.label == tab.label) {
+        Some(existing) => (existing, true),
+        None => add_tab(tabs, history, tab, pause_snapshot),
+    }
+}
+
+/// Removes `tabs[index]`, keeping everything indexed by tab aligned: the
+/// active tabs, the view whose scroll position is held, the pause
+/// snapshot's cutoffs and the `(all)` records' match sets. If it was the
+/// only tab shown, its neighbor is shown instead. The `(all)` tab and a
+/// lone tab can't be closed.
+fn close_tab(
+    tabs: &mut Vec<Tab>,
+    active_index: &mut usize,
+    active_tab_indices: &mut Vec<usize>,
+    scrolled_tabs: &mut Vec<usize>,
+    index: usize,
+    paused: bool,
+    pause_snapshot: Option<&mut PauseSnapshot>,
+) -> Result<Tab, &'static str> {
+    match tabs.get(index) {
+        None => return Err("no such tab"),
+        Some(tab) if matches!(tab.mode, MatchMode::All) => {
+            return Err("the (all) tab can't be closed");
+        }
+        Some(_) if tabs.len() == 1 => return Err("the only tab can't be closed"),
+        Some(_) => {}
+    }
+
+    let closed = tabs.remove(index);
+    for all in tabs
+        .iter_mut()
+        .filter(|tab| matches!(tab.mode, MatchMode::All))
+    {
+        for line in &mut all.lines {
+            line.matched_tabs.remove(index);
+        }
+    }
+    let snapshot = pause_snapshot.map(|snapshot| {
+        snapshot.line_cutoffs.remove(index);
+        snapshot.match_cutoffs.remove(index);
+        &*snapshot
+    });
+
+    let shift = |i: usize| if i > index { i - 1 } else { i };
+    // A view with the closed tab in it is gone, so its scroll position goes
+    // too rather than passing to whatever slides into the tab's place.
+    if scrolled_tabs.contains(&index) {
+        scrolled_tabs.clear();
+    } else {
+        scrolled_tabs.iter_mut().for_each(|i| *i = shift(*i));
+    }
+    let position = active_tab_indices.iter().position(|&i| i == index);
+    active_tab_indices.retain(|&i| i != index);
+    active_tab_indices.iter_mut().for_each(|i| *i = shift(*i));
+    let previous = active_tab_indices.clone();
+    if active_tab_indices.is_empty() {
+        active_tab_indices.push(index.min(tabs.len() - 1));
+    }
+    *active_index = match position {
+        Some(position) if *active_index == index => {
+            active_tab_indices[position.min(active_tab_indices.len() - 1)]
+        }
+        _ => shift(*active_index),
+    };
+    begin_viewing_new_tabs(tabs, &previous, active_tab_indices);
+    mark_active_tabs_seen(tabs, active_tab_indices, paused, snapshot);
+    Ok(closed)
+}
+
+/// Freezes every tab at its current length and marks what is on screen seen.
+fn pause_view(tabs: &mut [Tab], active_tab_indices: &[usize], next_seq: u64) -> PauseSnapshot {
+    let snapshot = PauseSnapshot {
+        seq_cutoff: next_seq,
+        line_cutoffs: tabs.iter().map(|tab| tab.lines.len()).collect(),
+        match_cutoffs: tabs.iter().map(|tab| tab.total_matches).collect(),
+        paused_at: Instant::now(),
+    };
+    mark_tabs_seen_paused(tabs, active_tab_indices, &snapshot.match_cutoffs);
+    snapshot
+}
+
+fn regex_error_summary(err: &regex::Error) -> String {
+    // Parse errors span several lines (pattern, caret, message); keep the message.
+    let text = err.to_string();
+    let last = text.lines().last().unwrap_or_default().trim();
+    last.strip_prefix("error: ").unwrap_or(last).to_owned()
+}
+
+#[derive(Debug)]
+struct PauseSnapshot {
+    seq_cutoff: u64,
+    line_cutoffs: Vec<usize>,
+    match_cutoffs: Vec<u64>,
+    /// When the pause began, for the paused time in the tab bar.
+    paused_at: Instant,
+}
+
+impl PauseSnapshot {
+    /// Recomputes the cutoffs of a tab whose lines were rebuilt while paused.
+    fn refresh_tab(&mut self, index: usize, tab: &Tab) {
+        let after_pause = tab
+            .lines
+            .iter()
+            .filter(|line| line.seq >= self.seq_cutoff)
+            .count();
+        if let Some(cutoff) = self.line_cutoffs.get_mut(index) {
+            *cutoff = tab.lines.len() - after_pause;
+        }
+        if let Some(cutoff) = self.match_cutoffs.get_mut(index) {
+            *cutoff = tab.total_matches - after_pause as u64;
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct StatusMessage {
+    text: String,
+    color: Color,
+    expires_at: Instant,
+}
+
+impl StatusMessage {
+    fn info(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            color: Color::Grey,
+            expires_at: Instant::now() + STATUS_MESSAGE_DURATION,
+        }
+    }
+
+    fn lasting(text: impl Into<String>, duration: Duration) -> Self {
+        Self {
+            expires_at: Instant::now() + duration,
+            ..Self::info(text)
+        }
+    }
+
+    fn error(text: impl Into<String>) -> Self {
+        Self {
+            color: Color::Red,
+            ..Self::info(text)
+        }
+    }
+
+    fn success(text: impl Into<String>) -> Self {
+        Self {
+            color: Color::Green,
+            ..Self::info(text)
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct SelectedLine {
+    seq: u64,
+    text: String,
+}
+
+/// A horizontal click-drag within one body line, in screen columns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ColumnSelection {
+    seq: u64,
+    anchor: u16,
+    cursor: u16,
+}
+
+impl ColumnSelection {
+    fn columns(&self) -> (usize, usize) {
+        let start = self.anchor.min(self.cursor) as usize;
+        let end = self.anchor.max(self.cursor) as usize;
+        (start, end)
+    }
+}
+
+/// A row of the body as a frame lays it out. The text is borrowed from the
+/// tab that holds the line, so laying out a long buffer copies none of it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct RenderedLine<'a> {
+    seq: u64,
+    text: Cow<'a, str>,
+    selected: bool,
+    /// Shown only because it's the highlighted line: none of the tabs in view
+    /// hold it, so it's styled apart from lines that match them.
+    injected: bool,
+    /// Set on separator rows, which sit just above the line with `seq`.
+    separator: Option<Separator>,
+}
+
+impl RenderedLine<'_> {
+    /// A copy that outlives the frame, for the rows kept after drawing it.
+    fn into_owned(self) -> RenderedLine<'static> {
+        RenderedLine {
+            text: Cow::Owned(self.text.into_owned()),
+            ..self
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Separator {
+    /// A lull in arrivals this long.
+    Gap(Duration),
+    Marker(MarkerSlice),
+    /// This many lines dropped under `--on-overflow drop`.
+    Skipped(usize),
+}
+
+/// What the body draws left of each line's text; see `render_gutter`.
+#[derive(Debug, Clone, Copy, Default)]
+struct GutterOptions<'a> {
+    /// Width of the seq column, when shown: wide enough for the largest seq in view.
+    seq_width: Option<usize>,
+    /// Numbers lines by how far their seq is from this one instead, like
+    /// relative line numbers in an editor.
+    relative_to: Option<u64>,
+    /// Shows when each line arrived, as wall-clock time relative to this.
+    clock: Option<WallClock>,
+    /// Printed between the gutter and the text, when any gutter is shown.
+    separator: &'a str,
+}
+
+/// A moment on both clocks, which turns a line's monotonic arrival into the
+/// time of day it came in.
+#[derive(Debug, Clone, Copy)]
+struct WallClock {
+    instant: Instant,
+    system: SystemTime,
+}
+
+impl WallClock {
+    fn now() -> Self {
+        Self {
+            instant: Instant::now(),
+            system: SystemTime::now(),
+        }
+    }
+
+    fn at(self, instant: Instant) -> SystemTime {
+        self.system - self.instant.saturating_duration_since(instant)
+    }
+}
+
+/// `time` as (year, month, day, hour, minute, second) in the local zone, or
+/// in UTC where the local zone can't be looked up.
+fn civil_time(time: SystemTime) -> (i64, u32, u32, u32, u32, u32) {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs());
+    #[cfg(unix)]
+    {
+        let t = secs as libc::time_t;
+        let mut tm = unsafe { std::mem::zeroed::<libc::tm>() };
+        if !unsafe { libc::localtime_r(&t, &mut tm) }.is_null() {
+            return (
+                tm.tm_year as i64 + 1900,
+                tm.tm_mon as u32 + 1,
+                tm.tm_mday as u32,
+                tm.tm_hour as u32,
+                tm.tm_min as u32,
+                tm.tm_sec as u32,
+            );
+        }
+    }
+    // Days since the epoch to a date, after Howard Hinnant's `civil_from_days`.
+    let days = (secs / 86_400) as i64 + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u32;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    let in_day = (secs % 86_400) as u32;
+    (
+        year,
+        month,
+        day,
+        in_day / 3600,
+        in_day / 60 % 60,
+        in_day % 60,
+    )
+}
+
+/// `time` as `HH:MM:SS`; see `civil_time`.
+fn clock_label(time: SystemTime) -> String {
+    let (_, _, _, hour, minute, second) = civil_time(time);
+    format!("{:02}:{:02}:{:02}", hour, minute, second)
+}
+
+/// Columns of `clock_label`.
+const CLOCK_WIDTH: usize = 8;
+
+impl<'a> GutterOptions<'a> {
+    /// Sizes the enabled gutters for the lines about to be drawn.
+    fn for_lines(
+        display: DisplayOptions,
+        separator: &'a str,
+        lines: &[RenderedLine],
+        relative_to: Option<u64>,
+    ) -> Self {
+        let seq_width = display.seq_gutter.then(|| {
+            lines
+                .iter()
+                .map(|line| seq_label(line.seq, relative_to).len())
+                .max()
+                .unwrap_or(1)
+        });
+        Self {
+            seq_width,
+            relative_to,
+            clock: None,
+            separator,
+        }
+    }
+}
+
+/// `seq` as the gutter shows it: as is, or signed relative to `relative_to`.
+fn seq_label(seq: u64, relative_to: Option<u64>) -> String {
+    match relative_to {
+        None => seq.to_string(),
+        Some(origin) if seq == origin => "0".to_owned(),
+        Some(origin) if seq > origin => format!("+{}", seq - origin),
+        Some(origin) => format!("-{}", origin - seq),
+    }
+}
+
+/// Composes every enabled gutter for `line`, which arrived at `arrived` if
+/// known, and returns it with its width in columns, which the text's clip
+/// width gives up. Gutters are joined by a space and end with the separator;
+/// with none enabled this is empty.
+fn render_gutter(
+    line: &RenderedLine,
+    arrived: Option<Instant>,
+    options: GutterOptions,
+) -> (String, usize) {
+    let mut parts = Vec::new();
+    if let Some(clock) = options.clock {
+        parts.push(arrived.map_or_else(
+            || " ".repeat(CLOCK_WIDTH),
+            |arrived| clock_label(clock.at(arrived)),
+        ));
+    }
+    if let Some(width) = options.seq_width {
+        parts.push(format!(
+            "{:>width$}",
+            seq_label(line.seq, options.relative_to)
+        ));
+    }
+    if parts.is_empty() {
+        return (String::new(), 0);
+    }
+    let mut gutter = parts.join(" ");
+    gutter.push_str(options.separator);
+    let width = display_width(&gutter);
+    (gutter, width)
+}
+
+/// A line marked with `x`, which the view can be sliced at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Marker {
+    seq: u64,
+    slice: MarkerSlice,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MarkerSlice {
+    Off,
+    /// Only the marked line and newer ones.
+    After,
+    /// Only the marked line and older ones.
+    Before,
+}
+
+impl MarkerSlice {
+    fn next(self) -> Self {
+        match self {
+            MarkerSlice::Off => MarkerSlice::After,
+            MarkerSlice::After => MarkerSlice::Before,
+            MarkerSlice::Before => MarkerSlice::Off,
+        }
+    }
+
+    fn keeps(self, seq: u64, marker_seq: u64) -> bool {
+        match self {
+            MarkerSlice::Off => true,
+            MarkerSlice::After => seq >= marker_seq,
+            MarkerSlice::Before => seq <= marker_seq,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            MarkerSlice::Off => "marker",
+            MarkerSlice::After => "marker: showing this line and newer",
+            MarkerSlice::Before => "marker: showing this line and older",
+        }
+    }
+}
+
+/// Presentation choices fixed at startup.
+#[derive(Debug, Clone, Copy, Default)]
+struct DisplayOptions {
+    status_glyphs: bool,
+    badge_layout: BadgeLayout,
+    selection_style: SelectionStyle,
+    bar_rule: bool,
+    recenter_on_select: bool,
+    memory: bool,
+    tabs_bottom: bool,
+    tint: bool,
+    /// Shows each line's seq in the gutter.
+    seq_gutter: bool,
+    /// Brightens a tab's badge briefly when it gets a match.
+    flash_badges: bool,
+    /// Colors each capture group of the shown `re:` tabs.
+    color_groups: bool,
+    /// Set from the `NO_COLOR` convention (https://no-color.org).
+    no_color: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Frame<'a> {
+    /// Columns and rows of the screen drawn on.
+    size: (u16, u16),
+    display: DisplayOptions,
+    tabs: &'a [Tab],
+    history: &'a History,
+    active_tab_indices: &'a [usize],
+    paused: bool,
+    input_held: bool,
+    /// Set once the `--exec` command has exited and its output is read.
+    exit_status: Option<ExitStatus>,
+    pause_line_cutoffs: Option<&'a [usize]>,
+    /// How long the view has been paused, while it is.
+    paused_for: Duration,
+    selected_line: Option<&'a SelectedLine>,
+    column_selection: Option<&'a ColumnSelection>,
+    detail_pane: bool,
+    tab_preview: bool,
+    unread_only: bool,
+    status: Option<&'a StatusMessage>,
+    /// Text typed so far into the open search prompt.
+    search_prompt: Option<&'a str>,
+    palette: Option<&'a Palette>,
+    /// Cursor row of the overview, drawn instead of the body while open.
+    overview: Option<usize>,
+    /// Draws the active tab's distinct lines instead of the body while set.
+    frequency_view: Option<FrequencySort>,
+    marker: Option<Marker>,
+    /// Arrival lulls longer than this get a separator row, when shown.
+    gap_threshold: Option<Duration>,
+    /// Runs of lines dropped under `--on-overflow drop`, as the seq each ends
+    /// before and its length.
+    skipped_lines: &'a [(u64, usize)],
+    /// A line still waiting for its newline under `--flush-partial`, with the
+    /// seq it will get. `None` while paused.
+    partial_line: Option<(u64, &'a str)>,
+    scroll_top: Option<u64>,
+    /// Rows to scroll by from `scroll_top` (or the bottom) on this draw.
+    scroll_delta: isize,
+    previous_viewport: Option<ViewportAnchor>,
+    split: Option<SplitView>,
+    gutter_separator: &'a str,
+    /// From `--json-template`, applied to the body's JSON lines.
+    json_template: Option<&'a str>,
+    /// Numbers the seq gutter relative to the highlighted line.
+    relative_seq: bool,
+    /// Wraps long lines onto further rows instead of clipping them.
+    wrap: bool,
+    /// Puts each line's arrival time in the gutter.
+    clock: bool,
+    /// Reserves the bottom row for key hints.
+    help_bar: bool,
+    /// The first tab the bar draws, unless `follow_tab` moves it.
+    tab_scroll: usize,
+    /// A newly active tab the bar scrolls to bring into view.
+    follow_tab: Option<usize>,
+    /// Screen row under the mouse, tinted if it holds a body line.
+    hover_row: Option<u16>,
+    /// Lines pinned with a right click, tinted wherever they're shown.
+    pinned: &'a [SelectedLine],
+    /// Names of the files read, when several are, to tag their lines with.
+    sources: &'a [String],
+}
+
+#[derive(Debug, Clone, Copy)]
+struct TabHitbox {
+    index: usize,
+    left: u16,
+    right: u16,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct RenderState {
+    tab_hitboxes: Vec<TabHitbox>,
+    line_rows: Vec<Option<RenderedLine<'static>>>,
+    viewport: Option<ViewportAnchor>,
+    /// The line at the top of a scrolled-up body; `None` while at the bottom.
+    scroll_top: Option<u64>,
+    /// First of the tab bar's three rows.
+    bar_top: u16,
+    /// Screen rows of the overview and the tab each one opens.
+    overview_rows: Vec<(u16, usize)>,
+    /// The split divider's row; it and the rows below belong to the lower pane.
+    split_first_row: Option<u16>,
+    split_viewport: Option<ViewportAnchor>,
+    split_scroll_top: Option<u64>,
+    /// Columns taken by the gutter in the main and the lower pane.
+    gutter_width: u16,
+    split_gutter_width: u16,
+    /// The first tab the bar drew.
+    tab_scroll: usize,
+}
+
+impl RenderState {
+    /// The seq of the line drawn on screen row `row`, if one is.
+    pub fn seq_at_row(&self, row: u16) -> Option<u64> {
+        line_at_row(self, row).map(|line| line.seq)
+    }
+
+    /// The seqs of the lines on screen, top to bottom. A wrapped line is
+    /// listed once.
+    pub fn visible_seqs(&self) -> Vec<u64> {
+        let mut seqs: Vec<u64> = self
+            .line_rows
+            .iter()
+            .flatten()
+            .filter(|line| line.separator.is_none())
+            .map(|line| line.seq)
+            .collect();
+        seqs.dedup();
+        seqs
+    }
+
+    /// The tab whose title is at this screen position, if any.
+    pub fn tab_at(&self, column: u16, row: u16) -> Option<usize> {
+        tab_index_at_position(self, column, row)
+    }
+
+    /// Columns the gutter takes on `row`, which depends on its pane.
+    fn gutter_width_at(&self, row: u16) -> u16 {
+        let in_split = self
+            .split_first_row
+            .is_some_and(|first_row| row >= first_row);
+        if in_split {
+            self.split_gutter_width
+        } else {
+            self.gutter_width
+        }
+    }
+}
+
+/// The second tab stacked under the main view, with its own scrolling.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct SplitView {
+    tab: usize,
+    /// Scroll keys and tab picks go to the lower pane while set.
+    bottom_focused: bool,
+    scroll_top: Option<u64>,
+    scroll_delta: isize,
+    previous_viewport: Option<ViewportAnchor>,
+}
+
+impl SplitView {
+    fn new(tab: usize) -> Self {
+        Self {
+            tab,
+            bottom_focused: false,
+            scroll_top: None,
+            scroll_delta: 0,
+            previous_viewport: None,
+        }
+    }
+
+    /// Switches the lower pane to `tab`, back at the bottom.
+    fn show_tab(&mut self, tab: usize) {
+        self.tab = tab;
+        self.scroll_top = None;
+        self.scroll_delta = 0;
+        self.previous_viewport = None;
+    }
+}
+
+/// One stacked region of the body: the tabs it merges and its scrolling.
+#[derive(Debug, Clone, Copy)]
+struct BodyPane<'a> {
+    tab_indices: &'a [usize],
+    first_row: usize,
+    height: usize,
+    scroll_top: Option<u64>,
+    scroll_delta: isize,
+    previous_viewport: Option<ViewportAnchor>,
+}
+
+/// Where the body viewport was last drawn, so selecting an already visible
+/// line can leave it in place.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ViewportAnchor {
+    start_seq: u64,
+    first_row: usize,
+}
+
+#[derive(Debug)]
+enum InputParserState {
+    Ground,
+    Esc,
+    Csi(Vec<u8>),
+}
+
+#[derive(Debug)]
+struct InputParser {
+    state: InputParserState,
+    keys: KeyRemap,
+    /// Set while a prompt is open, so keys are typed instead of mapped.
+    text_entry: bool,
+    pending_utf8: Vec<u8>,
+}
+
+impl InputParser {
+    #[cfg(test)]
+    fn new() -> Self {
+        Self::with_keys(KeyRemap::default())
+    }
+
+    fn with_keys(keys: KeyRemap) -> Self {
+        Self {
+            state: InputParserState::Ground,
+            keys,
+            text_entry: false,
+            pending_utf8: Vec::new(),
+        }
+    }
+
+    fn feed(&mut self, byte: u8) -> Option<UiMessage> {
+        match &mut self.state {
+            InputParserState::Ground => {
+                if byte == 0x1b {
+                    self.state = InputParserState::Esc;
+                    return None;
+                }
+                if self.text_entry {
+                    return self.feed_text(byte);
+                }
+
+                self.key_message(byte)
+            }
+            InputParserState::Esc => {
+                if byte == b'[' {
+                    self.state = InputParserState::Csi(Vec::new());
+                } else {
+                    self.state = InputParserState::Ground;
+                }
+                None
+            }
+            InputParserState::Csi(buf) => {
+                buf.push(byte);
+                if !(0x40..=0x7e).contains(&byte) {
+                    return None;
+                }
+
+                let message = try_parse_sgr_mouse_message(buf).or_else(|| csi_key_message(buf));
+                self.state = InputParserState::Ground;
+                message
+            }
+        }
+    }
+
+    /// Maps a key pressed outside a prompt, opening one for the keys that do.
+    fn key_message(&mut self, byte: u8) -> Option<UiMessage> {
+        let message = key_message_from_byte(self.keys.resolve(byte));
+        if matches!(
+            message,
+            Some(UiMessage::StartSearch | UiMessage::OpenPalette)
+        ) {
+            self.text_entry = true;
+        }
+        message
+    }
+
+    /// Maps an event crossterm read from the console, where there is no byte
+    /// stream to feed: keys and mouse reports arrive already decoded.
+    #[cfg(any(not(unix), test))]
+    fn event_message(&mut self, event: &crossterm::event::Event) -> Option<UiMessage> {
+        use crossterm::event::{
+            Event, KeyCode, KeyEventKind, KeyModifiers, MouseButton, MouseEventKind,
+        };
+
+        let key = match event {
+            Event::Key(key) if key.kind != KeyEventKind::Release => key,
+            Event::Mouse(mouse) => {
+                let (column, row) = (mouse.column, mouse.row);
+                return match mouse.kind {
+                    MouseEventKind::ScrollUp => Some(UiMessage::Scroll(-WHEEL_SCROLL_ROWS)),
+                    MouseEventKind::ScrollDown => Some(UiMessage::Scroll(WHEEL_SCROLL_ROWS)),
+                    MouseEventKind::Moved => Some(UiMessage::MouseMove { row }),
+                    MouseEventKind::Down(MouseButton::Right) => {
+                        Some(UiMessage::MouseRightDown { row })
+                    }
+                    MouseEventKind::Down(MouseButton::Left) => Some(UiMessage::MouseLeftDown {
+                        column,
+                        row,
+                        shift: mouse.modifiers.contains(KeyModifiers::SHIFT),
+                    }),
+                    MouseEventKind::Drag(MouseButton::Left) => {
+                        Some(UiMessage::MouseLeftDrag { column })
+                    }
+                    MouseEventKind::Up(MouseButton::Left) => {
+                        Some(UiMessage::MouseLeftUp { column })
+                    }
+                    _ => None,
+                };
+            }
+            Event::FocusGained => return Some(UiMessage::FocusGained),
+            Event::FocusLost => return Some(UiMessage::FocusLost),
+            _ => return None,
+        };
+
+        let control = key.modifiers.contains(KeyModifiers::CONTROL);
+        if self.text_entry {
+            return match key.code {
+                KeyCode::Enter => {
+                    self.end_text_entry();
+                    Some(UiMessage::PromptSubmit)
+                }
+                KeyCode::Esc => {
+                    self.end_text_entry();
+                    Some(UiMessage::PromptCancel)
+                }
+                KeyCode::Backspace => Some(UiMessage::PromptBackspace),
+                KeyCode::Char('c') if control => Some(UiMessage::Interrupt),
+                KeyCode::Char('l') if control => Some(UiMessage::Redraw),
+                KeyCode::Char(ch) if !control => Some(UiMessage::PromptInput(ch)),
+                _ => None,
+            };
+        }
+        match key.code {
+            KeyCode::Esc => Some(UiMessage::Escape),
+            KeyCode::Up => Some(UiMessage::Scroll(-1)),
+            KeyCode::Down => Some(UiMessage::Scroll(1)),
+            KeyCode::PageUp => Some(UiMessage::ScrollPage(-1)),
+            KeyCode::PageDown => Some(UiMessage::ScrollPage(1)),
+            KeyCode::BackTab => Some(UiMessage::PrevTab),
+            KeyCode::Tab => self.key_message(b'\t'),
+            KeyCode::Enter => self.key_message(b'\r'),
+            KeyCode::Char('c') if control => Some(UiMessage::Interrupt),
+            KeyCode::Char('l') if control => Some(UiMessage::Redraw),
+            KeyCode::Char(ch) if ch.is_ascii() && !control => self.key_message(ch as u8),
+            _ => None,
+        }
+    }
+
+    fn feed_text(&mut self, byte: u8) -> Option<UiMessage> {
+        match byte {
+            b'\r' | b'\n' => {
+                self.end_text_entry();
+                Some(UiMessage::PromptSubmit)
+            }
+            0x7f | 0x08 => Some(UiMessage::PromptBackspace),
+            // The prompt stays open: a Ctrl+C waiting to be confirmed may
+            // never be, and then typing carries on where it was.
+            0x03 => Some(UiMessage::Interrupt),
+            0x0c => Some(UiMessage::Redraw),
+            0x00..=0x1f => None,
+            _ => {
+                self.pending_utf8.push(byte);
+                match std::str::from_utf8(&self.pending_utf8) {
+                    Ok(text) => {
+                        let ch = text.chars().next();
+                        self.pending_utf8.clear();
+                        ch.map(UiMessage::PromptInput)
+                    }
+                    Err(err) => {
+                        if err.error_len().is_some() {
+                            self.pending_utf8.clear();
+                        }
+                        None
+                    }
+                }
+            }
+        }
+    }
+
+    fn end_text_entry(&mut self) {
+        self.text_entry = false;
+        self.pending_utf8.clear();
+    }
+
+    /// Called once a read is fully fed: an Escape with nothing after it in the
+    /// same read is the Escape key rather than the start of a sequence.
+    fn finish_read(&mut self) -> Option<UiMessage> {
+        if !matches!(self.state, InputParserState::Esc) {
+            return None;
+        }
+
+        self.state = InputParserState::Ground;
+        if self.text_entry {
+            self.end_text_entry();
+            Some(UiMessage::PromptCancel)
+        } else {
+            Some(UiMessage::Escape)
+        }
+    }
+}
+
+struct TerminalGuard {
+    alternate_screen: bool,
+    mouse: bool,
+    focus_events: bool,
+}
+
+impl TerminalGuard {
+    fn enter(
+        stdout: &mut Stdout,
+        alternate_screen: bool,
+        mouse: bool,
+        focus_events: bool,
+    ) -> io::Result<Self> {
+        let guard = Self {
+            alternate_screen,
+            mouse,
+            focus_events,
+        };
+        guard.resume(stdout)?;
+        Ok(guard)
+    }
+
+    /// Sets the terminal up for the UI, at startup and after `suspend`.
+    fn resume(&self, stdout: &mut Stdout) -> io::Result<()> {
+        terminal::enable_raw_mode()?;
+        if self.alternate_screen {
+            execute!(stdout, EnterAlternateScreen)?;
+        }
+        execute!(stdout, Hide)?;
+        if self.mouse {
+            execute!(stdout, EnableMouseCapture)?;
+        }
+        if self.focus_events {
+            execute!(stdout, EnableFocusChange)?;
+        }
+        Ok(())
+    }
+
+    /// Hands the terminal back as it was found, for a pager or on exit.
+    fn suspend(&self) {
+        let _ = terminal::disable_raw_mode();
+        let mut stdout = io::stdout();
+        if self.focus_events {
+            let _ = execute!(stdout, DisableFocusChange);
+        }
+        if self.mouse {
+            let _ = execute!(stdout, DisableMouseCapture);
+        }
+        let _ = execute!(stdout, Show);
+        if self.alternate_screen {
+            let _ = execute!(stdout, LeaveAlternateScreen);
+        } else {
+            // Leave the last frame in place and put the shell prompt below it.
+            let rows = terminal::size().map(|(_, rows)| rows).unwrap_or(0);
+            let _ = execute!(
+                stdout,
+                ResetColor,
+                MoveTo(0, rows.saturating_sub(1)),
+                Print("\r\n")
+            );
+        }
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        self.suspend();
+    }
+}
+
+/// Shows `text` in `$PAGER` (or `less`) with the UI suspended and comes back
+/// once the pager exits. Key reading stops first so the pager gets every
+/// key. The outer error is losing the terminal; the inner one is for the
+/// status row.
+fn page_text(
+    stdout: &mut Stdout,
+    guard: &TerminalGuard,
+    keys: &KeyHandoff,
+    text: &str,
+) -> io::Result<Result<(), String>> {
+    keys.pause();
+    guard.suspend();
+    let pager = std::env::var("PAGER")
+        .ok()
+        .filter(|pager| !pager.trim().is_empty())
+        .unwrap_or_else(|| "less".to_owned());
+    let outcome = run_pager(&pager, text);
+    let resumed = guard.resume(stdout);
+    keys.resume();
+    resumed.map(|()| outcome)
+}
+
+/// Runs the shell command `pager` with `text` on its stdin, and waits for it.
+fn run_pager(pager: &str, text: &str) -> Result<(), String> {
+    let mut command = Command::new("sh");
+    command.arg("-c").arg(pager).stdin(Stdio::piped());
+    if std::env::var_os("LESS").is_none() {
+        // Like git: keep the lines' colors instead of showing raw escapes.
+        command.env("LESS", "R");
+    }
+    let _sigint = IgnoreInterrupts::new(&mut command);
+    let mut child = command
+        .spawn()
+        .map_err(|err| format!("couldn't start the pager: {}", err))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // Quitting the pager before the end closes the pipe early.
+        let _ = stdin.write_all(text.as_bytes());
+    }
+    let status = child.wait().map_err(|err| err.to_string())?;
+    match status.code() {
+        Some(127) => Err(format!("pager not found: {} (set $PAGER)", pager)),
+        _ => Ok(()),
+    }
+}
+
+/// Keeps Ctrl+C in the pager, whose terminal is no longer raw, from killing
+/// us; the pager itself gets the default handling back.
+#[cfg(unix)]
+struct IgnoreInterrupts {
+    previous: libc::sighandler_t,
+}
+
+#[cfg(unix)]
+impl IgnoreInterrupts {
+    fn new(command: &mut Command) -> Self {
+        use std::os::unix::process::CommandExt;
+
+        // SAFETY: only async-signal-safe calls run between fork and exec.
+        unsafe {
+            command.pre_exec(|| {
+                libc::signal(libc::SIGINT, libc::SIG_DFL);
+                Ok(())
+            });
+            Self {
+                previous: libc::signal(libc::SIGINT, libc::SIG_IGN),
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+impl Drop for IgnoreInterrupts {
+    fn drop(&mut self) {
+        unsafe {
+            libc::signal(libc::SIGINT, self.previous);
+        }
+    }
+}
+
+#[cfg(not(unix))]
+struct IgnoreInterrupts;
+
+#[cfg(not(unix))]
+impl IgnoreInterrupts {
+    fn new(_command: &mut Command) -> Self {
+        Self
+    }
+}
+
+/// `--serve` and `--attach`: one instance reads the input and shares it over
+/// a Unix socket, and attached instances take their lines from there.
+///
+/// The server greets each client with `streamtabs-session 1`, a `filter
+/// <text>` line per startup filter and `begin`, then sends its history
+/// followed by every new line. Clients never write back.
+#[cfg(unix)]
+mod session {
+    use std::io::{self, BufRead, BufReader, BufWriter, Write};
+    use std::os::unix::net::{UnixListener, UnixStream};
+    use std::path::{Path, PathBuf};
+    use std::sync::mpsc::{self, Receiver, SyncSender};
+    use std::thread;
+
+    use super::{History, tagged_text};
+
+    const GREETING: &str = "streamtabs-session 1";
+    const BEGIN: &str = "begin";
+    const FILTER_PREFIX: &str = "filter ";
+    /// Lines queued for one client before it counts as stuck and is dropped.
+    const CLIENT_QUEUE_LEN: usize = 4096;
+
+    pub struct Server {
+        path: PathBuf,
+        filters: Vec<String>,
+        pending: Receiver<UnixStream>,
+        clients: Vec<SyncSender<String>>,
+    }
+
+    impl Server {
+        /// Listens on `path`, replacing a socket file left behind by a
+        /// server that is no longer running.
+        pub fn bind(path: &Path, filters: Vec<String>) -> io::Result<Self> {
+            if path.exists() {
+                if UnixStream::connect(path).is_ok() {
+                    return Err(io::Error::new(
+                        io::ErrorKind::AddrInUse,
+                        format!("{} is already being served", path.display()),
+                    ));
+                }
+                std::fs::remove_file(path).map_err(|err| {
+                    io::Error::new(err.kind(), format!("{}: {}", path.display(), err))
+                })?;
+            }
+            let listener = UnixListener::bind(path).map_err(|err| {
+                io::Error::new(err.kind(), format!("{}: {}", path.display(), err))
+            })?;
+            let (tx, pending) = mpsc::channel();
+            thread::spawn(move || {
+                for stream in listener.incoming().flatten() {
+                    if tx.send(stream).is_err() {
+                        break;
+                    }
+                }
+            });
+            Ok(Self {
+                path: path.to_owned(),
+                filters,
+                pending,
+                clients: Vec::new(),
+            })
+        }
+
+        /// Greets clients that connected since the last call and catches
+        /// them up on `history`.
+        pub fn admit(&mut self, history: &History, sources: &[String]) {
+            while let Ok(stream) = self.pending.try_recv() {
+                let mut preamble = Vec::with_capacity(self.filters.len() + history.lines.len() + 2);
+                preamble.push(GREETING.to_owned());
+                preamble.extend(
+                    self.filters
+                        .iter()
+                        .filter(|filter| !filter.contains('\n'))
+                        .map(|filter| format!("{}{}", FILTER_PREFIX, filter)),
+                );
+                preamble.push(BEGIN.to_owned());
+                preamble.extend(
+                    history
+                        .lines
+                        .iter()
+                        .map(|line| tagged_text(&line.text, line.source, sources).into_owned()),
+                );
+                let (tx, rx) = mpsc::sync_channel(CLIENT_QUEUE_LEN);
+                spawn_writer(stream, preamble, rx);
+                self.clients.push(tx);
+            }
+        }
+
+        /// Queues `line` for every client, dropping those that have
+        /// disconnected or fallen too far behind.
+        pub fn broadcast(&mut self, line: &str) {
+            self.clients
+                .retain(|client| client.try_send(line.to_owned()).is_ok());
+        }
+
+        #[cfg(test)]
+        pub fn client_count(&self) -> usize {
+            self.clients.len()
+        }
+    }
+
+    impl Drop for Server {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+
+    fn spawn_writer(stream: UnixStream, preamble: Vec<String>, rx: Receiver<String>) {
+        thread::spawn(move || {
+            let mut out = BufWriter::new(stream);
+            for line in &preamble {
+                if writeln!(out, "{}", line).is_err() {
+                    return;
+                }
+            }
+            if out.flush().is_err() {
+                return;
+            }
+            while let Ok(first) = rx.recv() {
+                let mut next = Some(first);
+                while let Some(line) = next {
+                    if writeln!(out, "{}", line).is_err() {
+                        return;
+                    }
+                    next = rx.try_recv().ok();
+                }
+                // Flushed once caught up, so a quiet stream still arrives promptly.
+                if out.flush().is_err() {
+                    return;
+                }
+            }
+        });
+    }
+
+    /// Connects to a `--serve` instance and reads its greeting, returning the
+    /// server's filters and the stream carrying its lines.
+    pub fn attach(path: &Path) -> io::Result<(Vec<String>, BufReader<UnixStream>)> {
+        let stream = UnixStream::connect(path).map_err(|err| {
+            io::Error::new(
+                err.kind(),
+                format!("couldn't attach to {}: {}", path.display(), err),
+            )
+        })?;
+        let mut reader = BufReader::new(stream);
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        if line.trim_end_matches('\n') != GREETING {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{} is not a streamtabs session", path.display()),
+            ));
+        }
+        let mut filters = Vec::new();
+        loop {
+            line.clear();
+            if reader.read_line(&mut line)? == 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    format!("{} closed before the session began", path.display()),
+                ));
+            }
+            let line = line.trim_end_matches('\n');
+            if line == BEGIN {
+                break;
+            }
+            if let Some(filter) = line.strip_prefix(FILTER_PREFIX) {
+                filters.push(filter.to_owned());
+            }
+        }
+        Ok((filters, reader))
+    }
+}
+
+#[cfg(feature = "jsonl")]
+mod jsonl_export {
+    use std::fs::File;
+    use std::io::{self, BufWriter, Write};
+    use std::path::Path;
+    use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, SyncSender};
+    use std::thread::{self, JoinHandle};
+    use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+    use super::{UiMessage, strip_ansi};
+
+    /// Longest a written record may sit in the buffer before reaching the file.
+    const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+    #[derive(Debug)]
+    struct Record {
+        seq: u64,
+        tab: String,
+        timestamp_ms: u64,
+        text: String,
+    }
+
+    /// Handed to each filter tab so its matches reach the writer thread.
+    #[derive(Debug, Clone)]
+    pub struct JsonlExport {
+        tx: Sender<Record>,
+    }
+
+    impl JsonlExport {
+        pub fn record(&self, seq: u64, tab: &str, line: &str) {
+            let timestamp_ms = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_millis() as u64);
+            let _ = self.tx.send(Record {
+                seq,
+                tab: tab.to_owned(),
+                timestamp_ms,
+                text: strip_ansi(line),
+            });
+        }
+    }
+
+    /// Owns the thread writing `--jsonl-out`, so the reader never waits on disk.
+    pub struct JsonlWriter {
+        handle: JoinHandle<()>,
+    }
+
+    impl JsonlWriter {
+        pub fn spawn(path: &Path, ui_tx: SyncSender<UiMessage>) -> io::Result<(Self, JsonlExport)> {
+            let file = File::create(path).map_err(|err| {
+                io::Error::new(err.kind(), format!("{}: {}", path.display(), err))
+            })?;
+            let (tx, rx) = mpsc::channel();
+            let name = path.display().to_string();
+            let handle = thread::spawn(move || {
+                if let Err(err) = write_records(BufWriter::new(file), &rx) {
+                    let _ = ui_tx.try_send(UiMessage::ExportFailed(format!(
+                        "--jsonl-out {}: {}; export stopped",
+                        name, err
+                    )));
+                }
+            });
+            Ok((Self { handle }, JsonlExport { tx }))
+        }
+
+        /// Waits for queued records to be written and flushed. Every
+        /// `JsonlExport` must be dropped first, or this never returns.
+        pub fn finish(self) {
+            let _ = self.handle.join();
+        }
+    }
+
+    fn write_records(mut out: impl Write, rx: &Receiver<Record>) -> io::Result<()> {
+        let mut last_flush = Instant::now();
+        loop {
+            match rx.recv_timeout(FLUSH_INTERVAL) {
+                Ok(record) => {
+                    let json = serde_json::json!({
+                        "seq": record.seq,
+                        "tab": record.tab,
+                        "timestamp": record.timestamp_ms,
+                        "text": record.text,
+                    });
+                    serde_json::to_writer(&mut out, &json)?;
+                    out.write_all(b"\n")?;
+                    if last_flush.elapsed() < FLUSH_INTERVAL {
+                        continue;
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => return out.flush(),
+            }
+            out.flush()?;
+            last_flush = Instant::now();
+        }
+    }
+}
+
+#[cfg(feature = "json")]
+mod json_input {
+    use serde_json::Value;
+
+    fn parse(line: &str) -> Option<Value> {
+        serde_json::from_str::<Value>(line.trim())
+            .ok()
+            .filter(Value::is_object)
+    }
+
+    /// The field at a dotted `path`, as filters and templates see it: strings
+    /// without their quotes, anything else as JSON.
+    fn field(object: &Value, path: &str) -> Option<String> {
+        let value = path
+            .split('.')
+            .try_fold(object, |value, key| value.get(key))?;
+        Some(match value {
+            Value::String(text) => text.clone(),
+            other => other.to_string(),
+        })
+    }
+
+    /// Whether the field at `path` equals `expected`; `None` when the line
+    /// isn't a JSON object.
+    pub fn field_equals(line: &str, path: &str, expected: &str) -> Option<bool> {
+        let object = parse(line)?;
+        Some(field(&object, path).is_some_and(|found| found == expected))
+    }
+
+    /// `template` with each `{path}` replaced by that field, left empty where
+    /// the object has none; `None` when the line isn't a JSON object. `{{` and
+    /// `}}` stand for braces, as does a `{` that is never closed.
+    pub fn render(line: &str, template: &str) -> Option<String> {
+        let object = parse(line)?;
+        let mut out = String::with_capacity(template.len());
+        let mut rest = template;
+        while let Some(open) = rest.find(['{', '}']) {
+            out.push_str(&rest[..open]);
+            let brace = rest.as_bytes()[open];
+            rest = &rest[open + 1..];
+            if let Some(after) = rest.strip_prefix(brace as char) {
+                out.push(brace as char);
+                rest = after;
+            } else if brace == b'{'
+                && let Some(close) = rest.find('}')
+            {
+                out.push_str(&field(&object, &rest[..close]).unwrap_or_default());
+                rest = &rest[close + 1..];
+            } else {
+                out.push(brace as char);
+            }
+        }
+        out.push_str(rest);
+        Some(out)
+    }
+}
+
+#[cfg(feature = "time-filter")]
+mod time_window {
+    use chrono::{DateTime, Local, NaiveDateTime, TimeDelta, TimeZone, Utc};
+    use regex::Regex;
+
+    /// ISO-8601-like stamps: `2026-02-06T12:34:56`, optional fraction and offset.
+    pub const DEFAULT_TIMESTAMP_PATTERN: &str =
+        r"\d{4}-\d{2}-\d{2}[T ]\d{2}:\d{2}:\d{2}(?:[.,]\d+)?(?:Z|[+-]\d{2}:?\d{2})?";
+
+    #[derive(Debug, Clone)]
+    pub struct TimeWindow {
+        pub since: Option<DateTime<Utc>>,
+        pub until: Option<DateTime<Utc>>,
+        pub timestamp_regex: Regex,
+        pub keep_untimestamped: bool,
+    }
+
+    impl TimeWindow {
+        pub fn admits(&self, line: &str) -> bool {
+            let Some(timestamp) = self.line_timestamp(line) else {
+                return self.keep_untimestamped;
+            };
+
+            self.since.is_none_or(|since| timestamp >= since)
+                && self.until.is_none_or(|until| timestamp <= until)
+        }
+
+        /// Uses the first capture group if the regex has one, otherwise the whole match.
+        fn line_timestamp(&self, line: &str) -> Option<DateTime<Utc>> {
+            let captures = self.timestamp_regex.captures(line)?;
+            let text = captures.get(1).or_else(|| captures.get(0))?.as_str();
+            parse_absolute(text)
+        }
+    }
+
+    /// Stamps without an offset are read as local time.
+    pub fn parse_absolute(text: &str) -> Option<DateTime<Utc>> {
+        let text = text.trim().replace(',', ".");
+        if let Ok(parsed) = DateTime::parse_from_rfc3339(&text) {
+            return Some(parsed.with_timezone(&Utc));
+        }
+
+        for format in [
+            "%Y-%m-%d %H:%M:%S%.f%:z",
+            "%Y-%m-%dT%H:%M:%S%.f%z",
+            "%Y-%m-%d %H:%M:%S%.f%z",
+        ] {
+            if let Ok(parsed) = DateTime::parse_from_str(&text, format) {
+                return Some(parsed.with_timezone(&Utc));
+            }
+        }
+
+        for format in ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S%.f"] {
+            if let Ok(naive) = NaiveDateTime::parse_from_str(&text, format) {
+                return Local
+                    .from_local_datetime(&naive)
+                    .earliest()
+                    .map(|local| local.with_timezone(&Utc));
+            }
+        }
+
+        None
+    }
+
+    /// Accepts an absolute timestamp or an offset into the past like `15m` / `-2h`.
+    pub fn parse_bound(text: &str, now: DateTime<Utc>) -> Result<DateTime<Utc>, String> {
+        if let Some(absolute) = parse_absolute(text) {
+            return Ok(absolute);
+        }
+
+        parse_relative(text)
+            .and_then(|offset| now.checked_sub_signed(offset))
+            .ok_or_else(|| {
+                format!(
+                    "invalid time: {} (use e.g. 2026-02-06T12:00:00 or 15m)",
+                    text
+                )
+            })
+    }
+
+    fn parse_relative(text: &str) -> Option<TimeDelta> {
+        let text = text.strip_prefix('-').unwrap_or(text);
+        let unit = text.chars().last()?;
+        let value = text[..text.len() - unit.len_utf8()].parse::<i64>().ok()?;
+        match unit {
+            's' => TimeDelta::try_seconds(value),
+            'm' => TimeDelta::try_minutes(value),
+            'h' => TimeDelta::try_hours(value),
+            'd' => TimeDelta::try_days(value),
+            _ => None,
+        }
+    }
+}
+
+/// A normalization from `--transform`, applied to each line as it arrives.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Transform {
+    /// Drops trailing whitespace.
+    Trim,
+    /// Replaces each tab with spaces up to the next multiple of this many
+    /// columns (`TAB_WIDTH` unless `--tabstop` says otherwise).
+    ExpandTabs(usize),
+    /// Matches tabs against the lowercased line, while storing and showing it
+    /// as it came; see `match_text`.
+    Lowercase,
+}
+
+const TAB_WIDTH: usize = 8;
+
+impl Transform {
+    fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "trim" => Ok(Self::Trim),
+            "expand-tabs" => Ok(Self::ExpandTabs(TAB_WIDTH)),
+            "lowercase" => Ok(Self::Lowercase),
+            _ => Err(format!(
+                "unknown --transform {name:?} (expected trim, expand-tabs or lowercase)"
+            )),
+        }
+    }
+
+    /// The stored line after this transform. `Lowercase` leaves it alone.
+    fn apply(self, line: String) -> String {
+        match self {
+            Self::Trim => {
+                let kept = line.trim_end().len();
+                let mut line = line;
+                line.truncate(kept);
+                line
+            }
+            Self::ExpandTabs(width) if line.contains('\t') => expand_tabs(&line, width),
+            Self::ExpandTabs(_) | Self::Lowercase => line,
+        }
+    }
+}
+
+/// Color codes take no columns, so tab stops are counted over visible text.
+fn expand_tabs(line: &str, tab_width: usize) -> String {
+    let mut expanded = String::with_capacity(line.len());
+    let mut column = 0;
+    for piece in ansi_pieces(line) {
+        match piece {
+            AnsiPiece::Escape(sequence) => expanded.push_str(sequence),
+            AnsiPiece::Char('\t') => {
+                let spaces = tab_width - column % tab_width;
+                expanded.extend(std::iter::repeat_n(' ', spaces));
+                column += spaces;
+            }
+            AnsiPiece::Char(ch) => {
+                expanded.push(ch);
+                column += char_display_width(ch);
+            }
+        }
+    }
+    expanded
+}
+
+/// What tabs match a line against: the line itself, or lowercased under
+/// `--transform lowercase`.
+fn match_text(line: &str, fold_case: bool) -> Cow<'_, str> {
+    if fold_case {
+        Cow::Owned(line.to_lowercase())
+    } else {
+        Cow::Borrowed(line)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct CaptureOptions {
+    strip_cursor_codes: bool,
+    strip_cr: bool,
+    /// Sends lines still waiting for their newline, from `--flush-partial`.
+    flush_partial: bool,
+    /// From `--transform`, in the order given.
+    transforms: Vec<Transform>,
+    #[cfg(feature = "time-filter")]
+    time_window: Option<time_window::TimeWindow>,
+}
+
+impl CaptureOptions {
+    /// Normalizes a captured line, or returns `None` if it should be skipped.
+    fn apply(&self, line: String) -> Option<String> {
+        let line = if self.strip_cursor_codes {
+            strip_non_sgr_csi(&line)
+        } else {
+            line
+        };
+        let line = if self.strip_cr && line.contains('\r') {
+            line.replace('\r', "")
+        } else {
+            line
+        };
+        let line = if line.contains('\r') {
+            collapse_carriage_returns(&line)
+        } else {
+            line
+        };
+        let line = self
+            .transforms
+            .iter()
+            .fold(line, |line, transform| transform.apply(line));
+
+        #[cfg(feature = "time-filter")]
+        if let Some(window) = &self.time_window
+            && !window.admits(&strip_ansi(&line))
+        {
+            return None;
+        }
+
+        Some(line)
+    }
+}
+
+/// How `--file` input is decoded; `Auto` picks by file extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum Decompress {
+    #[default]
+    Auto,
+    Plain,
+    Gzip,
+    Zstd,
+}
+
+impl Decompress {
+    fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "auto" => Ok(Decompress::Auto),
+            "none" => Ok(Decompress::Plain),
+            "gzip" => Ok(Decompress::Gzip),
+            "zstd" => Ok(Decompress::Zstd),
+            _ => Err(format!(
+                "invalid --decompress: {} (expected auto, none, gzip or zstd)",
+                value
+            )),
+        }
+    }
+
+    fn resolve(self, path: &Path) -> Self {
+        if self != Decompress::Auto {
+            return self;
+        }
+
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("gz") => Decompress::Gzip,
+            Some("zst") => Decompress::Zstd,
+            _ => Decompress::Plain,
+        }
+    }
+}
+
+#[cfg(not(all(feature = "gzip", feature = "zstd")))]
+fn missing_decoder(path: &Path, feature: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!(
+            "{} looks compressed; rebuild with `--features {}` or pass --decompress none",
+            path.display(),
+            feature
+        ),
+    )
+}
+
+/// A file that, where it ends, waits for more to be appended instead, as
+/// `tail -f` does. One that shrinks was truncated, and is read again from
+/// the start.
+struct FollowedFile {
+    file: File,
+    position: u64,
+}
+
+impl Read for FollowedFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let read = self.file.read(buf)?;
+            if read > 0 || buf.is_empty() {
+                self.position += read as u64;
+                return Ok(read);
+            }
+            if self.file.metadata()?.len() < self.position {
+                self.position = self.file.seek(SeekFrom::Start(0))?;
+                continue;
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+}
+
+fn open_input_file(
+    path: &Path,
+    decompress: Decompress,
+    follow: bool,
+) -> io::Result<Box<dyn BufRead + Send>> {
+    let file = File::open(path)
+        .map_err(|err| io::Error::new(err.kind(), format!("{}: {}", path.display(), err)))?;
+
+    match decompress.resolve(path) {
+        Decompress::Auto | Decompress::Plain if follow => {
+            Ok(Box::new(BufReader::new(FollowedFile { file, position: 0 })))
+        }
+        Decompress::Auto | Decompress::Plain => Ok(Box::new(BufReader::new(file))),
+        _ if follow => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!(
+                "{} is compressed, so it can't be followed; drop --follow or pass --decompress none",
+                path.display()
+            ),
+        )),
+        #[cfg(feature = "gzip")]
+        Decompress::Gzip => Ok(Box::new(BufReader::new(flate2::read::MultiGzDecoder::new(
+            file,
+        )))),
+        #[cfg(not(feature = "gzip"))]
+        Decompress::Gzip => Err(missing_decoder(path, "gzip")),
+        #[cfg(feature = "zstd")]
+        Decompress::Zstd => Ok(Box::new(BufReader::new(zstd::stream::read::Decoder::new(
+            file,
+        )?))),
+        #[cfg(not(feature = "zstd"))]
+        Decompress::Zstd => Err(missing_decoder(path, "zstd")),
+    }
+}
+
+/// What the input reader does with a line while the UI is too far behind
+/// to take it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum Overflow {
+    /// Waits for room, stalling the input.
+    #[default]
+    Block,
+    /// Drops the line and counts it, so the UI can mark where lines are missing.
+    Drop,
+}
+
+impl Overflow {
+    fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "block" => Ok(Overflow::Block),
+            "drop" => Ok(Overflow::Drop),
+            _ => Err(format!(
+                "invalid --on-overflow: {} (expected block or drop)",
+                value
+            )),
+        }
+    }
+}
+
+enum InputSource {
+    Stdin,
+    Reader(Box<dyn BufRead + Send>),
+    /// Several `--file`s read side by side, each line sent with the index
+    /// of its file.
+    Tagged(Vec<Box<dyn BufRead + Send>>),
+}
+
+/// Colors cycled through for the files' source tags, as SGR codes.
+const SOURCE_TAG_COLORS: [u8; 6] = [36, 33, 35, 32, 34, 31];
+
+/// The color of the `index`th file's tag.
+fn source_tag_color(index: usize) -> Color {
+    Color::AnsiValue(SOURCE_TAG_COLORS[index % SOURCE_TAG_COLORS.len()] - 30)
+}
+
+/// The tag the `index`th of several files' lines are sent to `--attach`
+/// clients with: its name, padded to `width` and colored.
+fn source_tag(label: &str, index: usize, width: usize) -> String {
+    let color = SOURCE_TAG_COLORS[index % SOURCE_TAG_COLORS.len()];
+    format!("\x1b[{}m{:<width$}\x1b[39m ", color, label, width = width)
+}
+
+/// `text` led by the tag of the file it came from, for `--attach` clients,
+/// whose lines carry nothing else to tell the files apart.
+fn tagged_text<'a>(text: &'a str, source: Option<usize>, sources: &[String]) -> Cow<'a, str> {
+    let Some(label) = source.and_then(|source| sources.get(source)) else {
+        return Cow::Borrowed(text);
+    };
+    let width = source_tags_width(sources);
+    let index = source.unwrap_or(0);
+    Cow::Owned(format!("{}{}", source_tag(label, index, width), text))
+}
+
+/// Columns of the widest file name among the sources.
+fn source_tags_width(sources: &[String]) -> usize {
+    sources
+        .iter()
+        .map(|label| display_width(label))
+        .max()
+        .unwrap_or(0)
+}
+
+/// Labels for several input files: their names, or the paths as given when
+/// two files share a name.
+fn source_labels(paths: &[PathBuf]) -> Vec<String> {
+    let names = paths
+        .iter()
+        .map(|path| {
+            path.file_name().map_or_else(
+                || path.display().to_string(),
+                |name| name.to_string_lossy().into_owned(),
+            )
+        })
+        .collect::<Vec<_>>();
+    let unique = names
+        .iter()
+        .enumerate()
+        .all(|(index, name)| !names[..index].contains(name));
+    if unique {
+        names
+    } else {
+        paths
+            .iter()
+            .map(|path| path.display().to_string())
+            .collect()
+    }
+}
+
+/// Starts an `--exec` command with its stdout and stderr on one pipe, so
+/// both become the input in the order they are written.
+fn spawn_command(command: &str) -> io::Result<(Child, io::PipeReader)> {
+    let (reader, writer) = io::pipe()?;
+    let child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(Stdio::null())
+        .stdout(writer.try_clone()?)
+        .stderr(writer)
+        .spawn()
+        .map_err(|err| io::Error::new(err.kind(), format!("couldn't run --exec: {}", err)))?;
+    Ok((child, reader))
+}
+
+fn spawn_input_reader(
+    tx: SyncSender<InputMessage>,
+    capture: CaptureOptions,
+    overflow: Overflow,
+    source: InputSource,
+) {
+    match source {
+        // Stdin's lock isn't `Send`, so it is taken on the reader thread.
+        InputSource::Stdin => {
+            thread::spawn(move || read_input_lines(io::stdin().lock(), &tx, &capture, overflow));
+        }
+        InputSource::Reader(reader) => {
+            thread::spawn(move || read_input_lines(reader, &tx, &capture, overflow));
+        }
+        InputSource::Tagged(readers) => {
+            // The input closes once the last of the files does.
+            let open = Arc::new(AtomicUsize::new(readers.len()));
+            for (index, reader) in readers.into_iter().enumerate() {
+                let (tx, capture, open) = (tx.clone(), capture.clone(), Arc::clone(&open));
+                thread::spawn(move || {
+                    if forward_input_lines(reader, &tx, &capture, overflow, Some(index))
+                        && open.fetch_sub(1, Ordering::SeqCst) == 1
+                    {
+                        let _ = tx.send(InputMessage::Closed);
+                    }
+                });
+            }
+        }
+    }
+}
+
+fn read_input_lines(
+    reader: impl BufRead,
+    tx: &SyncSender<InputMessage>,
+    capture: &CaptureOptions,
+    overflow: Overflow,
+) {
+    if forward_input_lines(reader, tx, capture, overflow, None) {
+        let _ = tx.send(InputMessage::Closed);
+    }
+}
+
+/// Sends each line, from `source` if one of several files, until the input
+/// ends; false if it stopped early on an error or a closed channel.
+fn forward_input_lines(
+    mut reader: impl BufRead,
+    tx: &SyncSender<InputMessage>,
+    capture: &CaptureOptions,
+    overflow: Overflow,
+    source: Option<usize>,
+) -> bool {
+    if capture.flush_partial {
+        return forward_partial_lines(reader, tx, capture, overflow, source);
+    }
+
+    let mut buf = String::new();
+    let mut skipped = 0;
+
+    loop {
+        buf.clear();
+        match reader.read_line(&mut buf) {
+            Ok(0) => return skipped == 0 || tx.send(InputMessage::Skipped(skipped)).is_ok(),
+            Ok(_) => {
+                let Some(line) = prepare_input_line(buf.clone(), capture) else {
+                    continue;
+                };
+                if !send_input_line(tx, line, source, overflow, &mut skipped) {
+                    return false;
+                }
+            }
+            Err(err) => {
+                let _ = tx.send(InputMessage::Error(err.to_string()));
+                return false;
+            }
+        }
+    }
+}
+
+/// `forward_input_lines` under `--flush-partial`: this thread reads while
+/// another cuts lines out of what arrives, so it can notice a line whose
+/// newline is slow in coming.
+fn forward_partial_lines(
+    mut reader: impl BufRead,
+    tx: &SyncSender<InputMessage>,
+    capture: &CaptureOptions,
+    overflow: Overflow,
+    source: Option<usize>,
+) -> bool {
+    thread::scope(|scope| {
+        let (chunk_tx, chunk_rx) = mpsc::sync_channel(PARTIAL_CHUNK_QUEUE_LEN);
+        let assembler = scope.spawn(move || {
+            let mut skipped = 0;
+            let sent = assemble_lines(
+                chunk_rx,
+                PARTIAL_LINE_DELAY,
+                |line| {
+                    prepare_input_line(line, capture).is_none_or(|line| {
+                        send_input_line(tx, line, source, overflow, &mut skipped)
+                    })
+                },
+                |partial| {
+                    // Another comes along with the next bytes, so one that
+                    // doesn't fit is no loss.
+                    prepare_input_line(partial, capture).is_none_or(|partial| {
+                        !matches!(
+                            tx.try_send(InputMessage::Partial(partial)),
+                            Err(TrySendError::Disconnected(_))
+                        )
+                    })
+                },
+            );
+            sent && (skipped == 0 || tx.send(InputMessage::Skipped(skipped)).is_ok())
+        });
+        let read = forward_chunks(&mut reader, &chunk_tx);
+        drop(chunk_tx);
+        let sent = assembler.join().unwrap_or(false);
+        match read {
+            Ok(()) => sent,
+            Err(err) => {
+                let _ = tx.send(InputMessage::Error(err.to_string()));
+                false
+            }
+        }
+    })
+}
+
+/// Passes on what `reader` delivers as it arrives, until it ends or `chunks`
+/// hangs up.
+fn forward_chunks(reader: &mut impl BufRead, chunks: &SyncSender<Vec<u8>>) -> io::Result<()> {
+    loop {
+        let chunk = match reader.fill_buf() {
+            Ok(buf) => buf.to_vec(),
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        };
+        if chunk.is_empty() {
+            return Ok(());
+        }
+        reader.consume(chunk.len());
+        if chunks.send(chunk).is_err() {
+            return Ok(());
+        }
+    }
+}
+
+/// Cuts lines out of the bytes `chunks` brings and hands each to `send_line`,
+/// newline included. Bytes that have waited `delay` for their newline go to
+/// `send_partial`, and again whenever more of them arrive. Whatever is left
+/// at the end is a last line. False once either send fails.
+fn assemble_lines(
+    chunks: Receiver<Vec<u8>>,
+    delay: Duration,
+    mut send_line: impl FnMut(String) -> bool,
+    mut send_partial: impl FnMut(String) -> bool,
+) -> bool {
+    let mut pending = Vec::new();
+    // When bytes of `pending` not yet sent as a partial first arrived.
+    let mut waiting_since: Option<Instant> = None;
+
+    loop {
+        let received = match waiting_since {
+            Some(since) => chunks.recv_timeout(delay.saturating_sub(since.elapsed())),
+            None => chunks.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        };
+        match received {
+            Ok(chunk) => {
+                pending.extend_from_slice(&chunk);
+                while let Some(end) = pending.iter().position(|&byte| byte == b'\n') {
+                    let line: Vec<u8> = pending.drain(..=end).collect();
+                    waiting_since = None;
+                    if !send_line(String::from_utf8_lossy(&line).into_owned()) {
+                        return false;
+                    }
+                }
+                if !pending.is_empty() {
+                    waiting_since.get_or_insert_with(Instant::now);
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => {
+                return pending.is_empty()
+                    || send_line(String::from_utf8_lossy(&pending).into_owned());
+            }
+        }
+        if waiting_since.is_some_and(|since| since.elapsed() >= delay) {
+            waiting_since = None;
+            if !send_partial(String::from_utf8_lossy(&pending).into_owned()) {
+                return false;
+            }
+        }
+    }
+}
+
+/// A line as read, with its newline dropped and normalized. `None` if
+/// `capture` skips it.
+fn prepare_input_line(mut line: String, capture: &CaptureOptions) -> Option<String> {
+    if line.ends_with('\n') {
+        line.pop();
+        if line.ends_with('\r') {
+            line.pop();
+        }
+    }
+
+    capture.apply(line)
+}
+
+/// Sends `line` the way `--on-overflow` says. False once the UI is gone.
+fn send_input_line(
+    tx: &SyncSender<InputMessage>,
+    line: String,
+    source: Option<usize>,
+    overflow: Overflow,
+    skipped: &mut usize,
+) -> bool {
+    match overflow {
+        Overflow::Block => tx.send(InputMessage::Line(line, source)).is_ok(),
+        Overflow::Drop => offer_line(tx, line, source, skipped),
+    }
+}
+
+/// Sends `line` if the channel has room, after the count of lines dropped
+/// before it, and otherwise drops it too. The count only goes out ahead of
+/// a line, so it stays in order with them. False once the UI is gone.
+fn offer_line(
+    tx: &SyncSender<InputMessage>,
+    line: String,
+    source: Option<usize>,
+    skipped: &mut usize,
+) -> bool {
+    if *skipped > 0 {
+        match tx.try_send(InputMessage::Skipped(*skipped)) {
+            Ok(()) => *skipped = 0,
+            Err(TrySendError::Full(_)) => {
+                *skipped += 1;
+                return true;
+            }
+            Err(TrySendError::Disconnected(_)) => return false,
+        }
+    }
+    match tx.try_send(InputMessage::Line(line, source)) {
+        Ok(()) => true,
+        Err(TrySendError::Full(_)) => {
+            *skipped += 1;
+            true
+        }
+        Err(TrySendError::Disconnected(_)) => false,
+    }
+}
+
+/// Runs an `--on-match` command for each line handed to it, piping the line to
+/// the command's stdin. Commands run one at a time on a worker thread; lines
+/// that arrive while the queue is full are dropped rather than blocking input.
+#[derive(Debug)]
+struct MatchHook {
+    tx: SyncSender<String>,
+    running: Arc<Mutex<Option<Child>>>,
+}
+
+impl MatchHook {
+    fn spawn(command: String, ui_tx: SyncSender<UiMessage>) -> Self {
+        let (tx, rx) = mpsc::sync_channel::<String>(HOOK_QUEUE_LEN);
+        let running = Arc::new(Mutex::new(None));
+        let worker_running = Arc::clone(&running);
+
+        thread::spawn(move || {
+            let mut backoff = Duration::ZERO;
+            while let Ok(line) = rx.recv() {
+                let started = Instant::now();
+                match run_hook_command(&command, &line, &worker_running) {
+                    Ok(()) => backoff = Duration::ZERO,
+                    Err(err) => {
+                        backoff = (backoff * 2).clamp(Duration::from_secs(1), HOOK_MAX_BACKOFF);
+                        let _ = ui_tx.try_send(UiMessage::HookFailed(format!(
+                            "--on-match `{}` failed: {}; pausing it for {}s",
+                            command,
+                            err,
+                            backoff.as_secs()
+                        )));
+                        // Lines that matched while backing off are skipped.
+                        thread::sleep(backoff);
+                        while rx.try_recv().is_ok() {}
+                    }
+                }
+                thread::sleep(HOOK_MIN_INTERVAL.saturating_sub(started.elapsed()));
+            }
+        });
+
+        Self { tx, running }
+    }
+
+    fn notify(&self, line: &str) {
+        let _ = self.tx.try_send(line.to_owned());
+    }
+}
+
+impl Drop for MatchHook {
+    fn drop(&mut self) {
+        if let Ok(mut running) = self.running.lock()
+            && let Some(mut child) = running.take()
+        {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+fn run_hook_command(
+    command: &str,
+    line: &str,
+    running: &Mutex<Option<Child>>,
+) -> Result<(), String> {
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|err| err.to_string())?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // A command that ignores its input may close the pipe early.
+        let _ = writeln!(stdin, "{}", line);
+    }
+    *running
+        .lock()
+        .map_err(|_| "hook state poisoned".to_owned())? = Some(child);
+
+    let deadline = Instant::now() + HOOK_TIMEOUT;
+    loop {
+        let mut guard = running
+            .lock()
+            .map_err(|_| "hook state poisoned".to_owned())?;
+        // Gone means the hook was dropped on exit and already killed the child.
+        let Some(child) = guard.as_mut() else {
+            return Ok(());
+        };
+        match child.try_wait() {
+            Ok(Some(exit)) => {
+                guard.take();
+                return if exit.success() {
+                    Ok(())
+                } else {
+                    Err(exit.to_string())
+                };
+            }
+            Ok(None) if Instant::now() >= deadline => {
+                let _ = child.kill();
+                let _ = child.wait();
+                guard.take();
+                return Err(format!("timed out after {}s", HOOK_TIMEOUT.as_secs()));
+            }
+            Ok(None) => {}
+            Err(err) => {
+                guard.take();
+                return Err(err.to_string());
+            }
+        }
+        drop(guard);
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Resolves the `<tab>` of an `--on-match <tab>:<cmd>` spec, given either as a
+/// tab's label or its number, and returns the tab index with the command.
+/// Labels are tried first, longest first, since filters may contain `:`.
+fn resolve_hook_spec(tabs: &[Tab], spec: &str) -> Result<(usize, String), String> {
+    let by_label = tabs
+        .iter()
+        .enumerate()
+        .filter_map(|(index, tab)| {
+            let command = spec.strip_prefix(tab.label.as_str())?.strip_prefix(':')?;
+            Some((tab.label.len(), index, command))
+        })
+        .max_by_key(|(label_len, _, _)| *label_len)
+        .map(|(_, index, command)| (index, command));
+    let by_number = || {
+        let (number, command) = spec.split_once(':')?;
+        let number = number.parse::<usize>().ok()?;
+        let index = number.checked_sub(tab_number_offset(tabs))?;
+        (index < tabs.len()).then_some((index, command))
+    };
+
+    match by_label.or_else(by_number) {
+        Some((_, "")) => Err(format!(
+            "--on-match needs a command after the tab: {}",
+            spec
+        )),
+        Some((index, command)) => Ok((index, command.to_owned())),
+        None => Err(format!(
+            "--on-match names no tab (expected <filter>:<cmd> or <number>:<cmd>): {}",
+            spec
+        )),
+    }
+}
+
+/// Where the UI's keys and mouse reports come from.
+trait TerminalInput: Send {
+    /// Whether there is input to read within `timeout`. Errors are left for
+    /// `read` to report.
+    fn wait(&mut self, timeout: Duration) -> bool;
+
+    /// Reads what has arrived and returns the messages it makes, or `None`
+    /// once the terminal is closed.
+    fn read(&mut self) -> io::Result<Option<Vec<UiMessage>>>;
+}
+
+/// The controlling terminal's bytes, read from `/dev/tty` so stdin stays free
+/// for the input stream, and decoded by `InputParser`.
+#[cfg(unix)]
+struct TtyInput {
+    tty: File,
+    parser: InputParser,
+}
+
+#[cfg(unix)]
+impl TtyInput {
+    fn open(keys: KeyRemap) -> io::Result<Self> {
+        Ok(Self {
+            tty: std::fs::OpenOptions::new().read(true).open("/dev/tty")?,
+            parser: InputParser::with_keys(keys),
+        })
+    }
+}
+
+#[cfg(unix)]
+impl TerminalInput for TtyInput {
+    fn wait(&mut self, timeout: Duration) -> bool {
+        wait_readable(&self.tty, timeout)
+    }
+
+    fn read(&mut self) -> io::Result<Option<Vec<UiMessage>>> {
+        let mut buf = [0u8; 64];
+        let n = match self.tty.read(&mut buf) {
+            Ok(0) => return Ok(None),
+            Ok(n) => n,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => return Ok(Some(Vec::new())),
+            Err(err) => return Err(err),
+        };
+        let mut messages = buf[..n]
+            .iter()
+            .filter_map(|byte| self.parser.feed(*byte))
+            .collect::<Vec<_>>();
+        messages.extend(self.parser.finish_read());
+        Ok(Some(messages))
+    }
+}
+
+/// The console's events as crossterm reads them, for platforms without a
+/// `/dev/tty`. crossterm reads the console itself, so stdin stays free here
+/// too.
+#[cfg(not(unix))]
+struct ConsoleInput {
+    parser: InputParser,
+}
+
+#[cfg(not(unix))]
+impl TerminalInput for ConsoleInput {
+    fn wait(&mut self, timeout: Duration) -> bool {
+        crossterm::event::poll(timeout).unwrap_or(true)
+    }
+
+    fn read(&mut self) -> io::Result<Option<Vec<UiMessage>>> {
+        let event = crossterm::event::read()?;
+        Ok(Some(
+            self.parser.event_message(&event).into_iter().collect(),
+        ))
+    }
+}
+
+/// Passes the terminal's keys between the UI's reader and a pager.
+#[derive(Debug, Default)]
+struct KeyHandoff {
+    /// Set while the keys are someone else's.
+    paused: AtomicBool,
+    /// Set by the reader while it reads nothing: during a pause, or for
+    /// good once it has stopped.
+    parked: AtomicBool,
+}
+
+impl KeyHandoff {
+    /// Stops the reader and waits until it is no longer reading, so a read
+    /// already under way can't take a key meant for the pager.
+    fn pause(&self) {
+        // Cleared first, so a park left over from an earlier pause can't
+        // stand in for this one.
+        self.parked.store(false, Ordering::SeqCst);
+        self.paused.store(true, Ordering::SeqCst);
+        while !self.parked.load(Ordering::SeqCst) {
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    fn resume(&self) {
+        self.paused.store(false, Ordering::SeqCst);
+    }
+}
+
+/// Reads keys from the terminal until it closes. While `handoff` is paused,
+/// they are left for whoever else is reading the terminal.
+fn spawn_ui_reader(
+    tx: SyncSender<UiMessage>,
+    handoff: Arc<KeyHandoff>,
+    keys: KeyRemap,
+) -> io::Result<()> {
+    #[cfg(unix)]
+    let input = TtyInput::open(keys)?;
+    #[cfg(not(unix))]
+    let input = ConsoleInput {
+        parser: InputParser::with_keys(keys),
+    };
+
+    thread::spawn(move || {
+        forward_terminal_input(input, &tx, &handoff);
+        // Nothing reads the keys anymore, so a pause has nothing to wait for.
+        handoff.parked.store(true, Ordering::SeqCst);
+    });
+    Ok(())
+}
+
+fn forward_terminal_input(
+    mut input: impl TerminalInput,
+    tx: &SyncSender<UiMessage>,
+    handoff: &KeyHandoff,
+) {
+    loop {
+        if handoff.paused.load(Ordering::SeqCst) {
+            handoff.parked.store(true, Ordering::SeqCst);
+            thread::sleep(POLL_INTERVAL);
+            continue;
+        }
+        // Check again after waiting, in case a pager took over meanwhile.
+        if !input.wait(POLL_INTERVAL) || handoff.paused.load(Ordering::SeqCst) {
+            continue;
+        }
+        match input.read() {
+            Ok(Some(messages)) => {
+                for message in messages {
+                    if tx.send(message).is_err() {
+                        return;
+                    }
+                }
+            }
+            Ok(None) => {
+                let _ = tx.send(UiMessage::TerminalClosed);
+                return;
+            }
+            Err(err) => {
+                let _ = tx.send(UiMessage::Error(err.to_string()));
+                return;
+            }
+        }
+    }
+}
+
+impl UiMessage {
+    /// Messages that don't come from a key press, so they leave a pending
+    /// repeat count alone.
+    fn is_background(&self) -> bool {
+        #[cfg(feature = "jsonl")]
+        if matches!(self, UiMessage::ExportFailed(_)) {
+            return true;
+        }
+        matches!(
+            self,
+            UiMessage::MouseMove { .. }
+                | UiMessage::FocusGained
+                | UiMessage::FocusLost
+                | UiMessage::HookFailed(_)
+                | UiMessage::TerminalClosed
+                | UiMessage::Error(_)
+        )
+    }
+
+    /// Scales a navigation message by a repeat count; other messages are
+    /// returned as they are.
+    fn repeated(self, count: usize) -> Self {
+        let count = count as isize;
+        match self {
+            UiMessage::Scroll(rows) => UiMessage::Scroll(rows * count),
+            UiMessage::ScrollPage(pages) => UiMessage::ScrollPage(pages * count),
+            UiMessage::MoveSelection(lines) => UiMessage::MoveSelection(lines * count),
+            other => other,
+        }
+    }
+}
+
+/// Names a `[keys]` table in a `--config` file can bind, each with the
+/// built-in key it stands in for.
+const KEY_ACTIONS: &[(&str, u8)] = &[
+    ("next-tab", b'\t'),
+    ("pause", b' '),
+    ("clear-selection", b'd'),
+    ("select-middle", b's'),
+    ("select-down", b'j'),
+    ("select-up", b'k'),
+    ("detail-pane", b'v'),
+    ("cycle-mode", b'm'),
+    ("invert-tab", b'!'),
+    ("relative-seq", b'+'),
+    ("seq-gutter", b'#'),
+    ("copy", b'y'),
+    ("dump-tab", b'b'),
+    ("help", b'?'),
+    ("scroll-tabs-left", b'<'),
+    ("scroll-tabs-right", b'>'),
+    ("follow", b'i'),
+    ("close-tab", b'-'),
+    ("wrap", b'f'),
+    ("clock", b't'),
+    ("pager", b'l'),
+    ("hold-input", b'h'),
+    ("gaps", b'g'),
+    ("search", b'/'),
+    ("search-next", b'n'),
+    ("search-previous", b'N'),
+    ("preview", b'p'),
+    ("palette", b'r'),
+    ("overview", b'o'),
+    ("marker", b'x'),
+    ("marker-slice", b'z'),
+    ("frequency", b'c'),
+    ("split", b'w'),
+    ("mark-read", b'a'),
+    ("split-focus", b'e'),
+    ("unread-only", b'u'),
+    ("quit", b'q'),
+];
+
+/// Keys bound by a config file, each acting as the built-in key of its action.
+/// Other keys, including the built-in ones, keep their usual meaning.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct KeyRemap(Vec<(u8, u8)>);
+
+impl KeyRemap {
+    #[cfg(any(feature = "config", test))]
+    fn bind(&mut self, key: u8, action: u8) {
+        self.0.retain(|&(bound, _)| bound != key);
+        self.0.push((key, action));
+    }
+
+    fn resolve(&self, byte: u8) -> u8 {
+        self.0
+            .iter()
+            .find(|&&(key, _)| key == byte)
+            .map_or(byte, |&(_, action)| action)
+    }
+
+    /// `key=action` for each binding, as `--print-config` lists them.
+    fn describe(&self) -> Vec<String> {
+        self.0
+            .iter()
+            .filter_map(|&(key, action)| {
+                let (name, _) = KEY_ACTIONS.iter().find(|(_, byte)| *byte == action)?;
+                Some(format!("{}={}", key as char, name))
+            })
+            .collect()
+    }
+}
+
+fn key_message_from_byte(byte: u8) -> Option<UiMessage> {
+    match byte {
+        b'\t' => Some(UiMessage::NextTab),
+        b'1'..=b'9' => Some(UiMessage::SelectTab((byte - b'0') as usize)),
+        b'0' => Some(UiMessage::SelectTab(0)),
+        b' ' => Some(UiMessage::TogglePause),
+        b'd' | b'D' => Some(UiMessage::ClearSelection),
+        b's' | b'S' => Some(UiMessage::SelectMiddleVisibleLine),
+        b'j' | b'J' => Some(UiMessage::MoveSelection(1)),
+        b'k' | b'K' => Some(UiMessage::MoveSelection(-1)),
+        b'v' | b'V' => Some(UiMessage::ToggleDetailPane),
+        b'm' | b'M' => Some(UiMessage::CycleMatchMode),
+        b'!' => Some(UiMessage::InvertTab),
+        b'+' => Some(UiMessage::ToggleRelativeSeq),
+        b'#' => Some(UiMessage::ToggleSeqGutter),
+        b'y' | b'Y' => Some(UiMessage::CopySelection),
+        b'b' | b'B' => Some(UiMessage::DumpActiveTab),
+        b'?' => Some(UiMessage::ToggleHelp),
+        b'<' => Some(UiMessage::ScrollTabs(-1)),
+        b'>' => Some(UiMessage::ScrollTabs(1)),
+        b'i' | b'I' => Some(UiMessage::ToggleFollow),
+        b'-' => Some(UiMessage::CloseTab),
+        b'f' | b'F' => Some(UiMessage::ToggleWrap),
+        b't' | b'T' => Some(UiMessage::ToggleClock),
+        b'l' | b'L' => Some(UiMessage::OpenPager),
+        b'h' | b'H' => Some(UiMessage::ToggleInputHold),
+        b'g' | b'G' => Some(UiMessage::ToggleGaps),
+        b'/' => Some(UiMessage::StartSearch),
+        b'n' => Some(UiMessage::SearchNext),
+        b'N' => Some(UiMessage::SearchPrevious),
+        b'p' | b'P' => Some(UiMessage::TogglePreview),
+        b'r' | b'R' => Some(UiMessage::OpenPalette),
+        b'o' | b'O' => Some(UiMessage::ToggleOverview),
+        b'x' | b'X' => Some(UiMessage::ToggleMarker),
+        b'z' | b'Z' => Some(UiMessage::CycleMarkerSlice),
+        b'c' | b'C' => Some(UiMessage::CycleFrequencyView),
+        b'w' | b'W' => Some(UiMessage::ToggleSplit),
+        b'a' | b'A' => Some(UiMessage::MarkRead),
+        b'e' | b'E' => Some(UiMessage::SwitchSplitFocus),
+        b'\r' => Some(UiMessage::Activate),
+        b'u' | b'U' => Some(UiMessage::ToggleUnreadOnly),
+        b'q' | b'Q' => Some(UiMessage::Quit),
+        0x03 => Some(UiMessage::Interrupt),
+        0x0c => Some(UiMessage::Redraw),
+        _ => None,
+    }
+}
+
+/// Arrow and page keys, as `ESC [` sequences without modifiers, and focus
+/// reports.
+fn csi_key_message(sequence: &[u8]) -> Option<UiMessage> {
+    match sequence {
+        b"A" => Some(UiMessage::Scroll(-1)),
+        b"B" => Some(UiMessage::Scroll(1)),
+        b"5~" => Some(UiMessage::ScrollPage(-1)),
+        b"6~" => Some(UiMessage::ScrollPage(1)),
+        b"I" => Some(UiMessage::FocusGained),
+        b"O" => Some(UiMessage::FocusLost),
+        b"Z" => Some(UiMessage::PrevTab),
+        _ => None,
+    }
+}
+
+fn try_parse_sgr_mouse_message(sequence: &[u8]) -> Option<UiMessage> {
+    let (final_byte, params) = sequence.split_last()?;
+    // SGR mouse reports end in `M` for presses and motion, `m` for releases.
+    if !matches!(final_byte, b'M' | b'm') || !params.starts_with(b"<") {
+        return None;
+    }
+    let is_release = *final_byte == b'm';
+
+    let payload = std::str::from_utf8(&params[1..]).ok()?;
+    let mut parts = payload.split(';');
+    let cb = parts.next()?.parse::<u16>().ok()?;
+    let col = parts.next()?.parse::<u16>().ok()?;
+    let row = parts.next()?.parse::<u16>().ok()?;
+    if parts.next().is_some() {
+        return None;
+    }
+
+    let is_left_button = (cb & 0b11) == 0;
+    let is_motion = (cb & 0b0010_0000) != 0;
+    let is_wheel = (cb & 0b0100_0000) != 0;
+    let shift = (cb & 0b0000_0100) != 0;
+    if is_wheel {
+        return match (cb & 0b11, is_release) {
+            (0, false) => Some(UiMessage::Scroll(-WHEEL_SCROLL_ROWS)),
+            (1, false) => Some(UiMessage::Scroll(WHEEL_SCROLL_ROWS)),
+            _ => None,
+        };
+    }
+    if is_motion && (cb & 0b11) == 3 {
+        return Some(UiMessage::MouseMove {
+            row: row.saturating_sub(1),
+        });
+    }
+    if (cb & 0b11) == 2 {
+        return (!is_release && !is_motion).then(|| UiMessage::MouseRightDown {
+            row: row.saturating_sub(1),
+        });
+    }
+    if !is_left_button {
+        return None;
+    }
+
+    let column = col.saturating_sub(1);
+    if is_release {
+        Some(UiMessage::MouseLeftUp { column })
+    } else if is_motion {
+        Some(UiMessage::MouseLeftDrag { column })
+    } else {
+        Some(UiMessage::MouseLeftDown {
+            column,
+            row: row.saturating_sub(1),
+            shift,
+        })
+    }
+}
+
+/// Whether `file` has input within `timeout`, so a reader can look at other
+/// state between reads instead of blocking in one.
+#[cfg(unix)]
+fn wait_readable(file: &File, timeout: Duration) -> bool {
+    use std::os::fd::AsRawFd;
+
+    let mut poll_fd = libc::pollfd {
+        fd: file.as_raw_fd(),
+        events: libc::POLLIN,
+        revents: 0,
+    };
+    // SAFETY: `poll_fd` is a single valid entry for the duration of the call.
+    let ready = unsafe { libc::poll(&mut poll_fd, 1, timeout.as_millis() as libc::c_int) };
+    // Errors and hangups are left for the read to report.
+    ready != 0
+}
+
+#[cfg(unix)]
+fn terminate_pipeline_group_if_safe() {
+    // In interactive shells with job control, pipeline commands are in a separate
+    // process group from the shell. Signaling that group lets `q` stop upstream
+    // producers like `tail -f` immediately.
+    unsafe {
+        let my_pgid = libc::getpgrp();
+        if my_pgid <= 0 {
+            return;
+        }
+
+        let parent_pgid = libc::getpgid(libc::getppid());
+        if parent_pgid == my_pgid {
+            return;
+        }
+
+        let _ = libc::signal(libc::SIGINT, libc::SIG_IGN);
+        let _ = libc::killpg(my_pgid, libc::SIGINT);
+    }
+}
+
+#[cfg(not(unix))]
+fn terminate_pipeline_group_if_safe() {}
+
+fn mark_tab_seen_live(tabs: &mut [Tab], index: usize) {
+    if let Some(tab) = tabs.get_mut(index) {
+        tab.mark_seen_through(tab.total_matches);
+    }
+}
+
+/// Marks a tab read through the line `seq`, leaving only newer matches unread.
+fn mark_tab_seen_through(tabs: &mut [Tab], index: usize, seq: u64) {
+    if let Some(tab) = tabs.get_mut(index) {
+        tab.mark_seen_through(tab.match_index_through(seq));
+    }
+}
+
+fn mark_tabs_seen_live(tabs: &mut [Tab], active_tab_indices: &[usize]) {
+    for &index in active_tab_indices {
+        mark_tab_seen_live(tabs, index);
+    }
+}
+
+fn mark_tab_seen_paused(tabs: &mut [Tab], index: usize, pause_match_cutoffs: &[u64]) {
+    if let Some(tab) = tabs.get_mut(index) {
+        let cutoff = pause_match_cutoffs
+            .get(index)
+            .copied()
+            .unwrap_or(tab.total_matches);
+        tab.mark_seen_through(cutoff);
+    }
+}
+
+fn mark_tabs_seen_paused(
+    tabs: &mut [Tab],
+    active_tab_indices: &[usize],
+    pause_match_cutoffs: &[u64],
+) {
+    for &index in active_tab_indices {
+        mark_tab_seen_paused(tabs, index, pause_match_cutoffs);
+    }
+}
+
+fn is_tab_active(active_tab_indices: &[usize], tab_index: usize) -> bool {
+    active_tab_indices.binary_search(&tab_index).is_ok()
+}
+
+fn select_tab(
+    tabs: &mut [Tab],
+    active_index: &mut usize,
+    active_tab_indices: &mut Vec<usize>,
+    next_index: usize,
+    paused: bool,
+    pause_snapshot: Option<&PauseSnapshot>,
+) {
+    if next_index >= tabs.len() {
+        return;
+    }
+
+    let previous = std::mem::replace(active_tab_indices, vec![next_index]);
+    *active_index = next_index;
+    begin_viewing_new_tabs(tabs, &previous, active_tab_indices);
+    mark_active_tabs_seen(tabs, active_tab_indices, paused, pause_snapshot);
+}
+
+fn begin_viewing_new_tabs(tabs: &mut [Tab], previous: &[usize], current: &[usize]) {
+    for &index in current {
+        if !is_tab_active(previous, index)
+            && let Some(tab) = tabs.get_mut(index)
+        {
+            tab.unread_view_start = tab.seen_matches;
+        }
+    }
+}
+
+fn mark_active_tabs_seen(
+    tabs: &mut [Tab],
+    active_tab_indices: &[usize],
+    paused: bool,
+    pause_snapshot: Option<&PauseSnapshot>,
+) {
+    if paused {
+        if let Some(snapshot) = pause_snapshot {
+            mark_tabs_seen_paused(tabs, active_tab_indices, &snapshot.match_cutoffs);
+        }
+    } else {
+        mark_tabs_seen_live(tabs, active_tab_indices);
+    }
+}
+
+fn select_all_tabs(
+    tabs: &mut [Tab],
+    active_index: &mut usize,
+    active_tab_indices: &mut Vec<usize>,
+    paused: bool,
+    pause_snapshot: Option<&PauseSnapshot>,
+) {
+    if tabs.is_empty() {
+        return;
+    }
+
+    let previous = std::mem::replace(active_tab_indices, (0..tabs.len()).collect());
+    if !is_tab_active(active_tab_indices, *active_index) {
+        *active_index = 0;
+    }
+    begin_viewing_new_tabs(tabs, &previous, active_tab_indices);
+    mark_active_tabs_seen(tabs, active_tab_indices, paused, pause_snapshot);
+}
+
+fn include_tab_in_or_view(
+    tabs: &mut [Tab],
+    active_index: &mut usize,
+    active_tab_indices: &mut Vec<usize>,
+    tab_index: usize,
+    paused: bool,
+    pause_snapshot: Option<&PauseSnapshot>,
+) {
+    if tab_index >= tabs.len() {
+        return;
+    }
+
+    let previous = active_tab_indices.clone();
+    match active_tab_indices.binary_search(&tab_index) {
+        Ok(existing_pos) => {
+            if active_tab_indices.len() > 1 {
+                active_tab_indices.remove(existing_pos);
+                if *active_index == tab_index {
+                    let fallback_pos = existing_pos.min(active_tab_indices.len() - 1);
+                    *active_index = active_tab_indices[fallback_pos];
+                }
+            } else {
+                *active_index = tab_index;
+            }
+        }
+        Err(insert_pos) => {
+            active_tab_indices.insert(insert_pos, tab_index);
+            *active_index = tab_index;
+        }
+    }
+
+    begin_viewing_new_tabs(tabs, &previous, active_tab_indices);
+    mark_active_tabs_seen(tabs, active_tab_indices, paused, pause_snapshot);
+}
+
+#[cfg(test)]
+fn apply_line_to_tabs(
+    tabs: &mut [Tab],
+    active_tab_indices: &[usize],
+    paused: bool,
+    seq: u64,
+    line: &str,
+) {
+    let record = LineRecord::new(seq, line, None);
+    apply_line_to_eligible_tabs(tabs, active_tab_indices, paused, &record, line, |_| true);
+}
+
+fn is_blank_line(line: &str) -> bool {
+    strip_ansi(line).trim().is_empty()
+}
+
+/// Applies a line unless it's blank and `skip_blank` is set. Tabs whose own
+/// pattern is whitespace still receive blank lines. Returns whether any tab
+/// could have taken the line, so skipped lines don't consume a `seq`.
+/// `fold_case` matches the line lowercased; see `match_text`.
+fn ingest_line(
+    tabs: &mut [Tab],
+    active_tab_indices: &[usize],
+    paused: bool,
+    record: &LineRecord,
+    skip_blank: bool,
+    fold_case: bool,
+) -> bool {
+    let line = record.text.as_str();
+    if !skip_blank || !is_blank_line(line) {
+        let matched = match_text(line, fold_case);
+        apply_line_to_eligible_tabs(tabs, active_tab_indices, paused, record, &matched, |_| true);
+        return true;
+    }
+
+    if !tabs.iter().any(Tab::targets_whitespace) {
+        return false;
+    }
+
+    apply_line_to_eligible_tabs(
+        tabs,
+        active_tab_indices,
+        paused,
+        record,
+        line,
+        Tab::targets_whitespace,
+    );
+    true
+}
+
+fn apply_line_to_eligible_tabs(
+    tabs: &mut [Tab],
+    active_tab_indices: &[usize],
+    paused: bool,
+    record: &LineRecord,
+    match_text: &str,
+    eligible: impl Fn(&Tab) -> bool,
+) {
+    let (line, arrived) = (record.text.as_str(), record.arrived);
+    let mut matched_tabs = TabSet::default();
+    for (index, tab) in tabs.iter().enumerate() {
+        matched_tabs.set(index, eligible(tab) && tab.matches(match_text));
+    }
+    for index in matched_tabs.iter() {
+        let tab = &mut tabs[index];
+        let matched_tabs = if matches!(tab.mode, MatchMode::All) {
+            matched_tabs.clone()
+        } else {
+            TabSet::default()
+        };
+        tab.push_record(LineRecord {
+            matched_tabs,
+            ..record.clone()
+        });
+        tab.last_match_at = Some(arrived);
+        if let Some(hook) = &tab.on_match {
+            hook.notify(line);
+        }
+        #[cfg(feature = "jsonl")]
+        if let Some(export) = &tab.export {
+            export.record(record.seq, &tab.label, line);
+        }
+        if is_tab_active(active_tab_indices, index) && !paused {
+            tab.mark_seen_through(tab.total_matches);
+        }
+    }
+}
+
+/// Keeps the first `width` columns of plain `text`. A wide char that would
+/// straddle the edge is dropped and its one column left padded with a space.
+fn clip_to_width(text: &str, width: usize) -> String {
+    let mut out = String::new();
+    let mut visible = 0usize;
+    for ch in text.chars() {
+        let ch_width = char_display_width(ch);
+        if visible + ch_width > width {
+            out.push_str(&" ".repeat(width - visible));
+            break;
+        }
+        out.push(ch);
+        visible += ch_width;
+    }
+    out
+}
+
+fn is_ansi_final_byte(ch: char) -> bool {
+    ('@'..='~').contains(&ch)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CsiKind {
+    /// Select Graphic Rendition (`m`): colors and text attributes.
+    Sgr,
+    /// Cursor positioning and erasing (`A`-`H`, `J`, `K`, `f`, ...).
+    CursorMovement,
+    Other,
+}
+
+fn csi_kind(final_byte: char) -> CsiKind {
+    match final_byte {
+        'm' => CsiKind::Sgr,
+        'A'..='H' | 'J' | 'K' | 'S' | 'T' | 'd' | 'f' | 's' | 'u' => CsiKind::CursorMovement,
+        _ => CsiKind::Other,
+    }
+}
+
+/// One piece of a captured line: an escape sequence, whole, or a visible char.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AnsiPiece<'a> {
+    Escape(&'a str),
+    Char(char),
+}
+
+/// Byte length of the escape sequence `text` starts with. A CSI sequence
+/// runs to its final byte; OSC and the other string sequences (hyperlinks,
+/// window titles) run to BEL or ST (`ESC \`); anything else is the escape,
+/// its intermediate bytes and one final char, as in `ESC ( B`. One cut off
+/// by the end of the line runs to the end.
+fn escape_len(text: &str) -> usize {
+    let mut chars = text.chars();
+    chars.next();
+    match chars.next() {
+        Some('[') => {
+            for seq_char in chars.by_ref() {
+                if is_ansi_final_byte(seq_char) {
+                    break;
+                }
+            }
+        }
+        Some(']' | 'P' | 'X' | '^' | '_') => {
+            while let Some(seq_char) = chars.next() {
+                if seq_char == '\u{7}' {
+                    break;
+                }
+                if seq_char == '\u{1b}' && chars.clone().next() == Some('\\') {
+                    chars.next();
+                    break;
+                }
+            }
+        }
+        Some(' '..='/') => {
+            for seq_char in chars.by_ref() {
+                if !(' '..='/').contains(&seq_char) {
+                    break;
+                }
+            }
+        }
+        _ => {}
+    }
+    text.len() - chars.as_str().len()
+}
+
+/// Splits `text` into escape sequences and the chars between them.
+fn ansi_pieces(text: &str) -> impl Iterator<Item = AnsiPiece<'_>> {
+    let mut rest = text;
+    std::iter::from_fn(move || {
+        let ch = rest.chars().next()?;
+        let len = if ch == '\u{1b}' {
+            escape_len(rest)
+        } else {
+            ch.len_utf8()
+        };
+        let (piece, tail) = rest.split_at(len);
+        rest = tail;
+        Some(if ch == '\u{1b}' {
+            AnsiPiece::Escape(piece)
+        } else {
+            AnsiPiece::Char(ch)
+        })
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ColorLayer {
+    Foreground,
+    Background,
+}
+
+impl ColorLayer {
+    /// The color an SGR sequence sets, if that is all it does.
+    fn set_by(sequence: &str) -> Option<Self> {
+        let params = sequence.strip_prefix("\u{1b}[")?.strip_suffix('m')?;
+        if params.starts_with("38;") {
+            return Some(Self::Foreground);
+        }
+        if params.starts_with("48;") {
+            return Some(Self::Background);
+        }
+        match params.parse::<u8>().ok()? {
+            30..=37 | 90..=97 => Some(Self::Foreground),
+            40..=47 | 100..=107 => Some(Self::Background),
+            _ => None,
+        }
+    }
+}
+
+/// The SGR sequences in effect at some point of a line, so a piece cut out
+/// of it can be opened in the same style and reset at its end.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct SgrState {
+    active: Vec<String>,
+}
+
+impl SgrState {
+    /// Notes `sequence` if it is SGR. A reset drops everything before it,
+    /// including one leading a longer list (`ESC[0;31m`).
+    fn apply(&mut self, sequence: &str) {
+        let Some(params) = sequence
+            .strip_prefix("\u{1b}[")
+            .and_then(|rest| rest.strip_suffix('m'))
+        else {
+            return;
+        };
+        if params.is_empty() || params == "0" {
+            self.active.clear();
+            return;
+        }
+        if params.starts_with("0;") || params.starts_with(';') {
+            self.active.clear();
+        }
+        // Ending a color drops the sequences that only set it, and isn't
+        // needed once nothing else could have.
+        let ended = match params {
+            "39" => Some(ColorLayer::Foreground),
+            "49" => Some(ColorLayer::Background),
+            _ => None,
+        };
+        if let Some(layer) = ended {
+            self.active
+                .retain(|active| ColorLayer::set_by(active) != Some(layer));
+            if self
+                .active
+                .iter()
+                .all(|active| ColorLayer::set_by(active).is_some())
+            {
+                return;
+            }
+        }
+        self.active.push(sequence.to_owned());
+    }
+
+    /// The state at the end of `text`.
+    fn after(text: &str) -> Self {
+        let mut state = Self::default();
+        for piece in ansi_pieces(text) {
+            if let AnsiPiece::Escape(sequence) = piece {
+                state.apply(sequence);
+            }
+        }
+        state
+    }
+
+    fn is_active(&self) -> bool {
+        !self.active.is_empty()
+    }
+
+    /// The sequences that restart the current style from a clean terminal.
+    fn opening(&self) -> String {
+        self.active.concat()
+    }
+
+    /// The reset that ends a piece still styled, so it can't bleed past it.
+    fn closing(&self) -> &'static str {
+        if self.is_active() { "\u{1b}[0m" } else { "" }
+    }
+}
+
+/// The screen columns `start..start + width` of `text`, opened with the
+/// styles in effect at `start` and reset at the end if any are still on.
+/// A wide char cut by either edge turns into spaces.
+fn ansi_slice(text: &str, start: usize, width: usize) -> String {
+    if width == 0 {
+        return String::new();
+    }
+
+    let end = start + width;
+    let mut state = SgrState::default();
+    let mut out = String::new();
+    let mut column = 0usize;
+    let mut started = false;
+    for piece in ansi_pieces(text) {
+        if !started && column >= start {
+            out.push_str(&state.opening());
+            started = true;
+        }
+        match piece {
+            AnsiPiece::Escape(sequence) => {
+                state.apply(sequence);
+                if started {
+                    out.push_str(sequence);
+                }
+            }
+            AnsiPiece::Char(ch) => {
+                let next = column + char_display_width(ch);
+                if !started {
+                    if next > start {
+                        out.push_str(&state.opening());
+                        out.push_str(&" ".repeat(next.min(end) - start));
+                        started = true;
+                    }
+                    column = next;
+                    continue;
+                }
+                if next > column && next > end {
+                    // Like `clip_to_width`, never leave half a wide char.
+                    out.push_str(&" ".repeat(end.saturating_sub(column)));
+                    break;
+                }
+                out.push(ch);
+                column = next;
+            }
+        }
+    }
+
+    if started {
+        out.push_str(state.closing());
+    }
+    out
+}
+
+/// What a terminal shows for a line that rewrites itself with bare carriage
+/// returns, as progress bars do: each part overwrites the start of the one
+/// before, and whatever it doesn't cover stays.
+fn collapse_carriage_returns(line: &str) -> String {
+    let mut parts = line.split('\r');
+    let first = parts.next().unwrap_or_default().to_owned();
+    parts.fold(first, |shown, part| {
+        let width = display_width(&strip_ansi(part));
+        let shown_width = display_width(&strip_ansi(&shown));
+        if width >= shown_width {
+            part.to_owned()
+        } else if width == 0 {
+            shown
+        } else {
+            format!("{part}{}", ansi_slice(&shown, width, shown_width - width))
+        }
+    })
+}
+
+/// Drops every CSI sequence except SGR, so captured lines can color themselves
+/// but never move the cursor or change terminal modes under our layout.
+fn strip_non_sgr_csi(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        if ch != '\u{1b}' || chars.peek() != Some(&'[') {
+            out.push(ch);
+            continue;
+        }
+
+        let _ = chars.next();
+        let mut sequence = String::from("\u{1b}[");
+        let mut final_byte = None;
+        for seq_char in chars.by_ref() {
+            sequence.push(seq_char);
+            if is_ansi_final_byte(seq_char) {
+                final_byte = Some(seq_char);
+                break;
+            }
+        }
+
+        if final_byte.map(csi_kind) == Some(CsiKind::Sgr) {
+            out.push_str(&sequence);
+        }
+    }
+
+    out
+}
+
+/// Bar drawn before the highlighted line under `--selection-style margin`.
+const SELECTION_MARGIN: &str = "▌";
+
+/// The highlighted line clipped to `width` columns and styled for `style`.
+/// Only `Margin` keeps the line's own SGR sequences. An `injected` line, one
+/// the tabs in view don't hold, is drawn in a dimmer yellow, and reversed
+/// dimly.
+fn selected_line_text(
+    text: &str,
+    width: usize,
+    style: SelectionStyle,
+    injected: bool,
+    no_color: bool,
+) -> String {
+    let accent = if injected {
+        Color::DarkYellow
+    } else {
+        Color::Yellow
+    };
+    let yellow = format!("\u{1b}[{}m", Colored::ForegroundColor(accent));
+    let default = format!("\u{1b}[{}m", Colored::ForegroundColor(Color::Reset));
+    match style {
+        SelectionStyle::Recolor => format!(
+            "{}{}{}",
+            yellow,
+            clip_to_width(&strip_ansi(text), width),
+            default
+        ),
+        SelectionStyle::Reverse if injected => format!(
+            "\u{1b}[{};{}m{}\u{1b}[{};{}m",
+            Attribute::Reverse.sgr(),
+            Attribute::Dim.sgr(),
+            clip_to_width(&strip_ansi(text), width),
+            Attribute::NoReverse.sgr(),
+            Attribute::NormalIntensity.sgr()
+        ),
+        SelectionStyle::Reverse => format!(
+            "\u{1b}[{}m{}\u{1b}[{}m",
+            Attribute::Reverse.sgr(),
+            clip_to_width(&strip_ansi(text), width),
+            Attribute::NoReverse.sgr()
+        ),
+        SelectionStyle::Margin if width == 0 => String::new(),
+        SelectionStyle::Margin => {
+            let rest = clip_ansi_to_visible_width(text, width - 1);
+            if no_color {
+                format!("{}{}", SELECTION_MARGIN, rest)
+            } else {
+                format!("{}{}{}{}", yellow, SELECTION_MARGIN, default, rest)
+            }
+        }
+    }
+}
+
+/// Re-applies `background` after each SGR sequence in `text` that resets it,
+/// so a line's own colors don't punch holes in a tinted row.
+fn reapply_background(text: &str, background: Color) -> String {
+    let restore = format!("\u{1b}[{}m", Colored::BackgroundColor(background));
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        out.push(ch);
+        if ch != '\u{1b}' || chars.peek() != Some(&'[') {
+            continue;
+        }
+
+        out.extend(chars.next());
+        let mut params = String::new();
+        let mut final_byte = None;
+        for seq_char in chars.by_ref() {
+            out.push(seq_char);
+            if is_ansi_final_byte(seq_char) {
+                final_byte = Some(seq_char);
+                break;
+            }
+            params.push(seq_char);
+        }
+
+        let resets = params
+            .split(';')
+            .any(|param| matches!(param, "" | "0" | "49"));
+        if final_byte.map(csi_kind) == Some(CsiKind::Sgr) && resets {
+            out.push_str(&restore);
+        }
+    }
+
+    out
+}
+
+#[cfg(unix)]
+unsafe extern "C" {
+    fn wcwidth(ch: libc::wchar_t) -> libc::c_int;
+}
+
+#[cfg(unix)]
+fn ensure_locale_for_wcwidth() {
+    static INIT: OnceLock<()> = OnceLock::new();
+    INIT.get_or_init(|| {
+        let empty = b"\0";
+        // Respect LC_* / LANG so width for East Asian characters is computed correctly.
+        let _ = unsafe { libc::setlocale(libc::LC_CTYPE, empty.as_ptr().cast()) };
+        // Lines are drawn as UTF-8 whatever the locale, so one that knows no
+        // width for `─` (`C`, `POSIX`, or one not set at all) gives way to a
+        // UTF-8 one. Otherwise widths, and the layout, would depend on `LANG`.
+        for fallback in [&b"C.UTF-8\0"[..], b"C.utf8\0", b"en_US.UTF-8\0"] {
+            if unsafe { wcwidth('─' as libc::wchar_t) } >= 0 {
+                break;
+            }
+            let _ = unsafe { libc::setlocale(libc::LC_CTYPE, fallback.as_ptr().cast()) };
+        }
+    });
+}
+
+fn char_display_width(ch: char) -> usize {
+    #[cfg(unix)]
+    {
+        ensure_locale_for_wcwidth();
+        // `wcwidth` returns terminal column width for a Unicode scalar value.
+        let width = unsafe { wcwidth(ch as libc::wchar_t) };
+        if width < 0 { 0 } else { width as usize }
+    }
+
+    #[cfg(not(unix))]
+    {
+        if ch.is_control() { 0 } else { 1 }
+    }
+}
+
+fn display_width(text: &str) -> usize {
+    text.chars().map(char_display_width).sum()
+}
+
+fn split_at_display_width(text: &str, width: usize) -> (&str, &str) {
+    let mut visible = 0usize;
+    for (byte_index, ch) in text.char_indices() {
+        let ch_width = char_display_width(ch);
+        if ch_width > 0 && visible + ch_width > width {
+            // Always make progress, even if a single wide char exceeds the width.
+            let split_at = if byte_index == 0 {
+                ch.len_utf8()
+            } else {
+                byte_index
+            };
+            return text.split_at(split_at);
+        }
+        visible += ch_width;
+    }
+
+    (text, "")
+}
+
+fn wrap_words(text: &str, width: usize) -> Vec<String> {
+    if width == 0 {
+        return Vec::new();
+    }
+
+    let mut rows = Vec::new();
+    let mut current = String::new();
+    let mut current_width = 0usize;
+
+    for word in text.split(' ') {
+        let word_width = display_width(word);
+        if current_width > 0 && current_width + 1 + word_width > width {
+            rows.push(std::mem::take(&mut current));
+            current_width = 0;
+        }
+
+        if current_width == 0 {
+            // Spaces that caused a wrap are not carried to the start of the next row.
+            if word.is_empty() && !rows.is_empty() {
+                continue;
+            }
+        } else {
+            current.push(' ');
+            current_width += 1;
+        }
+
+        let mut rest = word;
+        while display_width(rest) > width.saturating_sub(current_width) {
+            if current_width > 0 {
+                rows.push(std::mem::take(&mut current));
+                current_width = 0;
+            }
+            let (head, tail) = split_at_display_width(rest, width);
+            rows.push(head.to_owned());
+            rest = tail;
+        }
+
+        current.push_str(rest);
+        current_width += display_width(rest);
+    }
+
+    if current_width > 0 || rows.is_empty() {
+        rows.push(current);
+    }
+
+    rows
+}
+
+/// Wraps `text` into rows `width` columns wide, breaking after the last space
+/// that fits, or mid-word when there is none. Colors carry across breaks:
+/// each row restarts the SGR sequences still in effect and resets them at its
+/// end.
+fn wrap_ansi(text: &str, width: usize) -> Vec<String> {
+    let width = width.max(1);
+    let mut rows = Vec::new();
+    let mut row = String::new();
+    let mut row_width = 0usize;
+    let mut active = SgrState::default();
+    // Where the row can break: the byte just past its last space, the width
+    // before it and the sequences in effect there.
+    let mut last_break: Option<(usize, usize, SgrState)> = None;
+    let finish = |row: &mut String, active: &SgrState| {
+        let mut done = std::mem::take(row);
+        done.push_str(active.closing());
+        *row = active.opening();
+        done
+    };
+
+    for piece in ansi_pieces(text) {
+        let ch = match piece {
+            AnsiPiece::Escape(sequence) => {
+                active.apply(sequence);
+                row.push_str(sequence);
+                continue;
+            }
+            AnsiPiece::Char(ch) => ch,
+        };
+
+        let ch_width = char_display_width(ch);
+        if ch_width > 0 && row_width > 0 && row_width + ch_width > width {
+            match last_break.take() {
+                Some((at, break_width, break_active)) => {
+                    let tail = row.split_off(at);
+                    rows.push(finish(&mut row, &break_active));
+                    row.push_str(&tail);
+                    row_width -= break_width;
+                }
+                None => {
+                    rows.push(finish(&mut row, &active));
+                    row_width = 0;
+                }
+            }
+            // A wide char can still overflow what's left after the last word.
+            if row_width > 0 && row_width + ch_width > width {
+                rows.push(finish(&mut row, &active));
+                row_width = 0;
+            }
+        }
+        row.push(ch);
+        row_width += ch_width;
+        if ch == ' ' {
+            last_break = Some((row.len(), row_width, active.clone()));
+        }
+    }
+    rows.push(row);
+    rows
+}
+
+/// Byte range of the chars in plain `text` that cover any of the screen columns
+/// `start..=end`. A wide char is included if either of its columns is covered.
+fn column_range_byte_span(text: &str, start: usize, end: usize) -> std::ops::Range<usize> {
+    let mut column = 0usize;
+    let mut span_start = None;
+    let mut span_end = text.len();
+
+    for (byte_index, ch) in text.char_indices() {
+        let ch_width = char_display_width(ch).max(1);
+        let last_column = column + ch_width - 1;
+        if last_column >= start && span_start.is_none() {
+            span_start = Some(byte_index);
+        }
+        if column > end {
+            span_end = byte_index;
+            break;
+        }
+        column += ch_width;
+    }
+
+    let span_start = span_start.unwrap_or(text.len());
+    span_start..span_end.max(span_start)
+}
+
+fn column_range_text(text: &str, start: usize, end: usize) -> String {
+    let plain = strip_ansi(text);
+    plain[column_range_byte_span(&plain, start, end)].to_owned()
+}
+
+fn base64_encode(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let b = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let n = ((b[0] as u32) << 16) | ((b[1] as u32) << 8) | b[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[((n >> (18 - 6 * i)) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// Most chars of a tab's label a dump's file name keeps: even in four-byte
+/// characters, the name stays under the usual 255-byte limit.
+const DUMP_LABEL_MAX_CHARS: usize = 48;
+
+/// Writes every line `tab` stores to a new `streamtabs-<label>-<time>.log`
+/// in `dir`, without colors unless `colors`. A name already taken, as by a
+/// second dump within the same second, gets a `-1`, `-2`, ... suffix rather
+/// than being overwritten. Long labels are cut short. Returns the file's
+/// path and the number of lines written.
+fn dump_tab(tab: &Tab, dir: &Path, colors: bool, now: SystemTime) -> io::Result<(PathBuf, usize)> {
+    let label = tab
+        .label
+        .chars()
+        .map(|ch| if ch.is_alphanumeric() { ch } else { '_' })
+        .take(DUMP_LABEL_MAX_CHARS)
+        .collect::<String>();
+    let (year, month, day, hour, minute, second) = civil_time(now);
+    let stem = format!(
+        "streamtabs-{}-{:04}{:02}{:02}-{:02}{:02}{:02}",
+        label, year, month, day, hour, minute, second
+    );
+    let mut path = dir.join(format!("{}.log", stem));
+    let mut taken = 0;
+    let file = loop {
+        match std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+        {
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {
+                taken += 1;
+                path = dir.join(format!("{}-{}.log", stem, taken));
+            }
+            file => break file?,
+        }
+    };
+    let mut out = io::BufWriter::new(file);
+    for line in &tab.lines {
+        if colors {
+            writeln!(out, "{}", line.text)?;
+        } else {
+            writeln!(out, "{}", strip_ansi(&line.text))?;
+        }
+    }
+    out.flush()?;
+    Ok((path, tab.lines.len()))
+}
+
+/// Most text one OSC 52 sequence carries: terminals and multiplexers drop
+/// longer ones (tmux's and xterm's limits are around 100 000 base64 bytes).
+const CLIPBOARD_MAX_BYTES: usize = 74_000;
+
+/// The start of `text` that fits in `CLIPBOARD_MAX_BYTES`, cut at a char
+/// boundary.
+fn clipboard_payload(text: &str) -> &str {
+    let mut end = text.len().min(CLIPBOARD_MAX_BYTES);
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    &text[..end]
+}
+
+/// Sets the system clipboard through the terminal (OSC 52), which also works
+/// over SSH. Returns what was copied, which is all of `text` unless it's
+/// past the size limit; see `clipboard_payload`.
+fn copy_to_clipboard<'a>(stdout: &mut Stdout, text: &'a str) -> io::Result<&'a str> {
+    let payload = clipboard_payload(text);
+    queue!(
+        stdout,
+        Print(format!(
+            "\u{1b}]52;c;{}\u{7}",
+            base64_encode(payload.as_bytes())
+        ))
+    )?;
+    stdout.flush()?;
+    Ok(payload)
+}
+
+/// The status after copying `copied` out of `text`.
+fn copied_message(copied: &str, text: &str) -> StatusMessage {
+    let count = copied.chars().count();
+    if copied.len() < text.len() {
+        StatusMessage::error(format!(
+            "copied the first {} of {} chars (clipboard size limit)",
+            count,
+            text.chars().count()
+        ))
+    } else {
+        StatusMessage::info(format!("copied {} chars", count))
+    }
+}
+
+fn clip_ansi_to_visible_width(text: &str, width: usize) -> String {
+    ansi_slice(text, 0, width)
+}
+
+fn strip_ansi(text: &str) -> String {
+    ansi_pieces(text)
+        .filter_map(|piece| match piece {
+            AnsiPiece::Char(ch) => Some(ch),
+            AnsiPiece::Escape(_) => None,
+        })
+        .collect()
+}
+
+fn clip_with_ellipsis(text: &str, width: usize) -> String {
+    if width == 0 {
+        return String::new();
+    }
+
+    if display_width(text) <= width {
+        return text.to_owned();
+    }
+
+    if width <= 3 {
+        return ".".repeat(width);
+    }
+
+    let mut out = clip_to_width(text, width - 3);
+    out.push_str("...");
+    out
+}
+
+fn fit_tab_title(label: &str, width: usize) -> String {
+    match width {
+        0 => String::new(),
+        1 => " ".to_owned(),
+        2 => "  ".to_owned(),
+        _ => {
+            let clipped = clip_with_ellipsis(label, width - 2);
+            let mut piece = format!(" {} ", clipped);
+            let count = display_width(&piece);
+            if count < width {
+                piece.push_str(&" ".repeat(width - count));
+            } else if count > width {
+                piece = clip_to_width(&piece, width);
+            }
+            piece
+        }
+    }
+}
+
+/// What each tab's badge slot shows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum BadgeLayout {
+    #[default]
+    Unread,
+    /// Total matches followed by unread, e.g. `  123 •4   `.
+    TotalAndUnread,
+}
+
+impl BadgeLayout {
+    fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "unread" => Ok(BadgeLayout::Unread),
+            "total" => Ok(BadgeLayout::TotalAndUnread),
+            _ => Err(format!(
+                "invalid --badges: {} (expected unread or total)",
+                value
+            )),
+        }
+    }
+}
+
+/// How the highlighted line stands out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum SelectionStyle {
+    /// Yellow text, dropping the line's own colors.
+    #[default]
+    Recolor,
+    /// Reverse video, also dropping the line's own colors.
+    Reverse,
+    /// A yellow bar before the text, which keeps its own colors.
+    Margin,
+}
+
+impl SelectionStyle {
+    fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "recolor" => Ok(SelectionStyle::Recolor),
+            "reverse" => Ok(SelectionStyle::Reverse),
+            "margin" => Ok(SelectionStyle::Margin),
+            _ => Err(format!(
+                "invalid --selection-style: {} (expected recolor, reverse or margin)",
+                value
+            )),
+        }
+    }
+}
+
+/// Renders a badge slot. Each layout has a fixed width whatever the counts, so
+/// tab widths only change with their labels.
+fn format_badge(total: u64, unread: u64, layout: BadgeLayout) -> String {
+    let unread_badge = match unread {
+        0 => String::new(),
+        1..=999 => format!("•{}", unread),
+        _ => "•999+".to_owned(),
+    };
+
+    match layout {
+        BadgeLayout::Unread => format!("{:>6}", unread_badge),
+        BadgeLayout::TotalAndUnread => {
+            let total_badge = match total {
+                0..=9999 => total.to_string(),
+                10_000..=999_999 => format!("{}k", total / 1000),
+                _ => "1M+".to_owned(),
+            };
+            format!(" {:>4} {:<5}", total_badge, unread_badge)
+        }
+    }
+}
+
+/// Columns the whole tab bar needs when every label is shown in full.
+fn full_tab_bar_width(tabs: &[Tab], number_offset: usize, layout: BadgeLayout) -> usize {
+    let badge_width = format_badge(0, 0, layout).chars().count();
+    let tabs_width = tabs
+        .iter()
+        .enumerate()
+        .map(|(i, tab)| {
+            let number_width = tab_shortcut_label(i, number_offset).chars().count() + 2;
+            // Borders, number, padded title, badge and the trailing space.
+            2 + number_width + display_width(&tab.label) + 2 + badge_width + 1
+        })
+        .sum::<usize>();
+    tabs_width + tabs.len().saturating_sub(1)
+}
+
+/// Columns tab `index` takes when its label is shown in full, as `draw` lays it out.
+fn drawn_tab_width(tab: &Tab, index: usize, number_offset: usize, layout: BadgeLayout) -> usize {
+    let number_width = tab_shortcut_label(index, number_offset).chars().count() + 2;
+    let badge = format_badge(tab.total_matches, tab.unread_matches(), layout);
+    // Borders, number, padded title, badge and the trailing space.
+    2 + number_width + display_width(&tab.label) + 2 + badge.chars().count() + 1
+}
+
+/// Shown at either end of the tab bar when `hidden` tabs are scrolled out
+/// of view on that side.
+fn tab_overflow_marker(hidden: usize, before: bool) -> String {
+    format!("{}{}", if before { "‹" } else { "›" }, hidden)
+}
+
+/// Columns an overflow marker and its spacing take; none when nothing is hidden.
+fn tab_overflow_width(hidden: usize) -> usize {
+    if hidden == 0 {
+        0
+    } else {
+        display_width(&tab_overflow_marker(hidden, false)) + 1
+    }
+}
+
+/// The first tab the bar draws: `scroll`, or when `follow` names the active
+/// tab, moved just far enough that it fits in `limit` columns with its full
+/// label and the overflow markers.
+fn tab_strip_start(
+    tabs: &[Tab],
+    number_offset: usize,
+    layout: BadgeLayout,
+    limit: usize,
+    scroll: usize,
+    follow: Option<usize>,
+) -> usize {
+    let mut first = scroll.min(tabs.len().saturating_sub(1));
+    let Some(active) = follow.filter(|&active| active < tabs.len()) else {
+        return first;
+    };
+    first = first.min(active);
+    let width_through_active = |first: usize| {
+        let tabs_width = (first..=active)
+            .map(|index| drawn_tab_width(&tabs[index], index, number_offset, layout) + 1)
+            .sum::<usize>();
+        tab_overflow_width(first) + tabs_width + tab_overflow_width(tabs.len() - active - 1)
+    };
+    while first < active && width_through_active(first) > limit {
+        first += 1;
+    }
+    first
+}
+
+/// The total-and-unread layout only applies while it lets every tab show its
+/// full label; narrower bars fall back to unread-only badges.
+fn effective_badge_layout(
+    tabs: &[Tab],
+    number_offset: usize,
+    tab_cols_limit: usize,
+    layout: BadgeLayout,
+) -> BadgeLayout {
+    if layout == BadgeLayout::TotalAndUnread
+        && full_tab_bar_width(tabs, number_offset, layout) > tab_cols_limit
+    {
+        BadgeLayout::Unread
+    } else {
+        layout
+    }
+}
+
+fn first_body_row(body_start_row: usize, body_height: usize, visible_count: usize) -> usize {
+    body_start_row + body_height.saturating_sub(visible_count)
+}
+
+/// What the `0` key does when there is no `(all)` tab to jump to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum ZeroKey {
+    /// Show every filter tab at once in an OR view.
+    #[default]
+    AllFilters,
+    LastTab,
+    Unmapped,
+}
+
+impl ZeroKey {
+    fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "all-filters" => Ok(ZeroKey::AllFilters),
+            "last" => Ok(ZeroKey::LastTab),
+            "none" => Ok(ZeroKey::Unmapped),
+            _ => Err(format!(
+                "invalid --zero-key: {} (expected all-filters, last or none)",
+                value
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DigitTarget {
+    Tab(usize),
+    AllTabs,
+    Nothing,
+}
+
+/// Tabs are numbered from `0` when an `(all)` tab leads the bar, else from `1`.
+fn tab_number_offset(tabs: &[Tab]) -> usize {
+    if tabs
+        .first()
+        .is_some_and(|tab| matches!(tab.mode, MatchMode::All))
+    {
+        0
+    } else {
+        1
+    }
+}
+
+fn digit_target(number: usize, tabs: &[Tab], zero_key: ZeroKey) -> DigitTarget {
+    let offset = tab_number_offset(tabs);
+    if number == 0 && offset == 1 {
+        return match zero_key {
+            ZeroKey::AllFilters => DigitTarget::AllTabs,
+            ZeroKey::LastTab => tabs
+                .len()
+                .checked_sub(1)
+                .map_or(DigitTarget::Nothing, DigitTarget::Tab),
+            ZeroKey::Unmapped => DigitTarget::Nothing,
+        };
+    }
+
+    let index = number - offset;
+    if index < tabs.len() {
+        DigitTarget::Tab(index)
+    } else {
+        DigitTarget::Nothing
+    }
+}
+
+fn tab_shortcut_label(index: usize, offset: usize) -> String {
+    (index + offset).to_string()
+}
+
+/// The tab bar's notes on the view. `paused` says for how long; the time is
+/// left out for the first second, and with glyphs.
+fn view_mode_label(
+    paused: Option<Duration>,
+    input_held: bool,
+    unread_only: bool,
+    glyphs: bool,
+) -> String {
+    let mut label = String::new();
+    if glyphs {
+        // Leading space keeps the glyphs off the last tab's border.
+        for (on, glyph) in [
+            (paused.is_some(), PAUSED_GLYPH),
+            (input_held, INPUT_HELD_GLYPH),
+            (unread_only, UNREAD_ONLY_GLYPH),
+        ] {
+            if on {
+                label.push(' ');
+                label.push_str(glyph);
+            }
+        }
+        return label;
+    }
+
+    match paused.map(|elapsed| elapsed.as_secs()) {
+        Some(0) => label.push_str(PAUSED_LABEL),
+        Some(secs) if secs < 3600 => {
+            label.push_str(&format!(" (paused {:02}:{:02})", secs / 60, secs % 60));
+        }
+        Some(secs) => label.push_str(&format!(
+            " (paused {}:{:02}:{:02})",
+            secs / 3600,
+            secs / 60 % 60,
+            secs % 60
+        )),
+        None => {}
+    }
+    if input_held {
+        label.push_str(INPUT_HELD_LABEL);
+    }
+    if unread_only {
+        label.push_str(UNREAD_ONLY_LABEL);
+    }
+    label
+}
+
+/// Tab bar note once the `--exec` command has ended.
+fn exit_label(status: ExitStatus, glyphs: bool) -> String {
+    if glyphs {
+        return format!(" {}", EXITED_GLYPH);
+    }
+    match status.code() {
+        Some(code) => format!(" (exited {})", code),
+        None => " (killed)".to_owned(),
+    }
+}
+
+fn describe_exit(status: ExitStatus) -> String {
+    if let Some(code) = status.code() {
+        return format!("process exited with code {}", code);
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        if let Some(signal) = status.signal() {
+            return format!("process killed by signal {}", signal);
+        }
+    }
+    "process exited".to_owned()
+}
+
+/// Approximate buffer memory, counting only the text of stored lines.
+fn memory_label(tabs: &[Tab], history: &History) -> String {
+    let bytes = history.stored_bytes + tabs.iter().map(|tab| tab.stored_bytes).sum::<usize>();
+    format!(" mem: {}", format_bytes(bytes))
+}
+
+fn format_bytes(bytes: usize) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < UNITS.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{}B", bytes)
+    } else {
+        format!("{:.1}{}", value, UNITS[unit])
+    }
+}
+
+fn tab_columns_limit(total_cols: usize, mode_label: &str) -> usize {
+    total_cols.saturating_sub(display_width(mode_label))
+}
+
+/// Text labels follow the last tab; glyphs sit in the top-right corner.
+fn mode_label_column(tabs_right: u16, total_cols: usize, mode_label: &str, glyphs: bool) -> u16 {
+    if glyphs {
+        return total_cols.saturating_sub(display_width(mode_label)) as u16;
+    }
+
+    if tabs_right > 0 {
+        tabs_right.saturating_add(1)
+    } else {
+        0
+    }
+}
+
+fn draw_piece_clipped(
+    stdout: &mut Screen,
+    x: &mut u16,
+    y: u16,
+    remaining: &mut usize,
+    text: &str,
+    color: Option<Color>,
+) -> io::Result<()> {
+    if *remaining == 0 {
+        return Ok(());
+    }
+
+    let shown = clip_to_width(text, *remaining);
+    if shown.is_empty() {
+        return Ok(());
+    }
+
+    let width = display_width(&shown);
+    stdout.at(*x, y);
+    if let Some(color) = color {
+        queue!(stdout, SetForegroundColor(color), Print(&shown), ResetColor)?;
+    } else {
+        queue!(stdout, Print(&shown))?;
+    }
+
+    *x = x.saturating_add(width as u16);
+    *remaining = remaining.saturating_sub(width);
+    Ok(())
+}
+
+fn inject_selected_line<'a>(
+    lines: &mut Vec<RenderedLine<'a>>,
+    selected_line: Option<&'a SelectedLine>,
+) {
+    if let Some(selected) = selected_line {
+        if let Some(existing) = lines.iter_mut().find(|line| line.seq == selected.seq) {
+            existing.selected = true;
+        } else {
+            let insert_at = lines
+                .iter()
+                .position(|line| line.seq > selected.seq)
+                .unwrap_or(lines.len());
+            lines.insert(
+                insert_at,
+                RenderedLine {
+                    seq: selected.seq,
+                    text: Cow::Borrowed(&selected.text),
+                    selected: true,
+                    injected: true,
+                    separator: None,
+                },
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+fn prepare_visible_lines<'a>(
+    tab: &'a Tab,
+    cutoff_len: usize,
+    selected_line: Option<&'a SelectedLine>,
+) -> Vec<RenderedLine<'a>> {
+    let mut lines = tab
+        .lines
+        .iter()
+        .take(cutoff_len)
+        .map(|line| RenderedLine {
+            seq: line.seq,
+            text: Cow::Borrowed(&line.text),
+            selected: false,
+            injected: false,
+            separator: None,
+        })
+        .collect::<Vec<_>>();
+
+    inject_selected_line(&mut lines, selected_line);
+    lines
+}
+
+fn prepare_visible_lines_for_tabs<'a>(
+    tabs: &'a [Tab],
+    active_tab_indices: &[usize],
+    pause_line_cutoffs: Option<&[usize]>,
+    selected_line: Option<&'a SelectedLine>,
+    unread_only: bool,
+) -> Vec<RenderedLine<'a>> {
+    let mut merged_lines = BTreeMap::new();
+
+    for &tab_index in active_tab_indices {
+        let Some(tab) = tabs.get(tab_index) else {
+            continue;
+        };
+
+        let cutoff_len = pause_line_cutoffs
+            .and_then(|cutoffs| cutoffs.get(tab_index).copied())
+            .unwrap_or(tab.lines.len())
+            .min(tab.lines.len());
+        let skip = if unread_only {
+            tab.lines_read_before_view()
+        } else {
+            0
+        };
+        for line in tab.lines.iter().take(cutoff_len).skip(skip) {
+            merged_lines.entry(line.seq).or_insert(line.text.as_str());
+        }
+    }
+
+    let mut lines = merged_lines
+        .into_iter()
+        .map(|(seq, text)| RenderedLine {
+            seq,
+            text: Cow::Borrowed(text),
+            selected: false,
+            injected: false,
+            separator: None,
+        })
+        .collect::<Vec<_>>();
+    inject_selected_line(&mut lines, selected_line);
+    lines
+}
+
+/// Shows JSON lines through `--json-template`; other lines are left as they are.
+fn apply_json_template(lines: &mut [RenderedLine], template: Option<&str>) {
+    #[cfg(feature = "json")]
+    if let Some(template) = template {
+        for line in lines.iter_mut() {
+            if let Some(text) = json_input::render(&line.text, template) {
+                line.text = Cow::Owned(text);
+            }
+        }
+    }
+    #[cfg(not(feature = "json"))]
+    let _ = (lines, template);
+}
+
+/// Terms to highlight in the body: those of every active `any:` tab.
+fn active_highlight_terms<'a>(
+    tabs: &'a [Tab],
+    active_tab_indices: &[usize],
+) -> Vec<&'a HighlightTerm> {
+    active_tab_indices
+        .iter()
+        .filter_map(|&index| match &tabs.get(index)?.mode {
+            MatchMode::AnyOf(terms) => Some(terms),
+            _ => None,
+        })
+        .flatten()
+        .collect()
+}
+
+/// Regexes of the shown `re:` tabs that have capture groups to color.
+fn active_group_regexes<'a>(tabs: &'a [Tab], active_tab_indices: &[usize]) -> Vec<&'a Regex> {
+    active_tab_indices
+        .iter()
+        .filter_map(|&index| match &tabs.get(index)?.mode {
+            MatchMode::Regex(regex) if regex.captures_len() > 1 => Some(regex),
+            _ => None,
+        })
+        .collect()
+}
+
+/// The search queries of the shown tabs, highlighted where they occur.
+fn search_highlight_terms(tabs: &[Tab], active_tab_indices: &[usize]) -> Vec<HighlightTerm> {
+    active_tab_indices
+        .iter()
+        .filter_map(|&index| tabs.get(index)?.search.as_ref())
+        .filter(|search| !search.query.is_empty())
+        .map(|search| HighlightTerm {
+            text: search.query.clone(),
+            style: HighlightStyle::Search,
+        })
+        .collect()
+}
+
+/// The needles of the shown substring tabs, and whether each ignores case.
+fn active_filter_needles<'a>(
+    tabs: &'a [Tab],
+    active_tab_indices: &[usize],
+) -> Vec<(&'a str, bool)> {
+    active_tab_indices
+        .iter()
+        .filter_map(|&index| match &tabs.get(index)?.mode {
+            MatchMode::Contains(needle) => Some((needle.as_str(), false)),
+            MatchMode::ContainsCaseInsensitive(needle) => Some((needle.as_str(), true)),
+            _ => None,
+        })
+        .filter(|(needle, _)| !needle.is_empty())
+        .collect()
+}
+
+/// Wraps each occurrence of the needles in bold, leaving the line's own
+/// escape sequences where they are so its colors survive. Matching runs on
+/// the visible text, so a needle split by a color change is still found.
+/// Case is only ignored on ASCII lines, where lowercasing keeps offsets.
+/// Ending the bold also turns off the line's own, so its style is replayed.
+fn embolden_needles(text: &str, needles: &[(&str, bool)]) -> String {
+    // The visible text, and where each of its bytes sits in `text`.
+    let mut plain = String::new();
+    let mut raw_at = Vec::new();
+    let mut index = 0;
+    for piece in ansi_pieces(text) {
+        match piece {
+            AnsiPiece::Escape(sequence) => index += sequence.len(),
+            AnsiPiece::Char(ch) => {
+                plain.push(ch);
+                raw_at.extend((0..ch.len_utf8()).map(|offset| index + offset));
+                index += ch.len_utf8();
+            }
+        }
+    }
+    raw_at.push(text.len());
+
+    let lowered = plain.is_ascii().then(|| plain.to_ascii_lowercase());
+    let mut spans = needles
+        .iter()
+        .filter_map(|&(needle, ignore_case)| {
+            let haystack = if ignore_case {
+                lowered.as_deref()?
+            } else {
+                plain.as_str()
+            };
+            Some(
+                haystack
+                    .match_indices(needle)
+                    .map(|(start, found)| start..start + found.len())
+                    .collect::<Vec<_>>(),
+            )
+        })
+        .flatten()
+        .collect::<Vec<_>>();
+    if spans.is_empty() {
+        return text.to_owned();
+    }
+    spans.sort_by_key(|span| span.start);
+
+    let mut out = String::with_capacity(text.len() + spans.len() * 9);
+    let mut copied = 0;
+    let mut bold_until = 0;
+    for span in spans {
+        if span.start < bold_until {
+            // Overlaps the previous match; stretch it instead.
+            bold_until = bold_until.max(span.end);
+            continue;
+        }
+        if bold_until > 0 {
+            push_emboldened(&mut out, &text[copied..raw_at[bold_until]]);
+            out.push_str("\x1b[22m");
+            out.push_str(&SgrState::after(&text[..raw_at[bold_until]]).opening());
+            copied = raw_at[bold_until];
+        }
+        out.push_str(&text[copied..raw_at[span.start]]);
+        out.push_str("\x1b[1m");
+        copied = raw_at[span.start];
+        bold_until = span.end;
+    }
+    push_emboldened(&mut out, &text[copied..raw_at[bold_until]]);
+    out.push_str("\x1b[22m");
+    out.push_str(&SgrState::after(&text[..raw_at[bold_until]]).opening());
+    out.push_str(&text[raw_at[bold_until]..]);
+    out
+}
+
+/// Copies a matched run for `embolden_needles`, turning bold back on after
+/// each of the line's own sequences that turns it off.
+fn push_emboldened(out: &mut String, raw: &str) {
+    for piece in ansi_pieces(raw) {
+        match piece {
+            AnsiPiece::Escape(sequence) => {
+                out.push_str(sequence);
+                if ends_bold(sequence) {
+                    out.push_str("\x1b[1m");
+                }
+            }
+            AnsiPiece::Char(ch) => out.push(ch),
+        }
+    }
+}
+
+/// Whether an SGR sequence resets or clears bold. Any `0` or `22`
+/// parameter counts, even a color's, since bold again is harmless there.
+fn ends_bold(sequence: &str) -> bool {
+    let Some(params) = sequence
+        .strip_prefix("\x1b[")
+        .and_then(|rest| rest.strip_suffix('m'))
+    else {
+        return false;
+    };
+    params
+        .split(';')
+        .any(|param| matches!(param, "" | "0" | "22"))
+}
+
+/// Non-overlapping byte ranges of term occurrences in `text`, left to right.
+/// Where matches overlap, the earliest wins, then the longest.
+fn highlight_spans(
+    text: &str,
+    terms: &[&HighlightTerm],
+) -> Vec<(std::ops::Range<usize>, HighlightStyle)> {
+    let mut spans = Vec::new();
+    let mut pos = 0;
+    while pos < text.len() {
+        let next = terms
+            .iter()
+            .filter_map(|term| {
+                let start = pos + text[pos..].find(&term.text)?;
+                Some((start, term.text.len(), term.style))
+            })
+            .min_by_key(|(start, len, _)| (*start, std::cmp::Reverse(*len)));
+        let Some((start, len, style)) = next else {
+            break;
+        };
+        spans.push((start..start + len, style));
+        pos = start + len;
+    }
+    spans
+}
+
+/// Byte ranges of capture groups in `text`, each colored by its group's
+/// index, cut off at `shown` bytes. Groups found by matching the whole line
+/// keep the spans they would have in it, even when the row is clipped.
+/// Nested or overlapping groups give way to the earliest, then the longest.
+fn group_spans(
+    text: &str,
+    regexes: &[&Regex],
+    shown: usize,
+) -> Vec<(std::ops::Range<usize>, HighlightStyle)> {
+    let mut spans = regexes
+        .iter()
+        .flat_map(|regex| regex.captures_iter(text))
+        .flat_map(|captures| {
+            (1..captures.len())
+                .filter_map(|group| {
+                    let found = captures.get(group)?;
+                    let color = HIGHLIGHT_COLORS[(group - 1) % HIGHLIGHT_COLORS.len()];
+                    Some((
+                        found.start()..found.end().min(shown),
+                        HighlightStyle::Term(color),
+                    ))
+                })
+                .collect::<Vec<_>>()
+        })
+        .filter(|(span, _)| span.start < span.end)
+        .collect::<Vec<_>>();
+    spans.sort_by_key(|(span, _)| (span.start, std::cmp::Reverse(span.end)));
+    let mut kept: Vec<(std::ops::Range<usize>, HighlightStyle)> = Vec::with_capacity(spans.len());
+    for (span, style) in spans {
+        if kept.last().is_none_or(|(last, _)| span.start >= last.end) {
+            kept.push((span, style));
+        }
+    }
+    kept
+}
+
+/// Adds `extra` spans to `spans` where they don't overlap one already there,
+/// keeping the result in order.
+fn merge_spans(
+    mut spans: Vec<(std::ops::Range<usize>, HighlightStyle)>,
+    extra: Vec<(std::ops::Range<usize>, HighlightStyle)>,
+) -> Vec<(std::ops::Range<usize>, HighlightStyle)> {
+    let free = extra
+        .into_iter()
+        .filter(|(span, _)| {
+            !spans
+                .iter()
+                .any(|(taken, _)| span.start < taken.end && taken.start < span.end)
+        })
+        .collect::<Vec<_>>();
+    spans.extend(free);
+    spans.sort_by_key(|(span, _)| span.start);
+    spans
+}
+
+/// Which of several files the line with `seq` came from.
+fn line_source(tabs: &[Tab], active_tab_indices: &[usize], seq: u64) -> Option<usize> {
+    active_tab_indices.iter().find_map(|&index| {
+        let lines = &tabs.get(index)?.lines;
+        let position = lines.binary_search_by_key(&seq, |line| line.seq).ok()?;
+        lines[position].source
+    })
+}
+
+fn line_arrival(tabs: &[Tab], active_tab_indices: &[usize], seq: u64) -> Option<Instant> {
+    active_tab_indices.iter().find_map(|&index| {
+        let lines = &tabs.get(index)?.lines;
+        let position = lines.binary_search_by_key(&seq, |line| line.seq).ok()?;
+        Some(lines[position].arrived)
+    })
+}
+
+/// Puts a separator row before each line that arrived more than `threshold`
+/// after the line above it. Lines without a known arrival (an injected
+/// selection from another tab) neither get nor break a separator.
+fn insert_gap_separators<'a>(
+    lines: Vec<RenderedLine<'a>>,
+    tabs: &[Tab],
+    active_tab_indices: &[usize],
+    threshold: Duration,
+) -> Vec<RenderedLine<'a>> {
+    let mut out = Vec::with_capacity(lines.len());
+    let mut previous_arrival: Option<Instant> = None;
+    for line in lines {
+        if let Some(arrived) = line_arrival(tabs, active_tab_indices, line.seq) {
+            if let Some(previous) = previous_arrival {
+                let gap = arrived.saturating_duration_since(previous);
+                if gap > threshold {
+                    out.push(RenderedLine {
+                        seq: line.seq,
+                        text: Cow::Borrowed(""),
+                        selected: false,
+                        injected: false,
+                        separator: Some(Separator::Gap(gap)),
+                    });
+                }
+            }
+            previous_arrival = Some(arrived);
+        }
+        out.push(line);
+    }
+    out
+}
+
+/// Puts a separator row above each line that follows a run of lines dropped
+/// under `--on-overflow drop`, and below the last line for runs after it.
+/// `skipped` holds the seq each run ends before and its length, in seq
+/// order; runs that end before the first line have nowhere to go.
+fn insert_skip_separators<'a>(
+    lines: Vec<RenderedLine<'a>>,
+    skipped: &[(u64, usize)],
+) -> Vec<RenderedLine<'a>> {
+    if skipped.is_empty() {
+        return lines;
+    }
+    let separator = |&(seq, count): &(u64, usize)| RenderedLine {
+        seq,
+        text: Cow::Borrowed(""),
+        selected: false,
+        injected: false,
+        separator: Some(Separator::Skipped(count)),
+    };
+    let mut out = Vec::with_capacity(lines.len());
+    let mut previous_seq = None;
+    for line in lines {
+        if line.separator.is_none() {
+            let after = previous_seq.unwrap_or(line.seq.saturating_sub(1));
+            let start = skipped.partition_point(|&(seq, _)| seq <= after);
+            let end = skipped.partition_point(|&(seq, _)| seq <= line.seq);
+            out.extend(skipped[start..end].iter().map(separator));
+            previous_seq = Some(line.seq);
+        }
+        out.push(line);
+    }
+    if let Some(last) = previous_seq {
+        let start = skipped.partition_point(|&(seq, _)| seq <= last);
+        out.extend(skipped[start..].iter().map(separator));
+    }
+    out
+}
+
+/// Adds the line still arriving under `--flush-partial` after the others, if
+/// a tab in view would take it. No tab holds it yet: the whole line takes its
+/// place once its newline arrives.
+fn push_partial_line<'a>(
+    lines: &mut Vec<RenderedLine<'a>>,
+    partial_line: Option<(u64, &'a str)>,
+    tabs: &[Tab],
+    tab_indices: &[usize],
+    fold_case: bool,
+    selected_line: Option<&SelectedLine>,
+) {
+    let Some((seq, text)) = partial_line else {
+        return;
+    };
+    let matched = match_text(text, fold_case);
+    if is_blank_line(text)
+        || !tab_indices
+            .iter()
+            .any(|&index| tabs.get(index).is_some_and(|tab| tab.matches(&matched)))
+    {
+        return;
+    }
+    // Highlighting it injected the text it had then; this is newer.
+    if lines.last().is_some_and(|line| line.seq == seq) {
+        lines.pop();
+    }
+    lines.push(RenderedLine {
+        seq,
+        text: Cow::Borrowed(text),
+        selected: selected_line.is_some_and(|line| line.seq == seq),
+        injected: false,
+        separator: None,
+    });
+}
+
+/// Drops the lines on the far side of the marker when the view is sliced.
+fn slice_at_marker(lines: &mut Vec<RenderedLine>, marker: Option<Marker>) {
+    if let Some(marker) = marker {
+        lines.retain(|line| marker.slice.keeps(line.seq, marker.seq));
+    }
+}
+
+/// Puts the marker's separator row above the first line at or after it,
+/// below any gap separator already there.
+fn insert_marker_separator(
+    mut lines: Vec<RenderedLine<'_>>,
+    marker: Option<Marker>,
+) -> Vec<RenderedLine<'_>> {
+    let Some(marker) = marker else {
+        return lines;
+    };
+    let Some(position) = lines
+        .iter()
+        .position(|line| line.separator.is_none() && line.seq >= marker.seq)
+    else {
+        return lines;
+    };
+    lines.insert(
+        position,
+        RenderedLine {
+            seq: marker.seq,
+            text: Cow::Borrowed(""),
+            selected: false,
+            injected: false,
+            separator: Some(Separator::Marker(marker.slice)),
+        },
+    );
+    lines
+}
+
+fn format_gap(gap: Duration) -> String {
+    let secs = gap.as_secs();
+    if secs < 10 {
+        format!("{:.1}s", gap.as_secs_f64())
+    } else if secs < 60 {
+        format!("{}s", secs)
+    } else if secs < 3600 {
+        format!("{}m{:02}s", secs / 60, secs % 60)
+    } else {
+        format!("{}h{:02}m", secs / 3600, secs % 3600 / 60)
+    }
+}
+
+/// Keeps the previous viewport if the selected line would still be on screen in it.
+fn stable_viewport(
+    body_start_row: usize,
+    body_height: usize,
+    lines: &[RenderedLine],
+    selected_index: usize,
+    previous: ViewportAnchor,
+) -> Option<(usize, usize, usize)> {
+    let body_end = body_start_row + body_height;
+    if previous.first_row < body_start_row || previous.first_row >= body_end {
+        return None;
+    }
+
+    let start_index = lines.partition_point(|line| line.seq < previous.start_seq);
+    let count = lines
+        .len()
+        .saturating_sub(start_index)
+        .min(body_end - previous.first_row);
+    (start_index..start_index + count)
+        .contains(&selected_index)
+        .then_some((start_index, count, previous.first_row))
+}
+
+fn viewport_for_lines(
+    body_start_row: usize,
+    body_height: usize,
+    lines: &[RenderedLine],
+    paused: bool,
+    previous: Option<ViewportAnchor>,
+) -> (usize, usize, usize) {
+    let visible_count = lines.len().min(body_height);
+    if visible_count == 0 {
+        return (0, 0, body_start_row);
+    }
+
+    if paused && let Some(selected_index) = lines.iter().position(|line| line.selected) {
+        if let Some(stable) = previous.and_then(|previous| {
+            stable_viewport(body_start_row, body_height, lines, selected_index, previous)
+        }) {
+            return stable;
+        }
+
+        let half = body_height / 2;
+        let mut start_index = selected_index.saturating_sub(half);
+        let max_start = lines.len().saturating_sub(visible_count);
+        if start_index > max_start {
+            start_index = max_start;
+        }
+
+        let selected_row = selected_index.saturating_sub(start_index);
+        let desired_selected_row = body_height / 2;
+        let min_first_row = body_start_row;
+        let max_first_row = body_start_row + body_height.saturating_sub(visible_count);
+        let mut first_row = body_start_row + desired_selected_row.saturating_sub(selected_row);
+        if first_row < min_first_row {
+            first_row = min_first_row;
+        }
+        if first_row > max_first_row {
+            first_row = max_first_row;
+        }
+
+        return (start_index, visible_count, first_row);
+    }
+
+    let start_index = lines.len().saturating_sub(visible_count);
+    let first_row = first_body_row(body_start_row, body_height, visible_count);
+    (start_index, visible_count, first_row)
+}
+
+/// Moves the viewport `delta` rows from the line at `top` (or from
+/// `current_start` when not scrolled). Returns `None` once that reaches the
+/// bottom, where the body follows new lines again.
+fn scrolled_viewport(
+    body_start_row: usize,
+    body_height: usize,
+    lines: &[RenderedLine],
+    top: Option<u64>,
+    delta: isize,
+    current_start: usize,
+) -> Option<(usize, usize, usize)> {
+    let visible_count = lines.len().min(body_height);
+    let max_start = lines.len().saturating_sub(visible_count);
+    let from = top.map_or(current_start, |seq| {
+        lines.partition_point(|line| line.seq < seq)
+    });
+    let start = from.saturating_add_signed(delta).min(max_start);
+    (start < max_start).then_some((start, visible_count, body_start_row))
+}
+
+/// One screen row of a line wrapped onto several.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct WrappedRow {
+    text: String,
+    /// Set on every row but the line's first.
+    continuation: bool,
+}
+
+/// Gives each line wider than `width` a row per wrapped piece. Every row
+/// keeps its line's seq and full text, so a click on any of them selects the
+/// line; the second list holds the text each row draws, or `None` for lines
+/// that fit as they are.
+fn wrap_rendered_lines(
+    lines: Vec<RenderedLine<'_>>,
+    width: usize,
+) -> (Vec<RenderedLine<'_>>, Vec<Option<WrappedRow>>) {
+    let mut rows = Vec::with_capacity(lines.len());
+    let mut wrapped = Vec::with_capacity(lines.len());
+    for line in lines {
+        if line.separator.is_some() || display_width(&strip_ansi(&line.text)) <= width {
+            rows.push(line);
+            wrapped.push(None);
+            continue;
+        }
+        for (index, text) in wrap_ansi(&line.text, width).into_iter().enumerate() {
+            rows.push(line.clone());
+            wrapped.push(Some(WrappedRow {
+                text,
+                continuation: index > 0,
+            }));
+        }
+    }
+    (rows, wrapped)
+}
+
+/// Scrolling keeps its place as a line's seq, which can't say which of a
+/// wrapped line's rows is on top, so a scroll that ends partway through a
+/// line is carried on to that line's edge in the direction of `delta`.
+/// Returns `None` if that reaches the bottom.
+fn snap_to_line_start(
+    (mut start, visible_count, first_row): (usize, usize, usize),
+    rows: &[Option<WrappedRow>],
+    delta: isize,
+) -> Option<(usize, usize, usize)> {
+    let continues = |index: usize| {
+        rows.get(index)
+            .and_then(Option::as_ref)
+            .is_some_and(|row| row.continuation)
+    };
+    while continues(start) {
+        if delta < 0 {
+            start -= 1;
+        } else {
+            start += 1;
+        }
+    }
+    let max_start = rows.len().saturating_sub(visible_count);
+    (start < max_start).then_some((start, visible_count, first_row))
+}
+
+/// What fits of a "terminal too small" notice, or `None` if the screen is big
+/// enough to draw the tabs.
+fn too_small_notice(cols: usize, rows: usize) -> Option<Vec<String>> {
+    if cols >= MIN_COLS && rows >= MIN_ROWS {
+        return None;
+    }
+    let lines = [
+        "terminal too small".to_owned(),
+        format!("need {}x{}", MIN_COLS, MIN_ROWS),
+    ];
+    Some(
+        lines
+            .iter()
+            .take(rows)
+            .map(|line| clip_to_width(line, cols))
+            .collect(),
+    )
+}
+
+/// Where the three-row tab bar goes, and the rows left for the preview row,
+/// body, detail pane and status row. Screens shorter than the bar get no body.
+fn screen_layout(rows: usize, tabs_bottom: bool) -> (u16, std::ops::Range<usize>) {
+    if rows < 3 {
+        return (0, rows..rows);
+    }
+    if tabs_bottom {
+        ((rows - 3) as u16, 0..rows - 3)
+    } else {
+        (0, 3..rows)
+    }
+}
+
+fn tab_index_at_position(render_state: &RenderState, column: u16, row: u16) -> Option<usize> {
+    if row < render_state.bar_top || row > render_state.bar_top + 2 {
+        return None;
+    }
+
+    render_state
+        .tab_hitboxes
+        .iter()
+        .find(|hitbox| column >= hitbox.left && column <= hitbox.right)
+        .map(|hitbox| hitbox.index)
+}
+
+fn line_at_row(render_state: &RenderState, row: u16) -> Option<&RenderedLine<'static>> {
+    render_state
+        .line_rows
+        .get(row as usize)
+        .and_then(|line| line.as_ref())
+}
+
+/// The line `delta` lines from the one with `from_seq`, stopping at either end.
+fn moved_selection(lines: &[RenderedLine], from_seq: u64, delta: isize) -> Option<SelectedLine> {
+    let from = lines.iter().position(|line| line.seq == from_seq)?;
+    let target = from.saturating_add_signed(delta).min(lines.len() - 1);
+    Some(SelectedLine {
+        seq: lines[target].seq,
+        text: lines[target].text.to_string(),
+    })
+}
+
+/// Pins `line`, or unpins it if it already is, and says which it did.
+fn toggle_pinned_line(pinned: &mut Vec<SelectedLine>, line: &RenderedLine) -> StatusMessage {
+    if let Some(index) = pinned.iter().position(|pin| pin.seq == line.seq) {
+        pinned.remove(index);
+        return StatusMessage::info(format!(
+            "unpinned line {} ({} pinned)",
+            line.seq,
+            pinned.len()
+        ));
+    }
+    let at = pinned.partition_point(|pin| pin.seq < line.seq);
+    pinned.insert(
+        at,
+        SelectedLine {
+            seq: line.seq,
+            text: line.text.to_string(),
+        },
+    );
+    StatusMessage::info(format!(
+        "pinned line {} ({} pinned)",
+        line.seq,
+        pinned.len()
+    ))
+}
+
+fn toggle_selected_line(selected_line: &mut Option<SelectedLine>, line: &RenderedLine) {
+    if selected_line.as_ref().map(|current| current.seq) == Some(line.seq) {
+        *selected_line = None;
+    } else {
+        *selected_line = Some(SelectedLine {
+            seq: line.seq,
+            text: line.text.to_string(),
+        });
+    }
+}
+
+/// Returns `true` if a quit was already requested within the confirmation
+/// window; otherwise records this request as the pending one.
+fn quit_confirmed(quit_requested_at: &mut Option<Instant>, now: Instant) -> bool {
+    if quit_requested_at
+        .is_some_and(|requested| now.duration_since(requested) <= QUIT_CONFIRM_WINDOW)
+    {
+        return true;
+    }
+
+    *quit_requested_at = Some(now);
+    false
+}
+
+fn middle_visible_line(render_state: &RenderState) -> Option<&RenderedLine<'static>> {
+    let visible_lines = render_state
+        .line_rows
+        .iter()
+        .filter_map(|line| line.as_ref())
+        .collect::<Vec<_>>();
+    if visible_lines.is_empty() {
+        return None;
+    }
+
+    visible_lines.get(visible_lines.len() / 2).copied()
+}
+
+fn detail_pane_text_rows(
+    selected_line: Option<&SelectedLine>,
+    cols: usize,
+    available_rows: usize,
+) -> Vec<String> {
+    let Some(selected) = selected_line else {
+        return Vec::new();
+    };
+
+    // The pane needs two border rows and must leave at least one body row.
+    let max_text_rows = available_rows
+        .saturating_sub(3)
+        .min(DETAIL_PANE_MAX_TEXT_ROWS);
+    if max_text_rows == 0 || cols < 3 {
+        return Vec::new();
+    }
+
+    let mut rows = wrap_words(&strip_ansi(&selected.text), cols - 2);
+    rows.truncate(max_text_rows);
+    rows
+}
+
+fn draw_detail_pane(
+    stdout: &mut Screen,
+    top_row: u16,
+    cols: usize,
+    text_rows: &[String],
+    footer: Option<&str>,
+) -> io::Result<()> {
+    let inner_width = cols.saturating_sub(2);
+    let horiz = "─".repeat(inner_width);
+    queue!(
+        stdout.at(0, top_row),
+        SetForegroundColor(Color::DarkGrey),
+        Print("╭"),
+        Print(&horiz),
+        Print("╮"),
+        ResetColor
+    )?;
+
+    for (offset, text) in text_rows.iter().enumerate() {
+        let y = top_row + 1 + offset as u16;
+        let pad = inner_width.saturating_sub(display_width(text));
+        queue!(
+            stdout.at(0, y),
+            SetForegroundColor(Color::DarkGrey),
+            Print("│"),
+            ResetColor,
+            SetForegroundColor(Color::Yellow),
+            Print(text),
+            Print(" ".repeat(pad)),
+            ResetColor,
+            SetForegroundColor(Color::DarkGrey),
+            Print("│"),
+            ResetColor
+        )?;
+    }
+
+    let bottom_row = top_row + 1 + text_rows.len() as u16;
+    let bottom = match footer {
+        Some(footer) => {
+            let labelled = clip_ansi_to_visible_width(&format!("─ {} ", footer), inner_width);
+            let pad = inner_width.saturating_sub(display_width(&labelled));
+            format!("{}{}", labelled, "─".repeat(pad))
+        }
+        None => horiz,
+    };
+    queue!(
+        stdout.at(0, bottom_row),
+        SetForegroundColor(Color::DarkGrey),
+        Print("╰"),
+        Print(bottom),
+        Print("╯"),
+        ResetColor
+    )?;
+    Ok(())
+}
+
+fn draw_status_message(
+    stdout: &mut Screen,
+    row: u16,
+    cols: usize,
+    status: &StatusMessage,
+) -> io::Result<()> {
+    queue!(
+        stdout.at(0, row),
+        SetForegroundColor(status.color),
+        Print(clip_to_width(&status.text, cols)),
+        ResetColor
+    )
+}
+
+/// Key hints for the help bar, fitting what the view is doing now.
+fn help_hints(
+    prompt_open: bool,
+    overview: bool,
+    paused: bool,
+    selected: bool,
+) -> Vec<(&'static str, &'static str)> {
+    if prompt_open {
+        return vec![
+            ("Enter", "confirm"),
+            ("Esc", "cancel"),
+            ("Up/Down", "choose"),
+        ];
+    }
+    if overview {
+        return vec![("j/k", "move"), ("Enter", "open"), ("o", "back")];
+    }
+    let mut hints = vec![("Tab", "switch")];
+    if paused {
+        hints.extend([
+            ("Space", "resume"),
+            ("j/k", "move"),
+            ("PgUp/PgDn", "scroll"),
+        ]);
+    } else {
+        hints.push(("Space", "pause"));
+    }
+    if selected {
+        hints.extend([("d", "clear"), ("y", "copy"), ("v", "details")]);
+    } else {
+        hints.push(("s", "select"));
+    }
+    hints.extend([
+        ("/", "search"),
+        ("r", "add tab"),
+        ("?", "hide"),
+        ("q", "quit"),
+    ]);
+    hints
+}
+
+/// Draws as many whole hints as fit in `cols`.
+fn draw_help_bar(
+    stdout: &mut Screen,
+    row: u16,
+    cols: usize,
+    hints: &[(&str, &str)],
+    no_color: bool,
+) -> io::Result<()> {
+    stdout.at(0, row);
+    let mut used = 0;
+    for (index, (key, action)) in hints.iter().enumerate() {
+        let gap = if index == 0 { "" } else { "  " };
+        let width = display_width(gap) + display_width(key) + 1 + display_width(action);
+        if used + width > cols {
+            break;
+        }
+        used += width;
+        if no_color {
+            queue!(
+                stdout,
+                Print(gap),
+                SetAttribute(Attribute::Bold),
+                Print(key),
+                SetAttribute(Attribute::NormalIntensity),
+                Print(format!(":{}", action))
+            )?;
+        } else {
+            queue!(
+                stdout,
+                Print(gap),
+                SetForegroundColor(Color::Yellow),
+                Print(key),
+                SetForegroundColor(Color::DarkGrey),
+                Print(format!(":{}", action)),
+                ResetColor
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Shows the end of long input so the cursor stays in view.
+fn draw_prompt(
+    stdout: &mut Screen,
+    row: u16,
+    cols: usize,
+    prefix: &str,
+    text: &str,
+) -> io::Result<()> {
+    let line = format!("{}{}", prefix, text);
+    let keep = cols.saturating_sub(1);
+    // The tail that fits in `keep` columns, so the cursor stays in view.
+    let mut start = line.len();
+    let mut width = 0;
+    for (index, ch) in line.char_indices().rev() {
+        width += char_display_width(ch);
+        if width > keep {
+            break;
+        }
+        start = index;
+    }
+    let shown = &line[start..];
+    queue!(stdout.at(0, row), Print(shown))?;
+    if display_width(shown) < cols {
+        queue!(
+            stdout,
+            SetAttribute(Attribute::Reverse),
+            Print(" "),
+            SetAttribute(Attribute::Reset)
+        )?;
+    }
+    Ok(())
+}
+
+/// The overview's header and then one row per tab: the key that selects it,
+/// its label, match counts and latest line.
+fn overview_rows(tabs: &[Tab], pause_line_cutoffs: Option<&[usize]>) -> Vec<String> {
+    let label_width = tabs
+        .iter()
+        .map(|tab| display_width(&tab.label))
+        .max()
+        .unwrap_or(0)
+        .clamp(3, OVERVIEW_LABEL_WIDTH);
+    let number_offset = tab_number_offset(tabs);
+    let mut rows = vec![format!(
+        "{:>3}  {:<label_width$}  {:>7}  {:>7}  latest",
+        "#", "tab", "total", "unread"
+    )];
+    rows.extend(tabs.iter().enumerate().map(|(index, tab)| {
+        let cutoff = pause_line_cutoffs.and_then(|cutoffs| cutoffs.get(index).copied());
+        // Padded by columns, as wide characters take two each.
+        let label = clip_with_ellipsis(&tab.label, label_width);
+        format!(
+            "{:>3}  {}{}  {:>7}  {:>7}  {}",
+            index + number_offset,
+            label,
+            " ".repeat(label_width.saturating_sub(display_width(&label))),
+            tab.total_matches,
+            tab.unread_matches(),
+            latest_line_preview(tab, cutoff).unwrap_or_default()
+        )
+    }));
+    rows
+}
+
+/// Draws the overview in place of the body, scrolled to keep the cursor's
+/// row in view, and returns which tab each drawn row opens.
+fn draw_overview(
+    stdout: &mut Screen,
+    tabs: &[Tab],
+    pause_line_cutoffs: Option<&[usize]>,
+    cursor: usize,
+    body_rows: std::ops::Range<usize>,
+    cols: usize,
+) -> io::Result<Vec<(u16, usize)>> {
+    let mut rows = overview_rows(tabs, pause_line_cutoffs).into_iter();
+    let Some(header) = rows.next() else {
+        return Ok(Vec::new());
+    };
+    if body_rows.is_empty() {
+        return Ok(Vec::new());
+    }
+    queue!(
+        stdout.at(0, body_rows.start as u16),
+        SetAttribute(Attribute::Bold),
+        Print(clip_to_width(&header, cols)),
+        SetAttribute(Attribute::NormalIntensity)
+    )?;
+
+    let tab_rows = body_rows.len() - 1;
+    let skip = (cursor + 1).saturating_sub(tab_rows);
+    let mut hitboxes = Vec::new();
+    for (offset, (index, text)) in rows.enumerate().skip(skip).take(tab_rows).enumerate() {
+        let y = (body_rows.start + 1 + offset) as u16;
+        let text = clip_to_width(&text, cols);
+        stdout.at(0, y);
+        if index == cursor {
+            queue!(
+                stdout,
+                SetAttribute(Attribute::Reverse),
+                Print(text),
+                SetAttribute(Attribute::NoReverse)
+            )?;
+        } else {
+            queue!(stdout, Print(text))?;
+        }
+        hitboxes.push((y, index));
+    }
+    Ok(hitboxes)
+}
+
+/// Lists a tab's most frequent (or most recent) distinct lines with their
+/// counts, as many as fit under a header row.
+fn draw_frequency_view(
+    stdout: &mut Screen,
+    counts: &LineCounts,
+    sort: FrequencySort,
+    body_rows: std::ops::Range<usize>,
+    cols: usize,
+) -> io::Result<()> {
+    if body_rows.is_empty() {
+        return Ok(());
+    }
+    let order = match sort {
+        FrequencySort::Count => "count",
+        FrequencySort::Recency => "last seen",
+    };
+    let header = format!(
+        "{:>7}  {} distinct lines by {}",
+        "count",
+        counts.by_text.len(),
+        order
+    );
+    queue!(
+        stdout.at(0, body_rows.start as u16),
+        SetAttribute(Attribute::Bold),
+        Print(clip_to_width(&header, cols)),
+        SetAttribute(Attribute::NormalIntensity)
+    )?;
+    for (offset, (count, text)) in counts
+        .top(sort, body_rows.len() - 1)
+        .into_iter()
+        .enumerate()
+    {
+        let row = format!("{:>7}  {}", count, text);
+        queue!(
+            stdout.at(0, (body_rows.start + 1 + offset) as u16),
+            Print(clip_to_width(&row, cols))
+        )?;
+    }
+    Ok(())
+}
+
+fn latest_line_preview(tab: &Tab, cutoff_len: Option<usize>) -> Option<String> {
+    let visible_len = cutoff_len.unwrap_or(tab.lines.len()).min(tab.lines.len());
+    let line = tab.lines.get(visible_len.checked_sub(1)?)?;
+    Some(strip_ansi(&line.text).trim().to_owned())
+}
+
+fn draw_tab_previews(
+    stdout: &mut Screen,
+    row: u16,
+    tabs: &[Tab],
+    hitboxes: &[TabHitbox],
+    pause_line_cutoffs: Option<&[usize]>,
+) -> io::Result<()> {
+    for hitbox in hitboxes {
+        let Some(tab) = tabs.get(hitbox.index) else {
+            continue;
+        };
+        let cutoff_len = pause_line_cutoffs.and_then(|cutoffs| cutoffs.get(hitbox.index).copied());
+        let Some(preview) = latest_line_preview(tab, cutoff_len) else {
+            continue;
+        };
+
+        let width = (hitbox.right - hitbox.left) as usize + 1;
+        queue!(
+            stdout.at(hitbox.left, row),
+            SetForegroundColor(Color::DarkGrey),
+            Print(clip_with_ellipsis(&preview, width)),
+            ResetColor
+        )?;
+    }
+    Ok(())
+}
+
+/// A frame drawn row by row, so a repaint can compare each screen row with
+/// the one already there. Drawing picks a row with `at`; what is written
+/// after it lands on that row, until the next `at`.
+#[derive(Debug, Default)]
+struct Screen {
+    rows: Vec<Vec<u8>>,
+    /// The row being drawn, and where on it this visit began.
+    current: Option<(usize, usize)>,
+    /// The colors in effect when the current visit began.
+    style: SgrState,
+}
+
+impl Screen {
+    /// Moves to column `x` of row `y`. Each visit to a row opens with the
+    /// colors still in effect from the last, and ends reset, so every row
+    /// can be redrawn alone.
+    fn at(&mut self, x: u16, y: u16) -> &mut Self {
+        self.end_visit();
+        let row = y as usize;
+        if self.rows.len() <= row {
+            self.rows.resize(row + 1, Vec::new());
+        }
+        let start = self.rows[row].len();
+        // Writing to a Vec can't fail.
+        let _ = queue!(self.rows[row], MoveTo(x, y));
+        self.rows[row].extend_from_slice(self.style.opening().as_bytes());
+        self.current = Some((row, start));
+        self
+    }
+
+    fn end_visit(&mut self) {
+        let Some((row, start)) = self.current.take() else {
+            return;
+        };
+        let drawn = &mut self.rows[row];
+        self.style = SgrState::after(&String::from_utf8_lossy(&drawn[start..]));
+        drawn.extend_from_slice(self.style.closing().as_bytes());
+    }
+
+    fn into_rows(mut self) -> Vec<Vec<u8>> {
+        self.end_visit();
+        self.rows
+    }
+}
+
+impl Write for Screen {
+    /// Bytes written before the first `at` have no row and are dropped.
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if let Some((row, _)) = self.current {
+            self.rows[row].extend_from_slice(buf);
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Writes a frame's rows: all of them on a cleared screen when `drawn` is
+/// unknown, or else only the rows that differ from it, each cleared first.
+fn repaint(stdout: &mut impl Write, rows: &[Vec<u8>], drawn: Option<&[Vec<u8>]>) -> io::Result<()> {
+    let Some(drawn) = drawn else {
+        queue!(stdout, MoveTo(0, 0), Clear(ClearType::All))?;
+        for row in rows {
+            stdout.write_all(row)?;
+        }
+        return stdout.flush();
+    };
+    for y in 0..rows.len().max(drawn.len()) {
+        let row = rows.get(y).map_or(&[][..], Vec::as_slice);
+        if drawn.get(y).map_or(&[][..], Vec::as_slice) != row {
+            queue!(stdout, MoveTo(0, y as u16), Clear(ClearType::CurrentLine))?;
+            stdout.write_all(row)?;
+        }
+    }
+    stdout.flush()
+}
+
+fn draw(stdout: &mut Screen, frame: &Frame) -> io::Result<RenderState> {
+    let Frame {
+        display,
+        tabs,
+        history,
+        active_tab_indices,
+        paused,
+        input_held,
+        pause_line_cutoffs,
+        selected_line,
+        detail_pane,
+        tab_preview,
+        unread_only,
+        status,
+        search_prompt,
+        palette,
+        overview,
+        frequency_view,
+        scroll_top,
+        scroll_delta,
+        previous_viewport,
+        split,
+        ..
+    } = *frame;
+
+    let (cols, rows) = frame.size;
+    let cols_usize = cols as usize;
+    let rows_usize = rows as usize;
+
+    let mut render_state = RenderState {
+        tab_hitboxes: Vec::new(),
+        line_rows: vec![None; rows_usize],
+        viewport: None,
+        // Kept as is when there is no room to draw the body.
+        scroll_top,
+        bar_top: 0,
+        overview_rows: Vec::new(),
+        split_first_row: None,
+        split_viewport: None,
+        split_scroll_top: split.and_then(|split| split.scroll_top),
+        gutter_width: 0,
+        split_gutter_width: 0,
+        tab_scroll: frame.tab_scroll,
+    };
+
+    if rows_usize == 0 || cols_usize == 0 {
+        return Ok(render_state);
+    }
+    // Redrawn on every resize, so the view comes back once there is room again.
+    if let Some(notice) = too_small_notice(cols_usize, rows_usize) {
+        for (row, text) in notice.iter().enumerate() {
+            queue!(stdout.at(0, row as u16), Print(text))?;
+        }
+        return Ok(render_state);
+    }
+
+    let paused_for = paused.then_some(frame.paused_for);
+    let mut mode_label =
+        view_mode_label(paused_for, input_held, unread_only, display.status_glyphs);
+    if let Some(status) = frame.exit_status {
+        mode_label.push_str(&exit_label(status, display.status_glyphs));
+    }
+    if display.memory {
+        mode_label.push_str(&memory_label(tabs, history));
+    }
+    let tab_cols_limit = tab_columns_limit(cols_usize, &mode_label);
+    let number_offset = tab_number_offset(tabs);
+    let badge_layout =
+        effective_badge_layout(tabs, number_offset, tab_cols_limit, display.badge_layout);
+
+    let (bar_top, content_rows) = screen_layout(rows_usize, display.tabs_bottom);
+    render_state.bar_top = bar_top;
+    let (mut body_start_row, mut content_end) = (content_rows.start, content_rows.end);
+
+    let first_tab = tab_strip_start(
+        tabs,
+        number_offset,
+        badge_layout,
+        tab_cols_limit,
+        frame.tab_scroll,
+        frame.follow_tab,
+    );
+    render_state.tab_scroll = first_tab;
+    let full_tabs_width = (first_tab..tabs.len())
+        .map(|index| drawn_tab_width(&tabs[index], index, number_offset, badge_layout) + 1)
+        .sum::<usize>();
+    let left_marker = tab_overflow_width(first_tab);
+    // Room for the far marker too whenever the rest of the tabs won't fit.
+    let tab_cols_limit = if left_marker + full_tabs_width > tab_cols_limit + 1 {
+        tab_cols_limit.saturating_sub(tab_overflow_width(tabs.len() - first_tab))
+    } else {
+        tab_cols_limit
+    };
+    let marker_row = if rows_usize >= 2 {
+        bar_top + 1
+    } else {
+        bar_top
+    };
+    if first_tab > 0 {
+        queue!(
+            stdout.at(0, marker_row),
+            SetForegroundColor(Color::DarkGrey),
+            Print(tab_overflow_marker(first_tab, true)),
+            ResetColor
+        )?;
+    }
+
+    let now = Instant::now();
+    let mut x = left_marker as u16;
+    let mut tabs_right: u16 = 0;
+    for (i, tab) in tabs.iter().enumerate().skip(first_tab) {
+        if x as usize >= tab_cols_limit {
+            break;
+        }
+
+        let number_piece = format!(" {} ", tab_shortcut_label(i, number_offset));
+        let unread_piece = format_badge(tab.total_matches, tab.unread_matches(), badge_layout);
+        let badge_color = if display.flash_badges && tab.badge_flash_until(now).is_some() {
+            Color::White
+        } else {
+            Color::DarkCyan
+        };
+        let trailing_piece = " ";
+
+        let fixed_inner_width = number_piece.chars().count()
+            + unread_piece.chars().count()
+            + trailing_piece.chars().count();
+        let full_title_width = display_width(&tab.label) + 2;
+        let desired_inner_width = fixed_inner_width + full_title_width;
+
+        let remaining_cols = tab_cols_limit.saturating_sub(x as usize);
+        if remaining_cols < 3 {
+            break;
+        }
+
+        let inner_width = desired_inner_width.min(remaining_cols.saturating_sub(2));
+        if inner_width == 0 {
+            break;
+        }
+
+        let title_budget = inner_width.saturating_sub(fixed_inner_width);
+        let title_piece = fit_tab_title(&tab.label, title_budget);
+
+        let right = x + inner_width as u16 + 1;
+        let tab_color = tab.color.filter(|_| !display.no_color);
+        let border_color = if is_tab_active(active_tab_indices, i) {
+            tab_color.unwrap_or(Color::White)
+        } else {
+            Color::DarkGrey
+        };
+        let horiz = "─".repeat(inner_width);
+
+        if rows_usize >= 1 {
+            queue!(
+                stdout.at(x, bar_top),
+                SetForegroundColor(border_color),
+                Print("╭"),
+                Print(&horiz),
+                Print("╮"),
+                ResetColor
+            )?;
+        }
+
+        if rows_usize >= 2 {
+            queue!(
+                stdout.at(x, bar_top + 1),
+                SetForegroundColor(border_color),
+                Print("│"),
+                ResetColor
+            )?;
+
+            let mut inner_x = x + 1;
+            let mut remaining_inner = inner_width;
+            draw_piece_clipped(
+                stdout,
+                &mut inner_x,
+                bar_top + 1,
+                &mut remaining_inner,
+                &number_piece,
+                Some(Color::DarkGrey),
+            )?;
+            let title_color = if matches!(tab.mode, MatchMode::All) {
+                Some(Color::DarkGrey)
+            } else {
+                tab_color
+            };
+            draw_piece_clipped(
+                stdout,
+                &mut inner_x,
+                bar_top + 1,
+                &mut remaining_inner,
+                &title_piece,
+                title_color,
+            )?;
+            draw_piece_clipped(
+                stdout,
+                &mut inner_x,
+                bar_top + 1,
+                &mut remaining_inner,
+                &unread_piece,
+                Some(badge_color),
+            )?;
+            draw_piece_clipped(
+                stdout,
+                &mut inner_x,
+                bar_top + 1,
+                &mut remaining_inner,
+                trailing_piece,
+                None,
+            )?;
+            if remaining_inner > 0 {
+                let pad = " ".repeat(remaining_inner);
+                queue!(stdout.at(inner_x, bar_top + 1), Print(pad))?;
+            }
+
+            queue!(
+                stdout.at(right, bar_top + 1),
+                SetForegroundColor(border_color),
+                Print("│"),
+                ResetColor
+            )?;
+        }
+
+        if rows_usize >= 3 {
+            queue!(
+                stdout.at(x, bar_top + 2),
+                SetForegroundColor(border_color),
+                Print("╰"),
+                Print(&horiz),
+                Print("╯"),
+                ResetColor
+            )?;
+        }
+
+        render_state.tab_hitboxes.push(TabHitbox {
+            index: i,
+            left: x,
+            right,
+        });
+        tabs_right = right;
+        x = right.saturating_add(1);
+        if i + 1 < tabs.len() && (x as usize) < tab_cols_limit {
+            x = x.saturating_add(1);
+        }
+    }
+
+    let hidden_after = tabs.len()
+        - render_state
+            .tab_hitboxes
+            .last()
+            .map_or(first_tab, |hitbox| hitbox.index + 1);
+    if hidden_after > 0 {
+        let marker = tab_overflow_marker(hidden_after, false);
+        let marker_x = if tabs_right > 0 {
+            tabs_right + 1
+        } else {
+            left_marker as u16
+        };
+        queue!(
+            stdout.at(marker_x, marker_row),
+            SetForegroundColor(Color::DarkGrey),
+            Print(&marker),
+            ResetColor
+        )?;
+        // The mode label and bar rule go after the marker.
+        tabs_right = marker_x + display_width(&marker) as u16;
+    }
+
+    if display.bar_rule && rows_usize >= 3 {
+        let start_col = if tabs_right > 0 {
+            tabs_right as usize + 1
+        } else {
+            0
+        };
+        // Border rows only, so the rule never overlaps the mode label.
+        let rule = "─".repeat(cols_usize.saturating_sub(start_col));
+        for row in [bar_top, bar_top + 2] {
+            stdout.at(start_col as u16, row);
+            if display.no_color {
+                queue!(stdout, Print(&rule))?;
+            } else {
+                queue!(
+                    stdout,
+                    SetForegroundColor(Color::DarkGrey),
+                    Print(&rule),
+                    ResetColor
+                )?;
+            }
+        }
+    }
+
+    if !mode_label.is_empty() {
+        let start_col =
+            mode_label_column(tabs_right, cols_usize, &mode_label, display.status_glyphs);
+        if (start_col as usize) < cols_usize {
+            let available = cols_usize - start_col as usize;
+            let shown = clip_to_width(&mode_label, available);
+            if !shown.is_empty() {
+                let paused_row = if rows_usize >= 2 {
+                    bar_top + 1
+                } else {
+                    bar_top
+                };
+                queue!(
+                    stdout.at(start_col, paused_row),
+                    SetForegroundColor(Color::Grey),
+                    Print(shown),
+                    ResetColor
+                )?;
+            }
+        }
+    }
+
+    // Only spend a row on previews if at least one body row remains. They
+    // sit next to the tab bar, whichever end of the screen it is on.
+    if tab_preview && content_end > body_start_row + 1 {
+        let preview_row = if display.tabs_bottom {
+            content_end -= 1;
+            content_end
+        } else {
+            body_start_row += 1;
+            body_start_row - 1
+        };
+        draw_tab_previews(
+            stdout,
+            preview_row as u16,
+            tabs,
+            &render_state.tab_hitboxes,
+            pause_line_cutoffs,
+        )?;
+    }
+    if content_end <= body_start_row {
+        return Ok(render_state);
+    }
+
+    let mut body_height = content_end - body_start_row;
+    // The help bar takes the bottom row, under any prompt or status.
+    if frame.help_bar && body_height > 1 {
+        body_height -= 1;
+        content_end -= 1;
+        let hints = help_hints(
+            palette.is_some() || search_prompt.is_some(),
+            overview.is_some(),
+            paused,
+            selected_line.is_some(),
+        );
+        draw_help_bar(
+            stdout,
+            content_end as u16,
+            cols_usize,
+            &hints,
+            display.no_color,
+        )?;
+    }
+    if let Some(palette) = palette
+        && body_height > 1
+    {
+        body_height -= 1;
+        draw_prompt(
+            stdout,
+            (content_end - 1) as u16,
+            cols_usize,
+            "recent: ",
+            &palette.query,
+        )?;
+    } else if let Some(text) = search_prompt
+        && body_height > 1
+    {
+        body_height -= 1;
+        draw_prompt(stdout, (content_end - 1) as u16, cols_usize, "/", text)?;
+    } else if let Some(status) = status
+        && body_height > 1
+    {
+        body_height -= 1;
+        draw_status_message(stdout, (content_end - 1) as u16, cols_usize, status)?;
+    }
+
+    if let Some(sort) = frequency_view
+        && overview.is_none()
+        && let Some(counts) = active_tab_indices
+            .first()
+            .and_then(|&index| tabs.get(index)?.line_counts.as_ref())
+    {
+        draw_frequency_view(
+            stdout,
+            counts,
+            sort,
+            body_start_row..body_start_row + body_height,
+            cols_usize,
+        )?;
+        return Ok(render_state);
+    }
+
+    if let Some(cursor) = overview {
+        render_state.overview_rows = draw_overview(
+            stdout,
+            tabs,
+            pause_line_cutoffs,
+            cursor,
+            body_start_row..body_start_row + body_height,
+            cols_usize,
+        )?;
+        return Ok(render_state);
+    }
+
+    if detail_pane {
+        let text_rows = detail_pane_text_rows(selected_line, cols_usize, body_height);
+        if !text_rows.is_empty() {
+            body_height -= text_rows.len() + 2;
+            // Which filter tabs caught the line, while `(all)` still holds it.
+            let footer = selected_line
+                .and_then(|line| matched_tab_labels(tabs, line.seq))
+                .map(|labels| {
+                    if labels.is_empty() {
+                        "no filter tabs".to_owned()
+                    } else {
+                        labels.join(" · ")
+                    }
+                });
+            draw_detail_pane(
+                stdout,
+                (body_start_row + body_height) as u16,
+                cols_usize,
+                &text_rows,
+                footer.as_deref(),
+            )?;
+        }
+    }
+
+    let mut main_height = body_height;
+    if let Some(split) = split
+        && body_height >= 3
+    {
+        main_height = body_height / 2;
+        let divider_row = body_start_row + main_height;
+        draw_split_divider(stdout, divider_row as u16, cols_usize, tabs, split, display)?;
+        render_state.split_first_row = Some(divider_row as u16);
+        let pane = BodyPane {
+            tab_indices: std::slice::from_ref(&split.tab),
+            first_row: divider_row + 1,
+            height: body_height - main_height - 1,
+            scroll_top: split.scroll_top,
+            scroll_delta: split.scroll_delta,
+            previous_viewport: split.previous_viewport,
+        };
+        (
+            render_state.split_viewport,
+            render_state.split_scroll_top,
+            render_state.split_gutter_width,
+        ) = draw_body_pane(stdout, frame, pane, cols_usize, &mut render_state.line_rows)?;
+    }
+    let pane = BodyPane {
+        tab_indices: active_tab_indices,
+        first_row: body_start_row,
+        height: main_height,
+        scroll_top,
+        scroll_delta,
+        previous_viewport,
+    };
+    (
+        render_state.viewport,
+        render_state.scroll_top,
+        render_state.gutter_width,
+    ) = draw_body_pane(stdout, frame, pane, cols_usize, &mut render_state.line_rows)?;
+
+    if let Some(palette) = palette {
+        let list_rows = body_height.min(PALETTE_ROWS);
+        let first_row = body_start_row + body_height - list_rows;
+        draw_palette(stdout, palette, first_row, list_rows, cols_usize)?;
+        // Lines under the list can't be clicked while it covers them.
+        for slot in &mut render_state.line_rows[first_row..first_row + list_rows] {
+            *slot = None;
+        }
+    }
+
+    Ok(render_state)
+}
+
+/// Draws one stacked region of the body and records its lines in
+/// `line_rows`. Returns where its viewport ended up, the line at its top if
+/// scrolled up from the bottom, and its gutter's width.
+fn draw_body_pane(
+    stdout: &mut Screen,
+    frame: &Frame,
+    pane: BodyPane,
+    cols_usize: usize,
+    line_rows: &mut [Option<RenderedLine<'static>>],
+) -> io::Result<(Option<ViewportAnchor>, Option<u64>, u16)> {
+    let Frame {
+        display,
+        tabs,
+        paused,
+        pause_line_cutoffs,
+        selected_line,
+        column_selection,
+        unread_only,
+        marker,
+        gap_threshold,
+        skipped_lines,
+        partial_line,
+        gutter_separator,
+        json_template,
+        relative_seq,
+        wrap,
+        clock,
+        hover_row,
+        pinned,
+        sources,
+        ..
+    } = *frame;
+
+    let mut visible_lines = prepare_visible_lines_for_tabs(
+        tabs,
+        pane.tab_indices,
+        pause_line_cutoffs,
+        selected_line,
+        unread_only,
+    );
+    push_partial_line(
+        &mut visible_lines,
+        partial_line,
+        tabs,
+        pane.tab_indices,
+        frame.history.fold_case,
+        selected_line,
+    );
+    slice_at_marker(&mut visible_lines, marker);
+    // Wrapping needs every line's text as shown; otherwise only lines that
+    // end up in view are put through the template.
+    if wrap {
+        apply_json_template(&mut visible_lines, json_template);
+    }
+    let visible_lines = match gap_threshold {
+        Some(threshold) => insert_gap_separators(visible_lines, tabs, pane.tab_indices, threshold),
+        None => visible_lines,
+    };
+    // Dropped lines can't be told apart by filter, so only `(all)` marks them.
+    let shows_all = pane.tab_indices.iter().any(|&index| {
+        tabs.get(index)
+            .is_some_and(|tab| matches!(tab.mode, MatchMode::All))
+    });
+    let visible_lines = if shows_all {
+        insert_skip_separators(visible_lines, skipped_lines)
+    } else {
+        visible_lines
+    };
+    let visible_lines = insert_marker_separator(visible_lines, marker);
+    let mut highlight_terms = active_highlight_terms(tabs, pane.tab_indices);
+    let group_regexes = if display.color_groups {
+        active_group_regexes(tabs, pane.tab_indices)
+    } else {
+        Vec::new()
+    };
+    let search_terms = search_highlight_terms(tabs, pane.tab_indices);
+    highlight_terms.extend(&search_terms);
+    let filter_needles = active_filter_needles(tabs, pane.tab_indices);
+    let relative_to = selected_line.filter(|_| relative_seq).map(|line| line.seq);
+    // Wrapping needs the gutter's width before the viewport is known, so it's
+    // sized for every line rather than just those in view.
+    let clock = clock.then(WallClock::now);
+    // Lines from several files are tagged with the file's name after the
+    // gutter, so the name isn't part of what filters see or what's copied.
+    let source_width = match source_tags_width(sources) {
+        0 => 0,
+        width => width + 1,
+    };
+    let wrap_gutter = wrap.then(|| GutterOptions {
+        clock,
+        ..GutterOptions::for_lines(display, gutter_separator, &visible_lines, relative_to)
+    });
+    let (mut visible_lines, wrapped_rows) = match wrap_gutter {
+        Some(gutter) => {
+            let gutter_width = visible_lines
+                .first()
+                .map_or(0, |line| render_gutter(line, None, gutter).1);
+            wrap_rendered_lines(
+                visible_lines,
+                cols_usize.saturating_sub(gutter_width + source_width),
+            )
+        }
+        None => {
+            let rows = vec![None; visible_lines.len()];
+            (visible_lines, rows)
+        }
+    };
+    let (mut start_index, mut visible_count, mut first_row) = viewport_for_lines(
+        pane.first_row,
+        pane.height,
+        &visible_lines,
+        paused,
+        pane.previous_viewport
+            .filter(|_| !display.recenter_on_select),
+    );
+    let mut scroll_top = None;
+    if (pane.scroll_top.is_some() || pane.scroll_delta != 0)
+        && let Some(scrolled) = scrolled_viewport(
+            pane.first_row,
+            pane.height,
+            &visible_lines,
+            pane.scroll_top,
+            pane.scroll_delta,
+            start_index,
+        )
+        && let Some(scrolled) = snap_to_line_start(scrolled, &wrapped_rows, pane.scroll_delta)
+    {
+        (start_index, visible_count, first_row) = scrolled;
+        scroll_top = Some(visible_lines[start_index].seq);
+    }
+    let viewport = visible_lines
+        .get(start_index)
+        .filter(|_| visible_count > 0)
+        .map(|line| ViewportAnchor {
+            start_seq: line.seq,
+            first_row,
+        });
+    if !wrap {
+        apply_json_template(
+            &mut visible_lines[start_index..start_index + visible_count],
+            json_template,
+        );
+    }
+    let in_view = &visible_lines[start_index..start_index + visible_count];
+    let gutter = wrap_gutter.unwrap_or_else(|| GutterOptions {
+        clock,
+        ..GutterOptions::for_lines(display, gutter_separator, in_view, relative_to)
+    });
+
+    let tint = (display.tint && !display.no_color).then(|| {
+        let active = pane.tab_indices.first().copied().unwrap_or(0);
+        TINT_BACKGROUNDS[active % TINT_BACKGROUNDS.len()]
+    });
+    if let Some(color) = tint {
+        // Rows above a short body are tinted too, so the whole region reads as one.
+        queue!(stdout, SetBackgroundColor(color))?;
+        for y in pane.first_row..pane.first_row + pane.height {
+            queue!(stdout.at(0, y as u16), Clear(ClearType::UntilNewLine))?;
+        }
+        queue!(stdout, ResetColor)?;
+    }
+
+    let mut gutter_width = 0;
+    for (screen_row, line) in in_view.iter().enumerate() {
+        let y = (first_row + screen_row) as u16;
+        if let Some(separator) = line.separator {
+            let (label, color) = match separator {
+                Separator::Gap(gap) => (format_gap(gap), Color::DarkGrey),
+                Separator::Marker(slice) => (slice.label().to_owned(), Color::Cyan),
+                Separator::Skipped(1) => ("1 line skipped".to_owned(), Color::Yellow),
+                Separator::Skipped(count) => (format!("{} lines skipped", count), Color::Yellow),
+            };
+            let rule = format!("── {} {}", label, "─".repeat(cols_usize));
+            stdout.at(0, y);
+            if display.no_color {
+                queue!(stdout, Print(clip_to_width(&rule, cols_usize)))?;
+            } else {
+                if let Some(color) = tint {
+                    queue!(stdout, SetBackgroundColor(color))?;
+                }
+                queue!(
+                    stdout,
+                    SetForegroundColor(color),
+                    Print(clip_to_width(&rule, cols_usize)),
+                    ResetColor
+                )?;
+            }
+            // Separator rows aren't lines, so clicks on them select nothing.
+            continue;
+        }
+
+        let is_pinned = pinned.iter().any(|pin| pin.seq == line.seq);
+        let background = if display.no_color {
+            tint
+        } else if hover_row == Some(y) {
+            Some(HOVER_BACKGROUND)
+        } else if is_pinned {
+            Some(PINNED_BACKGROUND)
+        } else {
+            tint
+        };
+        if let Some(color) = background {
+            queue!(stdout, SetBackgroundColor(color))?;
+        }
+        let row = wrapped_rows[start_index + screen_row].as_ref();
+        let text = row.map_or(line.text.as_ref(), |row| row.text.as_str());
+        let arrived = gutter
+            .clock
+            .and_then(|_| line_arrival(tabs, pane.tab_indices, line.seq));
+        let (mut gutter_text, width) = render_gutter(line, arrived, gutter);
+        if row.is_some_and(|row| row.continuation) {
+            // Only a line's first row is numbered.
+            gutter_text = format!(
+                "{:width$}{}",
+                "",
+                gutter.separator,
+                width = width.saturating_sub(display_width(gutter.separator))
+            );
+        }
+        if width > 0 {
+            stdout.at(0, y);
+            if display.no_color {
+                queue!(stdout, Print(clip_to_width(&gutter_text, cols_usize)))?;
+            } else {
+                queue!(
+                    stdout,
+                    SetForegroundColor(Color::DarkGrey),
+                    Print(clip_to_width(&gutter_text, cols_usize)),
+                    SetForegroundColor(Color::Reset)
+                )?;
+            }
+        }
+        if source_width > 0 && width < cols_usize {
+            let source = line_source(tabs, pane.tab_indices, line.seq)
+                .filter(|_| !row.is_some_and(|row| row.continuation));
+            let label = source
+                .and_then(|source| sources.get(source))
+                .map_or("", String::as_str);
+            let tag = clip_to_width(
+                &format!(
+                    "{}{}",
+                    label,
+                    " ".repeat(source_width - display_width(label))
+                ),
+                cols_usize - width,
+            );
+            stdout.at(width as u16, y);
+            match source.filter(|_| !display.no_color) {
+                Some(source) => queue!(
+                    stdout,
+                    SetForegroundColor(source_tag_color(source)),
+                    Print(tag),
+                    SetForegroundColor(Color::Reset)
+                )?,
+                None => queue!(stdout, Print(tag))?,
+            }
+        }
+        let width = width + source_width;
+        gutter_width = width;
+        let text_x = width.min(cols_usize) as u16;
+        let cols_usize = cols_usize.saturating_sub(width);
+        let highlighted = (!highlight_terms.is_empty() || !group_regexes.is_empty())
+            .then(|| strip_ansi(text))
+            .filter(|stripped| {
+                highlight_terms
+                    .iter()
+                    .any(|term| stripped.contains(&term.text))
+                    || group_regexes.iter().any(|regex| regex.is_match(stripped))
+            });
+        if let Some(range) = column_selection.filter(|range| range.seq == line.seq) {
+            let plain = clip_ansi_to_visible_width(&strip_ansi(text), cols_usize);
+            let (start, end) = range.columns();
+            let span = column_range_byte_span(&plain, start, end);
+            queue!(
+                stdout.at(text_x, y),
+                Print(&plain[..span.start]),
+                SetAttribute(Attribute::Reverse),
+                Print(&plain[span.clone()]),
+                SetAttribute(Attribute::NoReverse),
+                Print(&plain[span.end..])
+            )?;
+        } else if line.selected {
+            let mut styled = selected_line_text(
+                text,
+                cols_usize,
+                display.selection_style,
+                line.injected,
+                display.no_color,
+            );
+            if let Some(color) = background {
+                styled = reapply_background(&styled, color);
+            }
+            queue!(stdout.at(text_x, y), Print(styled))?;
+        } else if let Some(stripped) = highlighted {
+            // Lines with a term or group in them drop their own colors so
+            // those stand out; the rest keep theirs.
+            let plain = clip_to_width(&stripped, cols_usize);
+            let mut spans = highlight_spans(&plain, &highlight_terms);
+            if !group_regexes.is_empty() {
+                // Where the clipped row stops agreeing with the line, past
+                // which it's only padding.
+                let shown = stripped
+                    .char_indices()
+                    .zip(plain.chars())
+                    .find(|((_, ch), shown_ch)| ch != shown_ch)
+                    .map_or(stripped.len().min(plain.len()), |((index, _), _)| index);
+                spans = merge_spans(spans, group_spans(&stripped, &group_regexes, shown));
+            }
+            stdout.at(text_x, y);
+            let mut printed = 0;
+            for (span, style) in spans {
+                queue!(stdout, Print(&plain[printed..span.start]))?;
+                match style {
+                    HighlightStyle::Search => queue!(
+                        stdout,
+                        SetAttribute(Attribute::Reverse),
+                        Print(&plain[span.clone()]),
+                        SetAttribute(Attribute::NoReverse)
+                    )?,
+                    HighlightStyle::Term(_) if display.no_color => queue!(
+                        stdout,
+                        SetAttribute(Attribute::Bold),
+                        Print(&plain[span.clone()]),
+                        SetAttribute(Attribute::NormalIntensity)
+                    )?,
+                    HighlightStyle::Term(color) => queue!(
+                        stdout,
+                        SetForegroundColor(color),
+                        Print(&plain[span.clone()]),
+                        SetForegroundColor(Color::Reset)
+                    )?,
+                }
+                printed = span.end;
+            }
+            queue!(stdout, Print(&plain[printed..]))?;
+        } else {
+            let mut clipped = if filter_needles.is_empty() {
+                clip_ansi_to_visible_width(text, cols_usize)
+            } else {
+                clip_ansi_to_visible_width(&embolden_needles(text, &filter_needles), cols_usize)
+            };
+            if let Some(color) = background {
+                clipped = reapply_background(&clipped, color);
+            }
+            if is_pinned && display.no_color {
+                // Without colors to tint it, a pinned line is underlined.
+                clipped = format!(
+                    "\u{1b}[{}m{}\u{1b}[{}m",
+                    Attribute::Underlined.sgr(),
+                    clipped,
+                    Attribute::NoUnderline.sgr()
+                );
+            }
+            queue!(stdout.at(text_x, y), Print(clipped))?;
+        }
+        if background.is_some() {
+            // Carry the tint to the right edge, past the end of the text.
+            queue!(stdout, Clear(ClearType::UntilNewLine), ResetColor)?;
+        }
+
+        if let Some(slot) = line_rows.get_mut(y as usize) {
+            *slot = Some(line.clone().into_owned());
+        }
+    }
+
+    Ok((viewport, scroll_top, gutter_width as u16))
+}
+
+/// The row between split panes: the lower pane's tab, highlighted while that
+/// pane has focus.
+fn draw_split_divider(
+    stdout: &mut Screen,
+    row: u16,
+    cols: usize,
+    tabs: &[Tab],
+    split: SplitView,
+    display: DisplayOptions,
+) -> io::Result<()> {
+    let label = tabs.get(split.tab).map_or("", |tab| tab.label.as_str());
+    let focus = if split.bottom_focused {
+        " (focused)"
+    } else {
+        ""
+    };
+    let rule = format!(
+        "── {} {}{} {}",
+        split.tab + tab_number_offset(tabs),
+        label,
+        focus,
+        "─".repeat(cols)
+    );
+    let rule = clip_to_width(&rule, cols);
+    stdout.at(0, row);
+    match (display.no_color, split.bottom_focused) {
+        (true, true) => queue!(
+            stdout,
+            SetAttribute(Attribute::Bold),
+            Print(rule),
+            SetAttribute(Attribute::NormalIntensity)
+        ),
+        (true, false) => queue!(stdout, Print(rule)),
+        (false, focused) => queue!(
+            stdout,
+            SetForegroundColor(if focused {
+                Color::Yellow
+            } else {
+                Color::DarkGrey
+            }),
+            Print(rule),
+            ResetColor
+        ),
+    }
+}
+
+/// Lists the palette's matching filters over the bottom of the body, keeping
+/// the cursor's entry in view and reversed.
+fn draw_palette(
+    stdout: &mut Screen,
+    palette: &Palette,
+    first_row: usize,
+    rows: usize,
+    cols: usize,
+) -> io::Result<()> {
+    for row in first_row..first_row + rows {
+        queue!(stdout.at(0, row as u16), Clear(ClearType::UntilNewLine))?;
+    }
+
+    let entries = palette.matching();
+    if entries.is_empty() {
+        let hint = if palette.query.is_empty() {
+            "no recent filters yet"
+        } else {
+            "no recent filter matches; Enter opens a tab for what you typed"
+        };
+        queue!(
+            stdout.at(0, (first_row + rows).saturating_sub(1) as u16),
+            SetForegroundColor(Color::DarkGrey),
+            Print(clip_to_width(hint, cols)),
+            ResetColor
+        )?;
+        return Ok(());
+    }
+
+    let skip = (palette.cursor + 1).saturating_sub(rows);
+    for (offset, entry) in entries.iter().enumerate().skip(skip).take(rows) {
+        let text = clip_to_width(&format!(" {}", entry), cols);
+        stdout.at(0, (first_row + offset - skip) as u16);
+        if offset == palette.cursor {
+            queue!(
+                stdout,
+                SetAttribute(Attribute::Reverse),
+                Print(text),
+                SetAttribute(Attribute::NoReverse)
+            )?;
+        } else {
+            queue!(stdout, Print(text))?;
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug)]
+struct Options {
+    filters: Vec<String>,
+    /// From `--file`, given once or more.
+    files: Vec<PathBuf>,
+    /// Wait at the end of each file for more instead of closing the input.
+    follow: bool,
+    decompress: Decompress,
+    /// From `--on-overflow`: whether input waits for a lagging UI or is dropped.
+    on_overflow: Overflow,
+    jsonl_out: Option<PathBuf>,
+    /// Keeps colors in the files `b` writes.
+    dump_colors: bool,
+    /// Shell command whose output is read instead of stdin.
+    exec: Option<String>,
+    /// Socket to share the input on, from `--serve`.
+    serve: Option<PathBuf>,
+    /// Socket of a `--serve` instance to read from instead of the input.
+    attach: Option<PathBuf>,
+    alternate_screen: bool,
+    mouse: bool,
+    all_tab: bool,
+    /// Opens a tab per log level, ahead of the filter tabs.
+    auto_levels: bool,
+    zero_key: ZeroKey,
+    skip_blank: bool,
+    /// Splits plain filters on whitespace into terms that must all appear.
+    and_tokens: bool,
+    /// Filters like `level=error` compare fields of JSON lines.
+    json: bool,
+    /// From `--json-template`: how JSON lines are shown.
+    json_template: Option<String>,
+    confirm_quit: bool,
+    confirm_ctrl_c: bool,
+    /// Raw `--on-match` specs, resolved against the tabs once they exist.
+    on_match: Vec<String>,
+    /// From `--gaps`, which also shows the separators at startup.
+    gap_threshold: Option<Duration>,
+    /// Pause while scrolled up from the bottom.
+    auto_pause: bool,
+    history_lines: usize,
+    /// Lines each tab keeps, from `--scrollback`.
+    scrollback: usize,
+    pause_on_blur: bool,
+    /// From `--selection-timeout`; `None` keeps selections until cleared.
+    selection_timeout: Option<Duration>,
+    /// Between the gutter and each line's text.
+    gutter_separator: String,
+    /// Prints the resolved setup instead of starting the UI.
+    print_config: Option<ConfigFormat>,
+    /// From `--config`; its filters and keys are loaded by `apply_config_file`.
+    config: Option<PathBuf>,
+    keys: KeyRemap,
+    display: DisplayOptions,
+    capture: CaptureOptions,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            filters: Vec::new(),
+            files: Vec::new(),
+            follow: false,
+            decompress: Decompress::default(),
+            on_overflow: Overflow::default(),
+            jsonl_out: None,
+            dump_colors: false,
+            exec: None,
+            serve: None,
+            attach: None,
+            alternate_screen: true,
+            mouse: true,
+            all_tab: true,
+            auto_levels: false,
+            zero_key: ZeroKey::default(),
+            skip_blank: false,
+            and_tokens: false,
+            json: false,
+            json_template: None,
+            confirm_quit: false,
+            confirm_ctrl_c: false,
+            on_match: Vec::new(),
+            gap_threshold: None,
+            auto_pause: true,
+            history_lines: MAX_STORED_LINES_PER_TAB,
+            scrollback: MAX_STORED_LINES_PER_TAB,
+            pause_on_blur: false,
+            selection_timeout: None,
+            gutter_separator: DEFAULT_GUTTER_SEPARATOR.to_owned(),
+            print_config: None,
+            config: None,
+            keys: KeyRemap::default(),
+            display: DisplayOptions::default(),
+            capture: CaptureOptions::default(),
+        }
+    }
+}
+
+/// Raw `--since` / `--until` arguments, validated once parsing is done.
+#[derive(Debug, Default)]
+struct TimeWindowArgs {
+    since: Option<String>,
+    until: Option<String>,
+    timestamp_regex: Option<String>,
+    drop_untimestamped: bool,
+}
+
+impl TimeWindowArgs {
+    fn is_empty(&self) -> bool {
+        self.since.is_none() && self.until.is_none()
+    }
+}
+
+#[cfg(feature = "time-filter")]
+fn build_time_window(args: TimeWindowArgs) -> Result<Option<time_window::TimeWindow>, String> {
+    use time_window::{DEFAULT_TIMESTAMP_PATTERN, TimeWindow, parse_bound};
+
+    if args.is_empty() {
+        return Ok(None);
+    }
+
+    let now = chrono::Utc::now();
+    let pattern = args
+        .timestamp_regex
+        .as_deref()
+        .unwrap_or(DEFAULT_TIMESTAMP_PATTERN);
+    let timestamp_regex = Regex::new(pattern)
+        .map_err(|err| format!("invalid --timestamp-regex: {}", regex_error_summary(&err)))?;
+
+    Ok(Some(TimeWindow {
+        since: args
+            .since
+            .as_deref()
+            .map(|text| parse_bound(text, now))
+            .transpose()?,
+        until: args
+            .until
+            .as_deref()
+            .map(|text| parse_bound(text, now))
+            .transpose()?,
+        timestamp_regex,
+        keep_untimestamped: !args.drop_untimestamped,
+    }))
+}
+
+#[cfg(not(feature = "time-filter"))]
+fn build_time_window(args: TimeWindowArgs) -> Result<(), String> {
+    if args.is_empty() {
+        Ok(())
+    } else {
+        Err("--since/--until need streamtabs built with the `time-filter` feature".to_owned())
+    }
+}
+
+/// Parses durations like `500ms`, `5s`, `2m` or `1h`.
+fn parse_duration(text: &str) -> Result<Duration, String> {
+    let digits_end = text
+        .find(|ch: char| !ch.is_ascii_digit())
+        .unwrap_or(text.len());
+    let (value, unit) = text.split_at(digits_end);
+    let value = value.parse::<u64>().ok();
+    let duration = match (value, unit) {
+        (Some(value), "ms") => Some(Duration::from_millis(value)),
+        (Some(value), "s") => Some(Duration::from_secs(value)),
+        (Some(value), "m") => value.checked_mul(60).map(Duration::from_secs),
+        (Some(value), "h") => value.checked_mul(3600).map(Duration::from_secs),
+        _ => None,
+    };
+    duration.ok_or_else(|| format!("invalid duration: {} (use e.g. 500ms, 5s or 2m)", text))
+}
+
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options::default();
+    let mut time_window_args = TimeWindowArgs::default();
+    let mut only_filters = false;
+    let mut tab_stop = None;
+    let mut args = args.into_iter();
+
+    while let Some(arg) = args.next() {
+        if only_filters || !arg.starts_with("--") {
+            if !arg.is_empty() {
+                options.filters.push(arg);
+            }
+            continue;
+        }
+
+        let (name, inline_value) = match arg.split_once('=') {
+            Some((name, value)) => (name, Some(value.to_owned())),
+            None => (arg.as_str(), None),
+        };
+        let mut value = || {
+            inline_value
+                .clone()
+                .or_else(|| args.next())
+                .ok_or_else(|| format!("{} requires a value", name))
+        };
+        // The help lists a flag by its bare name. Without this check,
+        // `--tint=no` would turn tinting on.
+        if inline_value.is_some() && OPTION_HELP.iter().any(|(usage, _)| *usage == name) {
+            return Err(format!("{} doesn't take a value", name));
+        }
+
+        match name {
+            "--" => only_filters = true,
+            "--file" => options.files.push(PathBuf::from(value()?)),
+            "--follow" => options.follow = true,
+            "--config" => options.config = Some(PathBuf::from(value()?)),
+            "--jsonl-out" => options.jsonl_out = Some(PathBuf::from(value()?)),
+            "--dump-colors" => options.dump_colors = true,
+            "--exec" => options.exec = Some(value()?),
+            "--serve" => options.serve = Some(PathBuf::from(value()?)),
+            "--attach" => options.attach = Some(PathBuf::from(value()?)),
+            "--decompress" => options.decompress = Decompress::parse(&value()?)?,
+            "--on-overflow" => options.on_overflow = Overflow::parse(&value()?)?,
+            "--no-altscreen" => options.alternate_screen = false,
+            "--no-mouse" => options.mouse = false,
+            "--strip-cursor-codes" => options.capture.strip_cursor_codes = true,
+            "--strip-cr" => options.capture.strip_cr = true,
+            "--flush-partial" => options.capture.flush_partial = true,
+            "--transform" => {
+                for name in value()?.split(',') {
+                    options
+                        .capture
+                        .transforms
+                        .push(Transform::parse(name.trim())?);
+                }
+            }
+            "--tabstop" => {
+                let text = value()?;
+                tab_stop = Some(
+                    text.parse::<usize>()
+                        .ok()
+                        .filter(|&width| width > 0)
+                        .ok_or_else(|| {
+                            format!(
+                                "invalid --tabstop: {} (expected a column count above 0)",
+                                text
+                            )
+                        })?,
+                );
+            }
+            "--no-auto-pause" => options.auto_pause = false,
+            "--pause-on-blur" => options.pause_on_blur = true,
+            "--selection-timeout" => {
+                let text = value()?;
+                let timeout = match text.parse::<u64>() {
+                    Ok(secs) => Duration::from_secs(secs),
+                    Err(_) => parse_duration(&text)?,
+                };
+                options.selection_timeout = Some(timeout).filter(|timeout| !timeout.is_zero());
+            }
+            "--mem" => options.display.memory = true,
+            "--tabs-bottom" => options.display.tabs_bottom = true,
+            "--tint" => options.display.tint = true,
+            "--seq-gutter" => options.display.seq_gutter = true,
+            "--gutter-separator" => options.gutter_separator = value()?,
+            "--history" => {
+                let text = value()?;
+                options.history_lines = text
+                    .parse()
+                    .map_err(|_| format!("invalid --history: {} (expected a line count)", text))?;
+            }
+            "--scrollback" => {
+                let text = value()?;
+                options.scrollback =
+                    text.parse()
+                        .ok()
+                        .filter(|&lines| lines > 0)
+                        .ok_or_else(|| {
+                            format!(
+                                "invalid --scrollback: {} (expected a line count above 0)",
+                                text
+                            )
+                        })?;
+            }
+            "--no-all" => options.all_tab = false,
+            "--auto-levels" => options.auto_levels = true,
+            // Takes its value only inline, so a filter can follow it.
+            "--print-config" => {
+                options.print_config = Some(ConfigFormat::parse(inline_value.as_deref())?);
+            }
+            "--skip-blank" => options.skip_blank = true,
+            "--and-tokens" => options.and_tokens = true,
+            "--json" => options.json = true,
+            "--json-template" => {
+                options.json = true;
+                options.json_template = Some(value()?);
+            }
+            "--confirm-quit" => options.confirm_quit = true,
+            "--on-match" => options.on_match.push(value()?),
+            "--gaps" => options.gap_threshold = Some(parse_duration(&value()?)?),
+            "--confirm-ctrl-c" => {
+                options.confirm_quit = true;
+                options.confirm_ctrl_c = true;
+            }
+            "--status-glyphs" => options.display.status_glyphs = true,
+            "--bar-rule" => options.display.bar_rule = true,
+            "--recenter-on-select" => options.display.recenter_on_select = true,
+            "--zero-key" => options.zero_key = ZeroKey::parse(&value()?)?,
+            "--badges" => options.display.badge_layout = BadgeLayout::parse(&value()?)?,
+            "--selection-style" => {
+                options.display.selection_style = SelectionStyle::parse(&value()?)?
+            }
+            "--flash-badges" => options.display.flash_badges = true,
+            "--color-groups" => options.display.color_groups = true,
+            "--since" => time_window_args.since = Some(value()?),
+            "--until" => time_window_args.until = Some(value()?),
+            "--timestamp-regex" => time_window_args.timestamp_regex = Some(value()?),
+            "--drop-untimestamped" => time_window_args.drop_untimestamped = true,
+            _ => return Err(format!("unknown option: {}", arg)),
+        }
+    }
+
+    if let Some(width) = tab_stop {
+        let mut expands = false;
+        for transform in &mut options.capture.transforms {
+            if let Transform::ExpandTabs(stop) = transform {
+                *stop = width;
+                expands = true;
+            }
+        }
+        // `--tabstop` alone expands tabs, before any other transform sees them.
+        if !expands {
+            options
+                .capture
+                .transforms
+                .insert(0, Transform::ExpandTabs(width));
+        }
+    }
+    #[cfg(feature = "time-filter")]
+    {
+        options.capture.time_window = build_time_window(time_window_args)?;
+    }
+    #[cfg(not(feature = "time-filter"))]
+    build_time_window(time_window_args)?;
+    if options.follow && options.files.is_empty() {
+        return Err("--follow needs a --file to follow".to_owned());
+    }
+    #[cfg(not(feature = "json"))]
+    if options.json {
+        return Err("--json needs streamtabs built with the `json` feature".to_owned());
+    }
+    #[cfg(not(feature = "jsonl"))]
+    if options.jsonl_out.is_some() {
+        return Err("--jsonl-out needs streamtabs built with the `jsonl` feature".to_owned());
+    }
+    #[cfg(not(feature = "config"))]
+    if options.config.is_some() {
+        return Err("--config needs streamtabs built with the `config` feature".to_owned());
+    }
+    if options.exec.is_some() && (!options.files.is_empty() || options.attach.is_some()) {
+        return Err("--exec can't be combined with --file or --attach".to_owned());
+    }
+    if options.attach.is_some() && options.serve.is_some() {
+        return Err("--serve and --attach can't be combined".to_owned());
+    }
+    if options.attach.is_some() && !options.files.is_empty() {
+        return Err("--attach reads from the session, so it can't take --file".to_owned());
+    }
+    if options.capture.flush_partial && options.files.len() > 1 {
+        return Err("--flush-partial shows one unfinished line, so it takes one --file".to_owned());
+    }
+    #[cfg(not(unix))]
+    if options.attach.is_some() || options.serve.is_some() {
+        return Err("--serve and --attach need Unix domain sockets".to_owned());
+    }
+
+    Ok(options)
+}
+
+const FILTERS_ENV_VAR: &str = "STREAMTABS_FILTERS";
+
+/// Splits `STREAMTABS_FILTERS`: one filter per line if the value spans several
+/// lines (so filters may contain spaces), otherwise one per whitespace-separated word.
+fn filters_from_env(value: &str) -> Vec<String> {
+    let filters: Vec<&str> = if value.trim().contains('\n') {
+        value.lines().map(str::trim).collect()
+    } else {
+        value.split_whitespace().collect()
+    };
+    filters
+        .into_iter()
+        .filter(|filter| !filter.is_empty())
+        .map(str::to_owned)
+        .collect()
+}
+
+const OPTION_HELP: &[(&str, &str)] = &[
+    ("--file <path>", "Read lines from a file instead of stdin"),
+    (
+        "--follow",
+        "Keep reading --file as lines are appended, like tail -f",
+    ),
+    (
+        "--config <path>",
+        "Read filters and key bindings from a TOML file",
+    ),
+    (
+        "--decompress <mode>",
+        "auto (by .gz/.zst extension, default), none, gzip or zstd",
+    ),
+    (
+        "--on-overflow <mode>",
+        "block (default) or drop input the UI is too far behind to take",
+    ),
+    (
+        "--jsonl-out <path>",
+        "Also write filter tab matches to a JSONL file as they arrive",
+    ),
+    (
+        "--dump-colors",
+        "Keep colors in the files the b key saves a tab to",
+    ),
+    (
+        "--exec <command>",
+        "Run a shell command and read its stdout and stderr instead of stdin",
+    ),
+    (
+        "--serve <socket>",
+        "Share the input on a Unix socket for --attach",
+    ),
+    (
+        "--attach <socket>",
+        "Read a --serve session's lines and filters, read-only",
+    ),
+    (
+        "--no-altscreen",
+        "Render inline so the last frame stays in scrollback",
+    ),
+    (
+        "--no-mouse",
+        "Leave the mouse to the terminal: no clicks, wheel or hover",
+    ),
+    (
+        "--no-all",
+        "Hide the (all) tab; filter tabs are numbered from 1",
+    ),
+    (
+        "--print-config[=json]",
+        "Print the resolved tabs and settings, then exit without the UI",
+    ),
+    (
+        "--auto-levels",
+        "Open ERROR, WARN, INFO and DEBUG tabs by guessing each line's level",
+    ),
+    (
+        "--zero-key <action>",
+        "Without (all), what 0 does: all-filters (default), last or none",
+    ),
+    (
+        "--confirm-quit",
+        "Require pressing q twice to quit (Ctrl+C still quits at once)",
+    ),
+    (
+        "--confirm-ctrl-c",
+        "Like --confirm-quit, but Ctrl+C needs confirming too",
+    ),
+    (
+        "--on-match <tab>:<cmd>",
+        "Run cmd (line on stdin) for each new line in a tab, by filter or number",
+    ),
+    (
+        "--gaps <duration>",
+        "Show separators where lines paused longer than this (e.g. 5s); g toggles",
+    ),
+    (
+        "--status-glyphs",
+        "Show paused/unread-only state as corner glyphs instead of text",
+    ),
+    (
+        "--badges <layout>",
+        "Tab badges: unread (default) or total (total and unread, when wide enough)",
+    ),
+    (
+        "--flash-badges",
+        "Briefly brighten a tab's badge whenever it gets a new match",
+    ),
+    (
+        "--color-groups",
+        "Color each capture group of shown re: tabs in its own color",
+    ),
+    (
+        "--history <lines>",
+        "Recent lines kept to backfill new tabs (default 5000, 0 disables)",
+    ),
+    (
+        "--scrollback <lines>",
+        "Lines each tab keeps before dropping the oldest (default 5000)",
+    ),
+    (
+        "--mem",
+        "Show roughly how much memory stored lines use next to the tabs",
+    ),
+    (
+        "--tint",
+        "Tint the body background with a dark shade per active tab",
+    ),
+    (
+        "--seq-gutter",
+        "Show each line's sequence number (shared by all tabs) in a gutter",
+    ),
+    (
+        "--gutter-separator <text>",
+        "Put this between the gutter and the text (default \"│ \")",
+    ),
+    (
+        "--tabs-bottom",
+        "Draw the tab bar on the bottom rows, with the body above it",
+    ),
+    (
+        "--bar-rule",
+        "Extend the tab bar with a dim rule across the remaining width",
+    ),
+    (
+        "--no-auto-pause",
+        "Keep following new lines while scrolled up (pause only with Space)",
+    ),
+    (
+        "--pause-on-blur",
+        "Pause while the terminal window is unfocused, resuming on focus",
+    ),
+    (
+        "--selection-timeout <duration>",
+        "Drop a highlighted line left alone this long and resume (0 = never)",
+    ),
+    (
+        "--selection-style <style>",
+        "Highlighted line: recolor (default), reverse, or margin to keep its colors",
+    ),
+    (
+        "--recenter-on-select",
+        "While paused, center every newly selected line, even if visible",
+    ),
+    (
+        "--and-tokens",
+        "Match a filter of several words when a line has all of them, in any order",
+    ),
+    (
+        "--json",
+        "Let filters like level=error compare fields of JSON lines",
+    ),
+    (
+        "--json-template <text>",
+        "Show JSON lines as text with {field} placeholders; implies --json",
+    ),
+    (
+        "--skip-blank",
+        "Ignore empty and whitespace-only lines (unless a filter is whitespace)",
+    ),
+    (
+        "--strip-cursor-codes",
+        "Drop cursor-movement escapes from input, keeping colors",
+    ),
+    (
+        "--strip-cr",
+        "Remove every carriage return from input lines",
+    ),
+    (
+        "--flush-partial",
+        "Show a line still waiting for its newline after 200ms",
+    ),
+    (
+        "--transform <list>",
+        "Normalize lines as they arrive: trim, expand-tabs, lowercase (match only)",
+    ),
+    (
+        "--tabstop <n>",
+        "Expand tabs to stops this many columns apart (default 8 with expand-tabs)",
+    ),
+    (
+        "--since <time>",
+        "Skip lines stamped before <time> (absolute, or relative like 15m)",
+    ),
+    ("--until <time>", "Skip lines stamped after <time>"),
+    (
+        "--timestamp-regex <re>",
+        "Where to find each line's timestamp (group 1 if present)",
+    ),
+    (
+        "--drop-untimestamped",
+        "With --since/--until, also skip lines without a timestamp",
+    ),
+    ("--", "Treat every following argument as a filter"),
+];
+
+/// How `--print-config` writes the resolved setup.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ConfigFormat {
+    Text,
+    #[cfg(feature = "json")]
+    Json,
+}
+
+impl ConfigFormat {
+    /// From the value after `--print-config=`, if any.
+    fn parse(value: Option<&str>) -> Result<Self, String> {
+        match value {
+            None | Some("text") => Ok(ConfigFormat::Text),
+            #[cfg(feature = "json")]
+            Some("json") => Ok(ConfigFormat::Json),
+            #[cfg(not(feature = "json"))]
+            Some("json") => {
+                Err("--print-config=json needs streamtabs built with the `json` feature".to_owned())
+            }
+            Some(other) => Err(format!(
+                "invalid --print-config: {} (expected text or json)",
+                other
+            )),
+        }
+    }
+}
+
+/// One resolved setting, as `--print-config` shows it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ConfigValue {
+    Flag(bool),
+    Count(u64),
+    Text(String),
+    List(Vec<String>),
+    Unset,
+}
+
+impl ConfigValue {
+    fn from_duration(duration: Option<Duration>) -> Self {
+        duration.map_or(ConfigValue::Unset, |duration| {
+            ConfigValue::Text(format!("{:?}", duration))
+        })
+    }
+
+    fn from_path(path: Option<&Path>) -> Self {
+        path.map_or(ConfigValue::Unset, |path| {
+            ConfigValue::Text(path.display().to_string())
+        })
+    }
+
+    fn from_env(name: &str) -> Self {
+        std::env::var(name).map_or(ConfigValue::Unset, ConfigValue::Text)
+    }
+
+    fn text(&self) -> String {
+        match self {
+            ConfigValue::Flag(on) => if *on { "on" } else { "off" }.to_owned(),
+            ConfigValue::Count(count) => count.to_string(),
+            ConfigValue::Text(text) => format!("{:?}", text),
+            ConfigValue::List(items) if items.is_empty() => "(none)".to_owned(),
+            ConfigValue::List(items) => items
+                .iter()
+                .map(|item| format!("{:?}", item))
+                .collect::<Vec<_>>()
+                .join(", "),
+            ConfigValue::Unset => "(unset)".to_owned(),
+        }
+    }
+
+    #[cfg(feature = "json")]
+    fn json(&self) -> serde_json::Value {
+        match self {
+            ConfigValue::Flag(on) => (*on).into(),
+            ConfigValue::Count(count) => (*count).into(),
+            ConfigValue::Text(text) => text.as_str().into(),
+            ConfigValue::List(items) => items.as_slice().into(),
+            ConfigValue::Unset => serde_json::Value::Null,
+        }
+    }
+}
+
+impl MatchMode {
+    /// What the mode matches, in words, for `--print-config`.
+    fn describe(&self) -> String {
+        let quoted = |terms: &mut dyn Iterator<Item = &str>| {
+            terms
+                .map(|term| format!("{:?}", term))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        match self {
+            MatchMode::All => "every line".to_owned(),
+            MatchMode::Contains(text) => format!("contains {:?}", text),
+            MatchMode::ContainsCaseInsensitive(text) => {
+                format!("contains {:?}, ignoring case", text)
+            }
+            MatchMode::Regex(regex) => format!("regex {:?}", regex.as_str()),
+            MatchMode::Word(regex) => format!("word regex {:?}", regex.as_str()),
+            MatchMode::Not(inner) => format!("not ({})", inner.describe()),
+            MatchMode::AnyOf(terms) => format!(
+                "any of {}",
+                quoted(&mut terms.iter().map(|term| term.text.as_str()))
+            ),
+            MatchMode::AllOf(terms) => {
+                format!("all of {}", quoted(&mut terms.iter().map(String::as_str)))
+            }
+            MatchMode::AnyGroup(groups) => groups
+                .iter()
+                .map(|group| format!("all of {}", quoted(&mut group.iter().map(String::as_str))))
+                .collect::<Vec<_>>()
+                .join(", or "),
+            MatchMode::Level(level) => format!("level {}", level.label()),
+            MatchMode::JsonField { path, value } => format!("json field {} = {:?}", path, value),
+        }
+    }
+}
+
+/// Every setting after flags and the environment are applied, named after
+/// the flag that sets it.
+fn config_settings(options: &Options) -> Vec<(&'static str, ConfigValue)> {
+    use ConfigValue::{Count, Flag, List, Text};
+
+    let display = options.display;
+    let capture = &options.capture;
+    let decompress = match options.decompress {
+        Decompress::Auto => "auto",
+        Decompress::Plain => "none",
+        Decompress::Gzip => "gzip",
+        Decompress::Zstd => "zstd",
+    };
+    let on_overflow = match options.on_overflow {
+        Overflow::Block => "block",
+        Overflow::Drop => "drop",
+    };
+    let zero_key = match options.zero_key {
+        ZeroKey::AllFilters => "all-filters",
+        ZeroKey::LastTab => "last",
+        ZeroKey::Unmapped => "none",
+    };
+    let badges = match display.badge_layout {
+        BadgeLayout::Unread => "unread",
+        BadgeLayout::TotalAndUnread => "total",
+    };
+    let selection_style = match display.selection_style {
+        SelectionStyle::Recolor => "recolor",
+        SelectionStyle::Reverse => "reverse",
+        SelectionStyle::Margin => "margin",
+    };
+    let transforms = capture
+        .transforms
+        .iter()
+        .map(|transform| match transform {
+            Transform::Trim => "trim",
+            Transform::ExpandTabs(_) => "expand-tabs",
+            Transform::Lowercase => "lowercase",
+        })
+        .map(str::to_owned)
+        .collect();
+    let mut settings = vec![
+        (
+            "file",
+            List(
+                options
+                    .files
+                    .iter()
+                    .map(|path| path.display().to_string())
+                    .collect(),
+            ),
+        ),
+        ("follow", Flag(options.follow)),
+        ("config", ConfigValue::from_path(options.config.as_deref())),
+        ("keys", List(options.keys.describe())),
+        ("decompress", Text(decompress.to_owned())),
+        ("on-overflow", Text(on_overflow.to_owned())),
+        (
+            "jsonl-out",
+            ConfigValue::from_path(options.jsonl_out.as_deref()),
+        ),
+        ("dump-colors", Flag(options.dump_colors)),
+        (
+            "exec",
+            options.exec.clone().map_or(ConfigValue::Unset, Text),
+        ),
+        ("serve", ConfigValue::from_path(options.serve.as_deref())),
+        ("attach", ConfigValue::from_path(options.attach.as_deref())),
+        ("altscreen", Flag(options.alternate_screen)),
+        ("mouse", Flag(options.mouse)),
+        ("all-tab", Flag(options.all_tab)),
+        ("auto-levels", Flag(options.auto_levels)),
+        ("zero-key", Text(zero_key.to_owned())),
+        ("skip-blank", Flag(options.skip_blank)),
+        ("and-tokens", Flag(options.and_tokens)),
+        ("json", Flag(options.json)),
+        (
+            "json-template",
+            options
+                .json_template
+                .clone()
+                .map_or(ConfigValue::Unset, Text),
+        ),
+        ("strip-cursor-codes", Flag(capture.strip_cursor_codes)),
+        ("strip-cr", Flag(capture.strip_cr)),
+        ("flush-partial", Flag(capture.flush_partial)),
+        ("transform", List(transforms)),
+        (
+            "tabstop",
+            capture
+                .transforms
+                .iter()
+                .find_map(|transform| match transform {
+                    Transform::ExpandTabs(width) => Some(Count(*width as u64)),
+                    _ => None,
+                })
+                .unwrap_or(ConfigValue::Unset),
+        ),
+        ("confirm-quit", Flag(options.confirm_quit)),
+        ("confirm-ctrl-c", Flag(options.confirm_ctrl_c)),
+        ("on-match", List(options.on_match.clone())),
+        ("gaps", ConfigValue::from_duration(options.gap_threshold)),
+        ("auto-pause", Flag(options.auto_pause)),
+        ("history", Count(options.history_lines as u64)),
+        ("scrollback", Count(options.scrollback as u64)),
+        ("pause-on-blur", Flag(options.pause_on_blur)),
+        (
+            "selection-timeout",
+            ConfigValue::from_duration(options.selection_timeout),
+        ),
+        ("badges", Text(badges.to_owned())),
+        ("flash-badges", Flag(display.flash_badges)),
+        ("color-groups", Flag(display.color_groups)),
+        ("status-glyphs", Flag(display.status_glyphs)),
+        ("bar-rule", Flag(display.bar_rule)),
+        ("selection-style", Text(selection_style.to_owned())),
+        ("recenter-on-select", Flag(display.recenter_on_select)),
+        ("mem", Flag(display.memory)),
+        ("tabs-bottom", Flag(display.tabs_bottom)),
+        ("tint", Flag(display.tint)),
+        ("seq-gutter", Flag(display.seq_gutter)),
+        ("gutter-separator", Text(options.gutter_separator.clone())),
+        ("no-color", Flag(display.no_color)),
+    ];
+    #[cfg(feature = "time-filter")]
+    settings.push(("time-window", Flag(capture.time_window.is_some())));
+    settings.extend([
+        (FILTERS_ENV_VAR, ConfigValue::from_env(FILTERS_ENV_VAR)),
+        ("PAGER", ConfigValue::from_env("PAGER")),
+        (
+            "recent-filters-file",
+            ConfigValue::from_path(recent_filters_path().as_deref()),
+        ),
+    ]);
+    settings
+}
+
+/// The `--print-config` report: the tabs as they would open, then every
+/// setting.
+fn render_config(options: &Options, tabs: &[Tab], format: ConfigFormat) -> String {
+    let number_offset = tab_number_offset(tabs);
+    let tab_rows = tabs.iter().enumerate().map(|(index, tab)| {
+        let number = index + number_offset;
+        let exclude = tab
+            .exclude
+            .clone()
+            .map_or(ConfigValue::Unset, ConfigValue::Text);
+        (number, tab, exclude)
+    });
+    let settings = config_settings(options);
+    match format {
+        ConfigFormat::Text => {
+            let label_width = tabs.iter().map(|tab| display_width(&tab.label)).max();
+            let name_width = settings.iter().map(|(name, _)| name.len()).max();
+            let mut out = String::from("tabs:\n");
+            for (number, tab, exclude) in tab_rows {
+                let pad = label_width.unwrap_or(0) - display_width(&tab.label);
+                out.push_str(&format!(
+                    "  {:>2}  {}{}  {}",
+                    number,
+                    tab.label,
+                    " ".repeat(pad),
+                    tab.mode.describe()
+                ));
+                if exclude != ConfigValue::Unset {
+                    out.push_str(&format!(", hiding lines with {}", exclude.text()));
+                }
+                out.push('\n');
+            }
+            out.push_str("settings:\n");
+            for (name, value) in &settings {
+                out.push_str(&format!(
+                    "  {:<width$}  {}\n",
+                    name,
+                    value.text(),
+                    width = name_width.unwrap_or(0)
+                ));
+            }
+            out
+        }
+        #[cfg(feature = "json")]
+        ConfigFormat::Json => {
+            let tabs = tab_rows
+                .map(|(number, tab, exclude)| {
+                    serde_json::json!({
+                        "number": number,
+                        "label": tab.label,
+                        "match": tab.mode.describe(),
+                        "exclude": exclude.json(),
+                    })
+                })
+                .collect::<Vec<_>>();
+            let settings = settings
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.json()))
+                .collect::<serde_json::Map<_, _>>();
+            format!(
+                "{}\n",
+                serde_json::json!({"tabs": tabs, "settings": settings})
+            )
+        }
+    }
+}
+
+fn print_usage(binary: &str) {
+    let name_width = OPTION_HELP
+        .iter()
+        .map(|(name, _)| name.len())
+        .max()
+        .unwrap_or(0);
+    let options = OPTION_HELP
+        .iter()
+        .map(|(name, help)| format!("  {:<width$}  {}\n", name, help, width = name_width))
+        .collect::<String>();
+    eprintln!(
+        "Usage: {} [options] <filter1> <filter2> ...\n\nOptions:\n{}\n\
+         Without filter arguments, filters are read from {} \
+         (whitespace-separated, or one per line).\n\n\
+         Example:\n  tail -f app.log | {} error warn info",
+        binary, options, FILTERS_ENV_VAR, binary
+    );
+}
+
+/// Fills in what the environment decides: `NO_COLOR`, and the filters when
+/// none were given as arguments.
+fn apply_environment(options: &mut Options) {
+    options.display.no_color = std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+
+    // Positional filters win; the environment only supplies a default set.
+    if options.filters.is_empty()
+        && let Ok(value) = std::env::var(FILTERS_ENV_VAR)
+    {
+        options.filters = filters_from_env(&value);
+    }
+}
+
+/// What a `--config` file sets up: filters to open in place of positional
+/// ones, and keys that act as built-in ones.
+#[cfg(feature = "config")]
+#[derive(Debug, Default, PartialEq, Eq)]
+struct ConfigFile {
+    filters: Vec<String>,
+    keys: KeyRemap,
+}
+
+/// Loads `--config`. Its filters stand in for the environment's default set,
+/// but giving filters both there and on the command line is an error rather
+/// than one quietly winning.
+#[cfg(feature = "config")]
+fn apply_config_file(options: &mut Options) -> Result<(), String> {
+    let Some(path) = &options.config else {
+        return Ok(());
+    };
+    let text = std::fs::read_to_string(path)
+        .map_err(|err| format!("can't read --config {}: {}", path.display(), err))?;
+    let config = parse_config_file(&text).map_err(|err| format!("{}: {}", path.display(), err))?;
+    if !config.filters.is_empty() {
+        if !options.filters.is_empty() {
+            return Err(format!(
+                "filters given both on the command line and in {}; use one or the other",
+                path.display()
+            ));
+        }
+        options.filters = config.filters;
+    }
+    options.keys = config.keys;
+    Ok(())
+}
+
+/// Reads a config file: a top-level `filters` array of strings, and a
+/// `[keys]` table binding single-character keys to action names from
+/// `KEY_ACTIONS`, e.g. `"," = "pause"`.
+#[cfg(feature = "config")]
+fn parse_config_file(text: &str) -> Result<ConfigFile, String> {
+    use toml::Value;
+
+    let table = text.parse::<toml::Table>().map_err(|err| err.to_string())?;
+    let mut config = ConfigFile::default();
+    for (name, value) in table {
+        match (name.as_str(), value) {
+            ("filters", Value::Array(filters)) => {
+                for filter in filters {
+                    let Value::String(filter) = filter else {
+                        return Err("filters must be an array of strings".to_owned());
+                    };
+                    if !filter.is_empty() {
+                        config.filters.push(filter);
+                    }
+                }
+            }
+            ("filters", _) => return Err("filters must be an array of strings".to_owned()),
+            ("keys", Value::Table(keys)) => {
+                for (key, action) in keys {
+                    let &[byte] = key.as_bytes() else {
+                        return Err(format!("{:?} is not a single-character key", key));
+                    };
+                    if !byte.is_ascii_graphic() && byte != b' ' {
+                        return Err(format!("{:?} is not a single-character key", key));
+                    }
+                    let Value::String(action) = action else {
+                        return Err(format!("the action of {:?} must be a string", key));
+                    };
+                    let Some(&(_, builtin)) = KEY_ACTIONS.iter().find(|(name, _)| *name == action)
+                    else {
+                        let names = KEY_ACTIONS
+                            .iter()
+                            .map(|(name, _)| *name)
+                            .collect::<Vec<_>>();
+                        return Err(format!(
+                            "unknown action {:?} (expected one of {})",
+                            action,
+                            names.join(", ")
+                        ));
+                    };
+                    config.keys.bind(byte, builtin);
+                }
+            }
+            ("keys", _) => return Err("keys must be a table".to_owned()),
+            (name, _) => return Err(format!("unknown setting {:?}", name)),
+        }
+    }
+    Ok(config)
+}
+
+fn filter_tab(filter: String, and_tokens: bool, json: bool, scrollback: usize) -> Tab {
+    let mut tab = Tab::new(filter).with_scrollback(scrollback);
+    if json {
+        tab = tab.with_json_fields();
+    }
+    if and_tokens {
+        tab.with_and_tokens()
+    } else {
+        tab
+    }
+}
+
+/// The tabs the UI opens with: `(all)`, the level tabs, then the filters.
+fn startup_tabs(options: &Options) -> Vec<Tab> {
+    let mut tabs = Vec::with_capacity(options.filters.len() + Level::ALL.len() + 1);
+    if options.all_tab {
+        tabs.push(Tab::unfiltered().with_scrollback(options.scrollback));
+    }
+    if options.auto_levels {
+        tabs.extend(
+            Level::ALL.map(|level| Tab::for_level(level).with_scrollback(options.scrollback)),
+        );
+    }
+    tabs.extend(options.filters.iter().map(|filter| {
+        filter_tab(
+            filter.clone(),
+            options.and_tokens,
+            options.json,
+            options.scrollback,
+        )
+    }));
+    tabs
+}
+
+/// What `App::handle_ui` leaves to whoever owns the terminal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TerminalAction {
+    Quit,
+    /// Put this text on the clipboard, then say so in the status row.
+    Copy(String),
+    /// Show this text in the pager.
+    Page(String),
+    /// Repaint every row, not just the ones that changed.
+    Redraw,
+}
+
+/// Everything the UI knows between frames: the tabs and what they show, and
+/// how the view is paused, scrolled and highlighted. `run` feeds it input and
+/// key messages and draws it; nothing in here needs a terminal, except
+/// drawing to learn the screen's size.
+pub struct App {
+    options: Options,
+    tabs: Vec<Tab>,
+    active_index: usize,
+    active_tab_indices: Vec<usize>,
+    next_seq: u64,
+    history: History,
+    selected_line: Option<SelectedLine>,
+    recent_filters: RecentFilters,
+    dirty: bool,
+    paused: bool,
+    detail_pane: bool,
+    tab_preview: bool,
+    unread_only: bool,
+    column_selection: Option<ColumnSelection>,
+    column_dragging: bool,
+    /// Columns of a drag count from the end of the gutter it started beside.
+    drag_gutter: u16,
+    quit_requested_at: Option<Instant>,
+    status: Option<StatusMessage>,
+    pause_snapshot: Option<PauseSnapshot>,
+    /// While held, input stays queued in the bounded channel so the reader
+    /// blocks and a producer that respects backpressure stalls too.
+    input_held: bool,
+    input_closed: bool,
+    exit_status: Option<ExitStatus>,
+    search_prompt: Option<String>,
+    palette: Option<Palette>,
+    repeat_count: Option<usize>,
+    overview: Option<usize>,
+    marker: Option<Marker>,
+    frequency_view: Option<FrequencySort>,
+    split: Option<SplitView>,
+    relative_seq: bool,
+    wrap: bool,
+    clock: bool,
+    help_bar: bool,
+    tab_scroll: usize,
+    /// Off keeps live views where they are as lines arrive; unlike a pause
+    /// the lines are still shown as read.
+    follow: bool,
+    /// The active tab as of the last draw; the bar follows it when it changes.
+    drawn_active_tab: Option<usize>,
+    paused_secs_drawn: Option<u64>,
+    /// The latest end of a badge flash on screen, under `--flash-badges`.
+    flash_until: Option<Instant>,
+    /// When the highlighted line last changed, for `--selection-timeout`.
+    last_selected_seq: Option<u64>,
+    selection_touched_at: Instant,
+    show_gaps: bool,
+    skipped_lines: Vec<(u64, usize)>,
+    partial_line: Option<String>,
+    gap_threshold: Duration,
+    scroll_top: Option<u64>,
+    scroll_delta: isize,
+    /// Set when scrolling up paused the view, so returning to the bottom resumes.
+    scroll_paused: bool,
+    /// Likewise for losing focus, so regaining it resumes.
+    blur_paused: bool,
+    scrolled_tabs: Vec<usize>,
+    render_state: RenderState,
+    hover_row: Option<u16>,
+    pinned: Vec<SelectedLine>,
+    /// Names of the `--file`s, when several are read; see `source_labels`.
+    sources: Vec<String>,
+    /// Handed to every filter tab, including those opened later.
+    #[cfg(feature = "jsonl")]
+    export: Option<jsonl_export::JsonlExport>,
+}
+
+impl App {
+    fn new(options: Options, tabs: Vec<Tab>, recent_filters: RecentFilters) -> Self {
+        let mut history = History::new(options.history_lines);
+        history.fold_case = options.capture.transforms.contains(&Transform::Lowercase);
+        Self {
+            tabs,
+            active_index: 0,
+            active_tab_indices: vec![0],
+            next_seq: 0,
+            history,
+            selected_line: None,
+            recent_filters,
+            dirty: true,
+            paused: false,
+            detail_pane: false,
+            tab_preview: false,
+            unread_only: false,
+            column_selection: None,
+            column_dragging: false,
+            drag_gutter: 0,
+            quit_requested_at: None,
+            status: None,
+            pause_snapshot: None,
+            input_held: false,
+            input_closed: false,
+            exit_status: None,
+            search_prompt: None,
+            palette: None,
+            repeat_count: None,
+            overview: None,
+            marker: None,
+            frequency_view: None,
+            split: None,
+            relative_seq: false,
+            wrap: false,
+            clock: false,
+            help_bar: false,
+            tab_scroll: 0,
+            follow: true,
+            drawn_active_tab: None,
+            paused_secs_drawn: None,
+            flash_until: None,
+            last_selected_seq: None,
+            selection_touched_at: Instant::now(),
+            show_gaps: options.gap_threshold.is_some(),
+            skipped_lines: Vec::new(),
+            partial_line: None,
+            gap_threshold: options.gap_threshold.unwrap_or(DEFAULT_GAP_THRESHOLD),
+            scroll_top: None,
+            scroll_delta: 0,
+            scroll_paused: false,
+            blur_paused: false,
+            scrolled_tabs: vec![0],
+            render_state: RenderState::default(),
+            hover_row: None,
+            pinned: Vec::new(),
+            sources: match options.files.as_slice() {
+                [] | [_] => Vec::new(),
+                paths => source_labels(paths),
+            },
+            #[cfg(feature = "jsonl")]
+            export: None,
+            options,
+        }
+    }
+
+    /// Sends the matches of every filter tab, now and to come, to `export`.
+    #[cfg(feature = "jsonl")]
+    fn set_export(&mut self, export: jsonl_export::JsonlExport) {
+        self.export = Some(export);
+        for index in 0..self.tabs.len() {
+            self.attach_export(index);
+        }
+    }
+
+    /// Gives the tab at `index` the `--jsonl-out` export, unless it's `(all)`.
+    fn attach_export(&mut self, index: usize) {
+        #[cfg(feature = "jsonl")]
+        if let Some(export) = &self.export {
+            let tab = &mut self.tabs[index];
+            if tab.export.is_none() && !matches!(tab.mode, MatchMode::All) {
+                tab.export = Some(export.clone());
+            }
+        }
+        #[cfg(not(feature = "jsonl"))]
+        let _ = index;
+    }
+
+    /// An app for these command-line arguments, as `run` would start it but
+    /// without a config file, the environment or recent filters.
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let options = parse_args(args)?;
+        for filter in &options.filters {
+            check_filter(filter)?;
+        }
+        let tabs = startup_tabs(&options);
+        Ok(Self::new(options, tabs, RecentFilters::default()))
+    }
+
+    pub fn tabs(&self) -> &[Tab] {
+        &self.tabs
+    }
+
+    /// The tab the bar shows as active.
+    pub fn active_tab(&self) -> usize {
+        self.active_index
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// The seq of the highlighted line, if any.
+    pub fn selected_seq(&self) -> Option<u64> {
+        self.selected_line.as_ref().map(|line| line.seq)
+    }
+
+    /// Drops the scroll position so the next frame brings the selection or
+    /// marker into view. A pause that scrolling up started stays on, but as
+    /// an ordinary pause: the view didn't reach the bottom, so it mustn't
+    /// resume as if it had.
+    fn jump_view(&mut self) {
+        self.scroll_top = None;
+        self.scroll_paused = false;
+    }
+
+    /// Where the last frame put things, for what the mouse lands on.
+    pub fn render_state(&self) -> &RenderState {
+        &self.render_state
+    }
+
+    /// Whether something changed since the last frame.
+    pub fn needs_redraw(&self) -> bool {
+        self.dirty
+    }
+
+    /// Hands a line of input to the tabs. False if nothing took it, as with
+    /// blank lines under `--skip-blank` when no tab matches whitespace.
+    pub fn apply_line(&mut self, line: &str) -> bool {
+        self.apply_source_line(line, None)
+    }
+
+    /// Like `apply_line`, for a line from the `source`th of several files.
+    fn apply_source_line(&mut self, line: &str, source: Option<usize>) -> bool {
+        if self.partial_line.take().is_some() && !self.paused {
+            self.dirty = true;
+        }
+        let record = LineRecord::new(self.next_seq, line, source);
+        if !ingest_line(
+            &mut self.tabs,
+            &self.active_tab_indices,
+            self.paused,
+            &record,
+            self.options.skip_blank,
+            self.history.fold_case,
+        ) {
+            return false;
+        }
+        // Blank lines only reach whitespace tabs under --skip-blank.
+        if !self.options.skip_blank || !is_blank_line(line) {
+            self.history.push_record(record);
+        }
+        self.next_seq = self.next_seq.saturating_add(1);
+        if !self.paused {
+            self.dirty = true;
+        }
+        true
+    }
+
+    /// Takes a message from the input reader. Its errors end the app.
+    pub fn apply_input(&mut self, message: InputMessage) -> io::Result<()> {
+        match message {
+            InputMessage::Line(line, source) => {
+                self.apply_source_line(&line, source);
+            }
+            InputMessage::Skipped(count) => self.skip_lines(count),
+            InputMessage::Partial(text) => self.show_partial(text),
+            InputMessage::Closed => self.input_closed = true,
+            InputMessage::Error(err) => return Err(io::Error::other(err)),
+        }
+        Ok(())
+    }
+
+    /// Notes `count` lines dropped under `--on-overflow drop`.
+    fn skip_lines(&mut self, count: usize) {
+        // The dropped lines keep their seqs, so the gutter shows the jump too.
+        self.next_seq = self.next_seq.saturating_add(count as u64);
+        // Runs before every tab's oldest line can never be drawn again.
+        let oldest = self
+            .tabs
+            .iter()
+            .filter_map(|tab| tab.lines.front())
+            .map(|line| line.seq)
+            .min()
+            .unwrap_or(self.next_seq);
+        self.skipped_lines.retain(|&(seq, _)| seq >= oldest);
+        self.skipped_lines.push((self.next_seq, count));
+        if !self.paused {
+            self.dirty = true;
+        }
+    }
+
+    fn show_partial(&mut self, text: String) {
+        self.partial_line = Some(text);
+        if !self.paused {
+            self.dirty = true;
+        }
+    }
+
+    /// Reports how the `--exec` command ended.
+    fn command_exited(&mut self, ended: ExitStatus) {
+        self.exit_status = Some(ended);
+        let text = describe_exit(ended);
+        let message = if ended.success() {
+            StatusMessage::success(text)
+        } else {
+            StatusMessage::error(text)
+        };
+        self.status = Some(StatusMessage {
+            expires_at: Instant::now() + EXIT_STATUS_DURATION,
+            ..message
+        });
+        self.dirty = true;
+    }
+
+    /// Acts on a key, mouse or background message. What needs the terminal
+    /// itself is handed back.
+    pub fn handle_ui(&mut self, message: UiMessage) -> io::Result<Option<TerminalAction>> {
+        // While paused, digits type a repeat count for the next
+        // navigation key instead of switching tabs.
+        if self.paused
+            && let UiMessage::SelectTab(digit) = message
+        {
+            let count = self.repeat_count.unwrap_or(0) * 10 + digit;
+            if count > 0 {
+                self.repeat_count = Some(count.min(MAX_REPEAT_COUNT));
+                self.status = Some(StatusMessage::info(format!(
+                    "{}… (j/k, arrows, PageUp/PageDown, n/N)",
+                    count.min(MAX_REPEAT_COUNT)
+                )));
+                self.dirty = true;
+                return Ok(None);
+            }
+        }
+        let count = if message.is_background() {
+            1
+        } else {
+            self.repeat_count.take().unwrap_or(1)
+        };
+        let message = message.repeated(count);
+        match message {
+            UiMessage::NextTab if self.split.is_some_and(|split| split.bottom_focused) => {
+                if let Some(split) = self.split.as_mut() {
+                    split.show_tab((split.tab + 1) % self.tabs.len());
+                }
+                self.scroll_paused = false;
+                self.dirty = true;
+            }
+            UiMessage::PrevTab if self.split.is_some_and(|split| split.bottom_focused) => {
+                if let Some(split) = self.split.as_mut() {
+                    split.show_tab((split.tab + self.tabs.len() - 1) % self.tabs.len());
+                }
+                self.scroll_paused = false;
+                self.dirty = true;
+            }
+            UiMessage::SelectTab(number)
+                if self.overview.is_none()
+                    && self.split.is_some_and(|split| split.bottom_focused) =>
+            {
+                // The lower pane shows a single tab, so a digit naming
+                // the merged view does nothing there.
+                let DigitTarget::Tab(tab_index) =
+                    digit_target(number, &self.tabs, self.options.zero_key)
+                else {
+                    return Ok(None);
+                };
+                if let Some(split) = self.split.as_mut() {
+                    split.show_tab(tab_index);
+                }
+                self.scroll_paused = false;
+                self.dirty = true;
+            }
+            UiMessage::NextTab => {
+                let next_index = (self.active_index + 1) % self.tabs.len();
+                select_tab(
+                    &mut self.tabs,
+                    &mut self.active_index,
+                    &mut self.active_tab_indices,
+                    next_index,
+                    self.paused,
+                    self.pause_snapshot.as_ref(),
+                );
+                self.dirty = true;
+            }
+            UiMessage::PrevTab => {
+                let prev_index = (self.active_index + self.tabs.len() - 1) % self.tabs.len();
+                select_tab(
+                    &mut self.tabs,
+                    &mut self.active_index,
+                    &mut self.active_tab_indices,
+                    prev_index,
+                    self.paused,
+                    self.pause_snapshot.as_ref(),
+                );
+                self.dirty = true;
+            }
+            UiMessage::SelectTab(number) => {
+                self.overview = None;
+                match digit_target(number, &self.tabs, self.options.zero_key) {
+                    DigitTarget::Tab(tab_index) => select_tab(
+                        &mut self.tabs,
+                        &mut self.active_index,
+                        &mut self.active_tab_indices,
+                        tab_index,
+                        self.paused,
+                        self.pause_snapshot.as_ref(),
+                    ),
+                    DigitTarget::AllTabs => select_all_tabs(
+                        &mut self.tabs,
+                        &mut self.active_index,
+                        &mut self.active_tab_indices,
+                        self.paused,
+                        self.pause_snapshot.as_ref(),
+                    ),
+                    DigitTarget::Nothing => return Ok(None),
+                }
+                self.dirty = true;
+            }
+            UiMessage::TogglePause => {
+                self.paused = !self.paused;
+                self.scroll_paused = false;
+                self.blur_paused = false;
+                if self.paused {
+                    self.pause_snapshot = Some(pause_view(
+                        &mut self.tabs,
+                        &self.active_tab_indices,
+                        self.next_seq,
+                    ));
+                } else {
+                    self.pause_snapshot = None;
+                    mark_tabs_seen_live(&mut self.tabs, &self.active_tab_indices);
+                }
+                self.dirty = true;
+            }
+            UiMessage::ClearSelection => {
+                let line = self.selected_line.take();
+                let columns = self.column_selection.take();
+                if line.is_some() || columns.is_some() {
+                    self.dirty = true;
+                }
+            }
+            UiMessage::CopySelection => {
+                // Nothing highlighted leaves the clipboard alone.
+                if let Some(line) = &self.selected_line {
+                    return Ok(Some(TerminalAction::Copy(strip_ansi(&line.text))));
+                }
+            }
+            UiMessage::ScrollTabs(delta) => {
+                let last = self.tabs.len().saturating_sub(1);
+                self.tab_scroll = self.tab_scroll.saturating_add_signed(delta).min(last);
+                self.dirty = true;
+            }
+            UiMessage::ToggleHelp => {
+                self.help_bar = !self.help_bar;
+                self.dirty = true;
+            }
+            UiMessage::DumpActiveTab => {
+                let tab = &self.tabs[self.active_index];
+                self.status = Some(
+                    match dump_tab(
+                        tab,
+                        Path::new("."),
+                        self.options.dump_colors,
+                        SystemTime::now(),
+                    ) {
+                        Ok((path, count)) => StatusMessage::success(format!(
+                            "wrote {} lines to {}",
+                            count,
+                            path.display()
+                        )),
+                        Err(err) => {
+                            StatusMessage::error(format!("couldn't save {}: {}", tab.label, err))
+                        }
+                    },
+                );
+                self.dirty = true;
+            }
+            UiMessage::ToggleMarker => {
+                let selected_seq = self.selected_line.as_ref().map(|line| line.seq);
+                match (self.marker, selected_seq) {
+                    // Moving the marker keeps the slice direction.
+                    (Some(current), Some(seq)) if current.seq != seq => {
+                        self.marker = Some(Marker { seq, ..current });
+                    }
+                    (Some(_), _) => {
+                        self.marker = None;
+                        self.status = Some(StatusMessage::info("marker cleared"));
+                    }
+                    (None, Some(seq)) => {
+                        self.marker = Some(Marker {
+                            seq,
+                            slice: MarkerSlice::Off,
+                        });
+                    }
+                    (None, None) => {
+                        self.status = Some(StatusMessage::info(
+                            "highlight a line first (click, s or j/k) to mark it",
+                        ));
+                    }
+                }
+                self.dirty = true;
+            }
+            UiMessage::CycleMarkerSlice => {
+                match self.marker.as_mut() {
+                    Some(marker) => {
+                        marker.slice = marker.slice.next();
+                        self.jump_view();
+                    }
+                    None => {
+                        self.status = Some(StatusMessage::info(
+                            "no marker; press x on a highlighted line to set one",
+                        ));
+                    }
+                }
+                self.dirty = true;
+            }
+            UiMessage::CycleFrequencyView => {
+                self.frequency_view = match self.frequency_view {
+                    None => Some(FrequencySort::Count),
+                    Some(FrequencySort::Count) => Some(FrequencySort::Recency),
+                    Some(FrequencySort::Recency) => None,
+                };
+                if self.frequency_view.is_none() {
+                    for tab in &mut self.tabs {
+                        tab.line_counts = None;
+                    }
+                }
+                self.dirty = true;
+            }
+            UiMessage::OpenPager => {
+                let mut text = String::new();
+                for line in &self.tabs[self.active_index].lines {
+                    text.push_str(&line.text);
+                    text.push('\n');
+                }
+                return Ok(Some(TerminalAction::Page(text)));
+            }
+            UiMessage::ToggleRelativeSeq => {
+                if !self.options.display.seq_gutter {
+                    self.status = Some(StatusMessage::info(
+                        "relative numbers need the seq gutter (#)",
+                    ));
+                } else {
+                    self.relative_seq = !self.relative_seq;
+                    self.status = Some(StatusMessage::info(if self.relative_seq {
+                        "seq gutter: relative to the highlighted line"
+                    } else {
+                        "seq gutter: absolute"
+                    }));
+                }
+                self.dirty = true;
+            }
+            UiMessage::ToggleSeqGutter => {
+                self.options.display.seq_gutter = !self.options.display.seq_gutter;
+                self.column_selection = None;
+                self.status = Some(StatusMessage::info(if self.options.display.seq_gutter {
+                    "seq gutter: shown"
+                } else {
+                    "seq gutter: hidden"
+                }));
+                self.dirty = true;
+            }
+            UiMessage::ToggleFollow => {
+                self.follow = !self.follow;
+                if self.follow && !self.paused {
+                    self.jump_view();
+                }
+                self.status = Some(StatusMessage::info(if self.follow {
+                    "following new lines"
+                } else {
+                    "holding the view; new lines still arrive"
+                }));
+                self.dirty = true;
+            }
+            UiMessage::ToggleWrap => {
+                self.wrap = !self.wrap;
+                self.column_selection = None;
+                self.status = Some(StatusMessage::info(if self.wrap {
+                    "long lines: wrapped"
+                } else {
+                    "long lines: clipped"
+                }));
+                self.dirty = true;
+            }
+            UiMessage::ToggleClock => {
+                self.clock = !self.clock;
+                self.column_selection = None;
+                self.status = Some(StatusMessage::info(if self.clock {
+                    "gutter: arrival times shown"
+                } else {
+                    "gutter: arrival times hidden"
+                }));
+                self.dirty = true;
+            }
+            UiMessage::CloseTab => {
+                self.dirty = true;
+                let closing = self.active_index;
+                match close_tab(
+                    &mut self.tabs,
+                    &mut self.active_index,
+                    &mut self.active_tab_indices,
+                    &mut self.scrolled_tabs,
+                    closing,
+                    self.paused,
+                    self.pause_snapshot.as_mut(),
+                ) {
+                    Ok(closed) => {
+                        self.status = Some(StatusMessage::info(format!("closed {}", closed.label)));
+                        self.split = self.split.and_then(|mut split| {
+                            if split.tab == closing {
+                                return None;
+                            }
+                            if split.tab > closing {
+                                split.tab -= 1;
+                            }
+                            Some(split)
+                        });
+                        self.overview = self.overview.map(|row| row.min(self.tabs.len() - 1));
+                    }
+                    Err(reason) => self.status = Some(StatusMessage::info(reason)),
+                }
+            }
+            UiMessage::MarkRead => {
+                let Some(seq) = self.selected_line.as_ref().map(|line| line.seq) else {
+                    self.status = Some(StatusMessage::info(
+                        "highlight a line first (click, s or j/k) to mark read through it",
+                    ));
+                    self.dirty = true;
+                    return Ok(None);
+                };
+                for index in 0..self.tabs.len() {
+                    mark_tab_seen_through(&mut self.tabs, index, seq);
+                }
+                self.status = Some(StatusMessage::info(
+                    "marked read through the highlighted line",
+                ));
+                self.dirty = true;
+            }
+            UiMessage::ToggleSplit => {
+                self.split = match self.split {
+                    Some(_) => None,
+                    None => Some(SplitView::new((self.active_index + 1) % self.tabs.len())),
+                };
+                self.dirty = true;
+            }
+            UiMessage::SwitchSplitFocus => {
+                if let Some(split) = self.split.as_mut() {
+                    split.bottom_focused = !split.bottom_focused;
+                    self.dirty = true;
+                }
+            }
+            UiMessage::ToggleOverview => {
+                self.overview = match self.overview {
+                    Some(_) => None,
+                    None => Some(self.active_index),
+                };
+                self.dirty = true;
+            }
+            UiMessage::MoveSelection(rows) | UiMessage::Scroll(rows) if self.overview.is_some() => {
+                self.overview = self
+                    .overview
+                    .map(|cursor| cursor.saturating_add_signed(rows).min(self.tabs.len() - 1));
+                self.dirty = true;
+            }
+            UiMessage::ScrollPage(pages) if self.overview.is_some() => {
+                self.overview = Some(if pages < 0 { 0 } else { self.tabs.len() - 1 });
+                self.dirty = true;
+            }
+            UiMessage::Activate => {
+                if let Some(cursor) = self.overview.take() {
+                    select_tab(
+                        &mut self.tabs,
+                        &mut self.active_index,
+                        &mut self.active_tab_indices,
+                        cursor,
+                        self.paused,
+                        self.pause_snapshot.as_ref(),
+                    );
+                    self.dirty = true;
+                }
+            }
+            UiMessage::MoveSelection(delta) => {
+                let from_seq = match &self.selected_line {
+                    Some(line) => line.seq,
+                    None => match middle_visible_line(&self.render_state) {
+                        Some(line) => line.seq,
+                        None => return Ok(None),
+                    },
+                };
+                let lines = prepare_visible_lines_for_tabs(
+                    &self.tabs,
+                    &self.active_tab_indices,
+                    self.pause_snapshot
+                        .as_ref()
+                        .map(|snapshot| snapshot.line_cutoffs.as_slice()),
+                    self.selected_line.as_ref(),
+                    self.unread_only,
+                );
+                let mut lines = lines;
+                slice_at_marker(&mut lines, self.marker);
+                let Some(line) = moved_selection(&lines, from_seq, delta) else {
+                    return Ok(None);
+                };
+                self.selected_line = Some(line);
+                self.jump_view();
+                // Like a search match, the viewport follows the selection while paused.
+                if !self.paused {
+                    self.paused = true;
+                    self.pause_snapshot = Some(pause_view(
+                        &mut self.tabs,
+                        &self.active_tab_indices,
+                        self.next_seq,
+                    ));
+                }
+                self.dirty = true;
+            }
+            UiMessage::SelectMiddleVisibleLine => {
+                if let Some(line) = middle_visible_line(&self.render_state) {
+                    toggle_selected_line(&mut self.selected_line, line);
+                    self.dirty = true;
+                }
+            }
+            UiMessage::ToggleDetailPane => {
+                self.detail_pane = !self.detail_pane;
+                self.dirty = true;
+            }
+            UiMessage::ToggleUnreadOnly => {
+                self.unread_only = !self.unread_only;
+                self.dirty = true;
+            }
+            UiMessage::TogglePreview => {
+                self.tab_preview = !self.tab_preview;
+                self.dirty = true;
+            }
+            UiMessage::CycleMatchMode => {
+                if matches!(self.tabs[self.active_index].mode, MatchMode::All) {
+                    return Ok(None);
+                }
+
+                self.status = self.tabs[self.active_index]
+                    .cycle_mode()
+                    .map(StatusMessage::error);
+                if rebuild_tab(&mut self.tabs, &self.history, self.active_index) {
+                    let tab = &mut self.tabs[self.active_index];
+                    if let Some(snapshot) = self.pause_snapshot.as_mut() {
+                        snapshot.refresh_tab(self.active_index, tab);
+                        tab.mark_seen_through(snapshot.match_cutoffs[self.active_index]);
+                    } else {
+                        tab.mark_seen_through(tab.total_matches);
+                    }
+                } else if self.status.is_none() {
+                    self.status = Some(StatusMessage::info(
+                        "no history to rescan (--history 0); new mode applies to new lines",
+                    ));
+                }
+                self.dirty = true;
+            }
+            UiMessage::StartSearch => {
+                self.search_prompt = Some(String::new());
+                self.dirty = true;
+            }
+            UiMessage::OpenPalette => {
+                self.overview = None;
+                self.palette = Some(Palette::new(self.recent_filters.entries.clone()));
+                self.dirty = true;
+            }
+            UiMessage::PromptInput(ch) => {
+                if let Some(palette) = self.palette.as_mut() {
+                    palette.edit_query(|query| query.push(ch));
+                    self.dirty = true;
+                } else if let Some(text) = self.search_prompt.as_mut() {
+                    text.push(ch);
+                    self.dirty = true;
+                }
+            }
+            UiMessage::PromptBackspace => {
+                if let Some(palette) = self.palette.as_mut() {
+                    palette.edit_query(|query| {
+                        query.pop();
+                    });
+                    self.dirty = true;
+                } else if let Some(text) = self.search_prompt.as_mut() {
+                    text.pop();
+                    self.dirty = true;
+                }
+            }
+            UiMessage::PromptCancel => {
+                self.search_prompt = None;
+                self.palette = None;
+                self.dirty = true;
+            }
+            UiMessage::PromptSubmit if self.palette.is_some() => {
+                self.dirty = true;
+                let Some(filter) = self.palette.take().and_then(|palette| palette.choice()) else {
+                    return Ok(None);
+                };
+                if let Err(err) = check_filter(&filter) {
+                    self.status = Some(StatusMessage::error(err));
+                    return Ok(None);
+                }
+                self.recent_filters.remember(&filter);
+                self.recent_filters.save();
+                let tab = filter_tab(
+                    filter,
+                    self.options.and_tokens,
+                    self.options.json,
+                    self.options.scrollback,
+                );
+                let (tab_index, backfilled) = find_or_add_tab(
+                    &mut self.tabs,
+                    &self.history,
+                    tab,
+                    self.pause_snapshot.as_mut(),
+                );
+                self.attach_export(tab_index);
+                if !backfilled {
+                    self.status = Some(StatusMessage::info(
+                        "no history to backfill from (--history 0); new tab starts empty",
+                    ));
+                }
+                select_tab(
+                    &mut self.tabs,
+                    &mut self.active_index,
+                    &mut self.active_tab_indices,
+                    tab_index,
+                    self.paused,
+                    self.pause_snapshot.as_ref(),
+                );
+            }
+            UiMessage::PromptSubmit | UiMessage::SearchNext | UiMessage::SearchPrevious => {
+                if let UiMessage::PromptSubmit = message {
+                    let Some(query) = self.search_prompt.take().filter(|text| !text.is_empty())
+                    else {
+                        self.search_prompt = None;
+                        self.dirty = true;
+                        return Ok(None);
+                    };
+                    self.tabs[self.active_index].search = Some(SearchState::new(query));
+                }
+
+                let tab = &mut self.tabs[self.active_index];
+                let Some(query) = tab.search.as_ref().map(|search| search.query.clone()) else {
+                    self.status = Some(StatusMessage::info(
+                        "no search in this tab; press / to search",
+                    ));
+                    self.dirty = true;
+                    return Ok(None);
+                };
+                let visible_len = self
+                    .pause_snapshot
+                    .as_ref()
+                    .map_or(tab.lines.len(), |snapshot| {
+                        snapshot.line_cutoffs[self.active_index]
+                    });
+                let older = !matches!(message, UiMessage::SearchPrevious);
+                let mut found = None;
+                for _ in 0..count {
+                    match tab.advance_search(visible_len, older) {
+                        Some(line) => found = Some(line),
+                        None => break,
+                    }
+                }
+                match found {
+                    Some(line) => {
+                        self.selected_line = Some(line);
+                        self.jump_view();
+                        // Pausing lets the viewport move to the match.
+                        if !self.paused {
+                            self.paused = true;
+                            self.pause_snapshot = Some(pause_view(
+                                &mut self.tabs,
+                                &self.active_tab_indices,
+                                self.next_seq,
+                            ));
+                        }
+                    }
+                    None => {
+                        let direction = if older { "older" } else { "newer" };
+                        self.status = Some(StatusMessage::info(format!(
+                            "no {} match for \"{}\"",
+                            direction, query
+                        )));
+                    }
+                }
+                self.dirty = true;
+            }
+            UiMessage::Escape if self.overview.is_some() => {
+                self.overview = None;
+                self.dirty = true;
+            }
+            UiMessage::Escape => {
+                if self.tabs[self.active_index].search.take().is_some() {
+                    self.status = Some(StatusMessage::info("search cleared"));
+                    self.dirty = true;
+                }
+            }
+            UiMessage::Scroll(_) | UiMessage::ScrollPage(_) if self.palette.is_some() => {
+                let rows = match message {
+                    UiMessage::ScrollPage(pages) => pages * PALETTE_ROWS as isize,
+                    UiMessage::Scroll(rows) => rows.signum(),
+                    _ => 0,
+                };
+                if let Some(palette) = self.palette.as_mut() {
+                    palette.move_cursor(rows);
+                }
+                self.dirty = true;
+            }
+            UiMessage::Scroll(_) | UiMessage::ScrollPage(_) => {
+                let rows = match message {
+                    UiMessage::ScrollPage(pages) => {
+                        let page = self
+                            .render_state
+                            .line_rows
+                            .iter()
+                            .filter(|row| row.is_some())
+                            .count()
+                            .saturating_sub(1)
+                            .max(1);
+                        pages * page as isize
+                    }
+                    UiMessage::Scroll(rows) => rows,
+                    _ => 0,
+                };
+                if rows < 0 && !self.paused && self.options.auto_pause {
+                    self.paused = true;
+                    self.scroll_paused = true;
+                    self.pause_snapshot = Some(pause_view(
+                        &mut self.tabs,
+                        &self.active_tab_indices,
+                        self.next_seq,
+                    ));
+                }
+                match self.split.as_mut() {
+                    Some(split) if split.bottom_focused => split.scroll_delta += rows,
+                    _ => self.scroll_delta += rows,
+                }
+                self.dirty = true;
+            }
+            UiMessage::FocusLost => {
+                if self.options.pause_on_blur && !self.paused {
+                    self.paused = true;
+                    self.blur_paused = true;
+                    self.pause_snapshot = Some(pause_view(
+                        &mut self.tabs,
+                        &self.active_tab_indices,
+                        self.next_seq,
+                    ));
+                    self.dirty = true;
+                }
+            }
+            UiMessage::FocusGained => {
+                if self.blur_paused {
+                    self.paused = false;
+                    self.blur_paused = false;
+                    self.scroll_paused = false;
+                    self.pause_snapshot = None;
+                    mark_tabs_seen_live(&mut self.tabs, &self.active_tab_indices);
+                }
+                // Whatever had the focus may have drawn over the screen.
+                self.dirty = true;
+                return Ok(Some(TerminalAction::Redraw));
+            }
+            UiMessage::Redraw => {
+                self.dirty = true;
+                return Ok(Some(TerminalAction::Redraw));
+            }
+            UiMessage::ToggleGaps => {
+                self.show_gaps = !self.show_gaps;
+                self.dirty = true;
+            }
+            UiMessage::ToggleInputHold => {
+                self.input_held = !self.input_held;
+                self.dirty = true;
+            }
+            UiMessage::InvertTab => {
+                let Some(inverse) = self.tabs[self.active_index].inverted() else {
+                    if self.tabs[self.active_index].exclude.is_some() {
+                        self.status = Some(StatusMessage::info(
+                            "tabs with a ~ exclude can't be inverted",
+                        ));
+                        self.dirty = true;
+                    }
+                    return Ok(None);
+                };
+
+                let (tab_index, backfilled) = find_or_add_tab(
+                    &mut self.tabs,
+                    &self.history,
+                    inverse,
+                    self.pause_snapshot.as_mut(),
+                );
+                self.attach_export(tab_index);
+                if !backfilled {
+                    self.status = Some(StatusMessage::info(
+                        "no history to backfill from (--history 0); new tab starts empty",
+                    ));
+                }
+                select_tab(
+                    &mut self.tabs,
+                    &mut self.active_index,
+                    &mut self.active_tab_indices,
+                    tab_index,
+                    self.paused,
+                    self.pause_snapshot.as_ref(),
+                );
+                self.dirty = true;
+            }
+            UiMessage::MouseLeftDown { row, .. } if self.overview.is_some() => {
+                let clicked = self
+                    .render_state
+                    .overview_rows
+                    .iter()
+                    .find(|(overview_row, _)| *overview_row == row);
+                if let Some(&(_, tab_index)) = clicked {
+                    self.overview = None;
+                    select_tab(
+                        &mut self.tabs,
+                        &mut self.active_index,
+                        &mut self.active_tab_indices,
+                        tab_index,
+                        self.paused,
+                        self.pause_snapshot.as_ref(),
+                    );
+                    self.dirty = true;
+                }
+            }
+            UiMessage::MouseLeftDown { column, row, shift } => {
+                if let Some(tab_index) = tab_index_at_position(&self.render_state, column, row) {
+                    if let Some(split) = self.split.as_mut()
+                        && split.bottom_focused
+                        && !shift
+                    {
+                        split.show_tab(tab_index);
+                        self.scroll_paused = false;
+                    } else if shift {
+                        include_tab_in_or_view(
+                            &mut self.tabs,
+                            &mut self.active_index,
+                            &mut self.active_tab_indices,
+                            tab_index,
+                            self.paused,
+                            self.pause_snapshot.as_ref(),
+                        );
+                    } else {
+                        select_tab(
+                            &mut self.tabs,
+                            &mut self.active_index,
+                            &mut self.active_tab_indices,
+                            tab_index,
+                            self.paused,
+                            self.pause_snapshot.as_ref(),
+                        );
+                    }
+                    self.dirty = true;
+                    return Ok(None);
+                }
+
+                // Clicking in a pane focuses it.
+                if let Some(split) = self.split.as_mut()
+                    && let Some(first_row) = self.render_state.split_first_row
+                    && (row == first_row || line_at_row(&self.render_state, row).is_some())
+                    && split.bottom_focused != (row >= first_row)
+                {
+                    split.bottom_focused = row >= first_row;
+                    self.dirty = true;
+                }
+
+                // Whether this is a click or a column drag is decided on release.
+                // Wrapped rows don't start at the line's first column, so
+                // they only take clicks.
+                if let Some(line) = line_at_row(&self.render_state, row).filter(|_| !self.wrap) {
+                    self.drag_gutter = self.render_state.gutter_width_at(row);
+                    let column = column.saturating_sub(self.drag_gutter);
+                    self.column_selection = Some(ColumnSelection {
+                        seq: line.seq,
+                        anchor: column,
+                        cursor: column,
+                    });
+                    self.column_dragging = true;
+                    self.dirty = true;
+                }
+            }
+            UiMessage::MouseRightDown { row } => {
+                let Some(line) = line_at_row(&self.render_state, row) else {
+                    return Ok(None);
+                };
+                self.status = Some(toggle_pinned_line(&mut self.pinned, line));
+                self.dirty = true;
+            }
+            UiMessage::MouseMove { row } => {
+                // Only repaint when the tinted row actually changes.
+                let hovered = line_at_row(&self.render_state, row).map(|_| row);
+                if hovered != self.hover_row {
+                    self.hover_row = hovered;
+                    self.dirty = true;
+                }
+            }
+            UiMessage::MouseLeftDrag { column } => {
+                if self.column_dragging
+                    && let Some(range) = self.column_selection.as_mut()
+                {
+                    range.cursor = column.saturating_sub(self.drag_gutter);
+                    self.dirty = true;
+                }
+            }
+            UiMessage::MouseLeftUp { column } => {
+                if !std::mem::take(&mut self.column_dragging) {
+                    return Ok(None);
+                }
+                let Some(range) = self.column_selection.as_mut() else {
+                    return Ok(None);
+                };
+                range.cursor = column.saturating_sub(self.drag_gutter);
+                let range = *range;
+                let line = self
+                    .render_state
+                    .line_rows
+                    .iter()
+                    .flatten()
+                    .find(|line| line.seq == range.seq)
+                    .cloned();
+                let Some(line) = line else {
+                    self.column_selection = None;
+                    return Ok(None);
+                };
+
+                if range.anchor == range.cursor {
+                    self.column_selection = None;
+                    toggle_selected_line(&mut self.selected_line, &line);
+                } else {
+                    let (start, end) = range.columns();
+                    let text = column_range_text(&line.text, start, end);
+                    return Ok(Some(TerminalAction::Copy(text)));
+                }
+                self.dirty = true;
+            }
+            UiMessage::TerminalClosed => return Ok(Some(TerminalAction::Quit)),
+            UiMessage::Quit | UiMessage::Interrupt => {
+                let (needs_confirm, key) = match message {
+                    UiMessage::Interrupt => (self.options.confirm_ctrl_c, "Ctrl+C"),
+                    _ => (self.options.confirm_quit, "q"),
+                };
+                if !needs_confirm || quit_confirmed(&mut self.quit_requested_at, Instant::now()) {
+                    return Ok(Some(TerminalAction::Quit));
+                }
+                self.status = Some(StatusMessage::lasting(
+                    format!("Press {} again to quit", key),
+                    QUIT_CONFIRM_WINDOW,
+                ));
+                self.dirty = true;
+            }
+            UiMessage::HookFailed(text) => {
+                self.status = Some(StatusMessage::error(text));
+                self.dirty = true;
+            }
+            #[cfg(feature = "jsonl")]
+            UiMessage::ExportFailed(text) => {
+                self.status = Some(StatusMessage::error(text));
+                self.dirty = true;
+            }
+            UiMessage::Error(err) => return Err(io::Error::other(err)),
+        }
+        Ok(None)
+    }
+
+    /// Catches up on what changes with time alone: timeouts, expiring status
+    /// messages and flashes, and the paused clock.
+    fn tick(&mut self) {
+        let selected_seq = self.selected_line.as_ref().map(|line| line.seq);
+        if selected_seq != self.last_selected_seq {
+            self.last_selected_seq = selected_seq;
+            self.selection_touched_at = Instant::now();
+        }
+        if let Some(timeout) = self.options.selection_timeout
+            && self.selected_line.is_some()
+            && self.selection_touched_at.elapsed() >= timeout
+        {
+            self.selected_line = None;
+            self.column_selection = None;
+            self.scroll_top = None;
+            if self.paused {
+                self.paused = false;
+                self.scroll_paused = false;
+                self.blur_paused = false;
+                self.pause_snapshot = None;
+                mark_tabs_seen_live(&mut self.tabs, &self.active_tab_indices);
+            }
+            self.dirty = true;
+        }
+
+        if self
+            .status
+            .as_ref()
+            .is_some_and(|message| Instant::now() >= message.expires_at)
+        {
+            self.status = None;
+            self.dirty = true;
+        }
+
+        // Redraw once each drawn flash is over, so the badge dims again.
+        if self
+            .flash_until
+            .is_some_and(|until| Instant::now() >= until)
+        {
+            self.flash_until = None;
+            self.dirty = true;
+        }
+
+        let paused_for = self.paused_for();
+        if self.paused
+            && !self.options.display.status_glyphs
+            && self.paused_secs_drawn != Some(paused_for.as_secs())
+        {
+            self.dirty = true;
+        }
+
+        // Each view scrolls on its own, from the bottom, and a single tab
+        // picks up where it was left.
+        if self.scrolled_tabs != self.active_tab_indices {
+            if let [left_index] = self.scrolled_tabs[..]
+                && let Some(tab) = self.tabs.get_mut(left_index)
+            {
+                tab.saved_scroll_top = self.scroll_top;
+            }
+            self.scrolled_tabs.clone_from(&self.active_tab_indices);
+            self.scroll_top = match self.active_tab_indices[..] {
+                [shown_index] => self.tabs[shown_index].restore_scroll_top(),
+                _ => None,
+            };
+            self.scroll_delta = 0;
+        }
+    }
+
+    /// How long the view has been paused; the status row counts it in
+    /// whole seconds.
+    fn paused_for(&self) -> Duration {
+        self.pause_snapshot
+            .as_ref()
+            .filter(|_| self.paused)
+            .map_or(Duration::ZERO, |snapshot| snapshot.paused_at.elapsed())
+    }
+
+    /// Draws a frame to `out`, at the terminal's size.
+    pub fn draw(&mut self, out: &mut impl Write) -> io::Result<()> {
+        let (width, height) = terminal::size()?;
+        self.draw_at(out, width, height)
+    }
+
+    /// Draws a frame for a screen of this size, whatever the real terminal's.
+    pub fn draw_at(&mut self, out: &mut impl Write, width: u16, height: u16) -> io::Result<()> {
+        let rows = self.draw_screen((width, height))?.into_rows();
+        repaint(out, &rows, None)
+    }
+
+    /// The bytes, escape sequences and all, that a frame of this size
+    /// writes to the terminal.
+    pub fn frame_text(&mut self, width: u16, height: u16) -> String {
+        let mut out = Vec::new();
+        // As in `render`, writing to a Vec can't fail.
+        let _ = self.draw_at(&mut out, width, height);
+        String::from_utf8_lossy(&out).into_owned()
+    }
+
+    /// Lays out a frame for a screen of this size without drawing it
+    /// anywhere, and returns where it put things.
+    pub fn render(&mut self, width: u16, height: u16) -> RenderState {
+        // Nothing but writing can fail, and a Vec takes every write.
+        let _ = self.draw_screen((width, height));
+        self.render_state.clone()
+    }
+
+    /// Draws a frame of this size into rows, ready to repaint the screen with.
+    fn draw_screen(&mut self, size: (u16, u16)) -> io::Result<Screen> {
+        let paused_for = self.paused_for();
+        if self.frequency_view.is_some() {
+            self.tabs[self.active_index].count_lines();
+        }
+        let frame = Frame {
+            size,
+            display: self.options.display,
+            tabs: &self.tabs,
+            history: &self.history,
+            active_tab_indices: &self.active_tab_indices,
+            paused: self.paused,
+            input_held: self.input_held,
+            exit_status: self.exit_status,
+            pause_line_cutoffs: self
+                .pause_snapshot
+                .as_ref()
+                .map(|snapshot| snapshot.line_cutoffs.as_slice()),
+            paused_for,
+            selected_line: self.selected_line.as_ref(),
+            column_selection: self.column_selection.as_ref(),
+            detail_pane: self.detail_pane,
+            tab_preview: self.tab_preview,
+            unread_only: self.unread_only,
+            status: self.status.as_ref(),
+            search_prompt: self.search_prompt.as_deref(),
+            palette: self.palette.as_ref(),
+            overview: self.overview,
+            frequency_view: self.frequency_view,
+            marker: self.marker,
+            gap_threshold: self.show_gaps.then_some(self.gap_threshold),
+            // Runs dropped since a pause belong with the lines it hides.
+            skipped_lines: &self.skipped_lines[..self.skipped_lines.partition_point(
+                |&(seq, _)| {
+                    self.pause_snapshot
+                        .as_ref()
+                        .is_none_or(|snapshot| seq <= snapshot.seq_cutoff)
+                },
+            )],
+            partial_line: self
+                .partial_line
+                .as_deref()
+                .filter(|_| self.pause_snapshot.is_none())
+                .map(|text| (self.next_seq, text)),
+            scroll_top: self.scroll_top,
+            scroll_delta: self.scroll_delta,
+            previous_viewport: self.render_state.viewport,
+            gutter_separator: &self.options.gutter_separator,
+            json_template: self.options.json_template.as_deref(),
+            relative_seq: self.relative_seq,
+            wrap: self.wrap,
+            clock: self.clock,
+            help_bar: self.help_bar,
+            tab_scroll: self.tab_scroll,
+            follow_tab: (self.drawn_active_tab != Some(self.active_index))
+                .then_some(self.active_index),
+            split: self.split.map(|split| SplitView {
+                previous_viewport: self.render_state.split_viewport,
+                ..split
+            }),
+            hover_row: self.hover_row,
+            pinned: &self.pinned,
+            sources: &self.sources,
+        };
+        let mut screen = Screen::default();
+        self.render_state = draw(&mut screen, &frame)?;
+        self.dirty = false;
+        self.tab_scroll = self.render_state.tab_scroll;
+        self.drawn_active_tab = Some(self.active_index);
+        self.paused_secs_drawn = self.paused.then_some(paused_for.as_secs());
+        if self.options.display.flash_badges {
+            let now = Instant::now();
+            self.flash_until = self
+                .tabs
+                .iter()
+                .filter_map(|tab| tab.badge_flash_until(now))
+                .max();
+        }
+        self.scroll_top = self.render_state.scroll_top;
+        self.scroll_delta = 0;
+        if let Some(split) = self.split.as_mut() {
+            split.scroll_top = self.render_state.split_scroll_top;
+            split.scroll_delta = 0;
+        }
+
+        if self.scroll_paused
+            && self.scroll_top.is_none()
+            && self.split.is_none_or(|split| split.scroll_top.is_none())
+        {
+            self.paused = false;
+            self.scroll_paused = false;
+            self.pause_snapshot = None;
+            mark_tabs_seen_live(&mut self.tabs, &self.active_tab_indices);
+            self.dirty = true;
+        }
+        // Not following pins a live view to the lines it just drew.
+        if !self.follow && !self.paused && self.scroll_top.is_none() {
+            self.scroll_top = self
+                .render_state
+                .viewport
+                .map(|viewport| viewport.start_seq);
+        }
+        Ok(screen)
+    }
+}
+
+pub fn run() -> io::Result<()> {
+    let binary = std::env::args()
+        .next()
+        .unwrap_or_else(|| "streamtabs".to_owned());
+    let mut options = match parse_args(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("{}\n", err);
+            print_usage(&binary);
+            std::process::exit(2);
+        }
+    };
+    #[cfg(feature = "config")]
+    if let Err(err) = apply_config_file(&mut options) {
+        eprintln!("{}\n", err);
+        std::process::exit(2);
+    }
+    // The server's filters stand in for ones given here or by the environment.
+    #[cfg(unix)]
+    let attached = match &options.attach {
+        Some(path) => {
+            let (filters, reader) = session::attach(path)?;
+            if options.filters.is_empty() {
+                options.filters = filters;
+            }
+            Some(reader)
+        }
+        None => None,
+    };
+    apply_environment(&mut options);
+    if let Some(err) = options
+        .filters
+        .iter()
+        .find_map(|filter| check_filter(filter).err())
+    {
+        eprintln!("{}\n", err);
+        print_usage(&binary);
+        std::process::exit(2);
+    }
+
+    // Checked before the TTY, so the output can be piped or redirected.
+    if let Some(format) = options.print_config {
+        print!(
+            "{}",
+            render_config(&options, &startup_tabs(&options), format)
+        );
+        return Ok(());
+    }
+
+    if !io::stdout().is_terminal() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "stdout must be a TTY (run this in a terminal, not redirected)",
+        ));
+    }
+
+    #[cfg(unix)]
+    ensure_locale_for_wcwidth();
+
+    if options.filters.is_empty() && !options.auto_levels {
+        print_usage(&binary);
+        std::process::exit(2);
+    }
+
+    let mut tabs = startup_tabs(&options);
+    let mut recent_filters = RecentFilters::load();
+    for filter in options.filters.iter().rev() {
+        recent_filters.remember(filter);
+    }
+    recent_filters.save();
+
+    let (tx, rx): (SyncSender<InputMessage>, Receiver<InputMessage>) = mpsc::sync_channel(1024);
+    let open_file = |path| open_input_file(path, options.decompress, options.follow);
+    let source = match options.files.as_slice() {
+        [] => InputSource::Stdin,
+        [path] => InputSource::Reader(open_file(path)?),
+        paths => {
+            let mut readers = Vec::with_capacity(paths.len());
+            for path in paths {
+                readers.push(open_file(path)?);
+            }
+            InputSource::Tagged(readers)
+        }
+    };
+    let mut command_child = None;
+    let source = match &options.exec {
+        Some(command) => {
+            let (child, reader) = spawn_command(command)?;
+            command_child = Some(child);
+            InputSource::Reader(Box::new(BufReader::new(reader)))
+        }
+        None => source,
+    };
+    #[cfg(unix)]
+    let source = match attached {
+        Some(reader) => InputSource::Reader(Box::new(reader)),
+        None => source,
+    };
+    #[cfg(unix)]
+    let mut server = match &options.serve {
+        Some(path) => Some(session::Server::bind(path, options.filters.clone())?),
+        None => None,
+    };
+    spawn_input_reader(tx, options.capture.clone(), options.on_overflow, source);
+    let (ui_tx, ui_rx): (SyncSender<UiMessage>, Receiver<UiMessage>) = mpsc::sync_channel(128);
+    for spec in &options.on_match {
+        match resolve_hook_spec(&tabs, spec) {
+            Ok((index, command)) => {
+                tabs[index].on_match = Some(MatchHook::spawn(command, ui_tx.clone()));
+            }
+            Err(err) => {
+                eprintln!("{}\n", err);
+                print_usage(&binary);
+                std::process::exit(2);
+            }
+        }
+    }
+    #[cfg(feature = "jsonl")]
+    let (jsonl_writer, jsonl_export) = match &options.jsonl_out {
+        Some(path) => {
+            let (writer, export) = jsonl_export::JsonlWriter::spawn(path, ui_tx.clone())?;
+            (Some(writer), Some(export))
+        }
+        None => (None, None),
+    };
+    let key_handoff = Arc::new(KeyHandoff::default());
+    spawn_ui_reader(ui_tx, Arc::clone(&key_handoff), options.keys.clone())?;
+
+    let mut stdout = io::stdout();
+    {
+        let guard = TerminalGuard::enter(
+            &mut stdout,
+            options.alternate_screen,
+            options.mouse,
+            options.pause_on_blur,
+        )?;
+
+        let mut app = App::new(options, tabs, recent_filters);
+        #[cfg(feature = "jsonl")]
+        if let Some(export) = jsonl_export {
+            app.set_export(export);
+        }
+        let mut last_size = terminal::size().unwrap_or((0, 0));
+        // The rows on screen, so a frame only rewrites those that change;
+        // forgotten when something else may have drawn over them.
+        let mut drawn_rows: Option<Vec<Vec<u8>>> = None;
+
+        'app: loop {
+            #[cfg(unix)]
+            if let Some(server) = server.as_mut() {
+                server.admit(&app.history, &app.sources);
+            }
+            while !app.input_held
+                && let Ok(message) = rx.try_recv()
+            {
+                match message {
+                    InputMessage::Line(line, source) => {
+                        if !app.apply_source_line(&line, source) {
+                            continue;
+                        }
+                        #[cfg(unix)]
+                        if let Some(server) = server.as_mut() {
+                            server.broadcast(&tagged_text(&line, source, &app.sources));
+                        }
+                    }
+                    message => app.apply_input(message)?,
+                }
+            }
+            // Waited for only once its output is all in, so nothing trails the status.
+            if app.input_closed
+                && app.exit_status.is_none()
+                && let Some(child) = command_child.as_mut()
+                && let Ok(Some(ended)) = child.try_wait()
+            {
+                app.command_exited(ended);
+            }
+
+            while let Ok(message) = ui_rx.try_recv() {
+                match app.handle_ui(message)? {
+                    None => {}
+                    Some(TerminalAction::Quit) => break 'app,
+                    Some(TerminalAction::Copy(text)) => {
+                        let copied = copy_to_clipboard(&mut stdout, &text)?;
+                        app.status = Some(copied_message(copied, &text));
+                        app.dirty = true;
+                    }
+                    Some(TerminalAction::Page(text)) => {
+                        if let Err(err) = page_text(&mut stdout, &guard, &key_handoff, &text)? {
+                            app.status = Some(StatusMessage::error(err));
+                        }
+                        drawn_rows = None;
+                        app.dirty = true;
+                    }
+                    Some(TerminalAction::Redraw) => drawn_rows = None,
+                }
+            }
+
+            if let Ok(current_size) = terminal::size()
+                && current_size != last_size
+            {
+                last_size = current_size;
+                drawn_rows = None;
+                app.dirty = true;
+            }
+            app.tick();
+
+            if app.dirty {
+                // Most frames differ from the last in a few rows, if any;
+                // rewriting the whole screen only makes the terminal flicker.
+                let rows = app.draw_screen(terminal::size()?)?.into_rows();
+                repaint(&mut stdout, &rows, drawn_rows.as_deref())?;
+                drawn_rows = Some(rows);
+            }
+
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+
+    // The app went with the UI, and its tabs' exports with it, so the writer
+    // can drain and exit.
+    #[cfg(feature = "jsonl")]
+    if let Some(writer) = jsonl_writer {
+        writer.finish();
+    }
+
+    if let Some(mut child) = command_child {
+        if matches!(child.try_wait(), Ok(None)) {
+            let _ = child.kill();
+        }
+        let _ = child.wait();
+    }
+    terminate_pipeline_group_if_safe();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        RenderedLine, SelectedLine, Tab, UiMessage, apply_line_to_tabs, clip_ansi_to_visible_width,
+        clip_to_width, clip_with_ellipsis, fit_tab_title, include_tab_in_or_view,
+        key_message_from_byte, mark_tab_seen_live, mark_tab_seen_paused, mark_tab_seen_through,
+        middle_visible_line, prepare_visible_lines, prepare_visible_lines_for_tabs, strip_ansi,
+        toggle_selected_line, try_parse_sgr_mouse_message, viewport_for_lines, wrap_words,
+    };
+
+    #[test]
+    fn args_split_into_options_and_filters() {
+        let args = ["--no-altscreen", "error", "", "warn"].map(String::from);
+        let options = super::parse_args(args).expect("valid args");
+        assert!(!options.alternate_screen);
+        assert_eq!(options.filters, vec!["error", "warn"]);
+
+        let defaults = super::parse_args(["error".to_owned()]).expect("valid args");
+        assert!(defaults.alternate_screen);
+
+        let parse = |arg: &str| super::parse_args([arg.to_owned()]);
+        assert_eq!(
+            parse("--tint=no").unwrap_err(),
+            "--tint doesn't take a value"
+        );
+        assert!(parse("--no-mouse=").is_err());
+        assert!(parse("--scrollback=10").is_ok());
+        assert!(parse("--print-config=text").is_ok());
+    }
+
+    #[cfg(feature = "config")]
+    #[test]
+    fn config_file_reads_filters_and_key_bindings() {
+        use super::{KEY_ACTIONS, parse_config_file};
+
+        let config = parse_config_file(
+            "# my usual tabs\n\
+             filters = [\n  \"color=red:error\", # loud\n  'i:warn',\n  \"say \\\"hi\\\"\",\n]\n\n\
+             [keys]\n\",\" = \"pause\"\nP = \"palette\"\n",
+        )
+        .unwrap();
+        assert_eq!(config.filters, ["color=red:error", "i:warn", "say \"hi\""]);
+        assert_eq!(config.keys.resolve(b','), b' ');
+        assert_eq!(config.keys.resolve(b'P'), b'r');
+        assert_eq!(config.keys.resolve(b'q'), b'q');
+        assert_eq!(config.keys.describe(), [",=pause", "P=palette"]);
+
+        let err = |text: &str| parse_config_file(text).unwrap_err();
+        assert_eq!(
+            err("filters = \"x\""),
+            "filters must be an array of strings"
+        );
+        assert_eq!(err("filters = [1]"), "filters must be an array of strings");
+        assert_eq!(err("\nwrap = \"on\""), "unknown setting \"wrap\"");
+        assert_eq!(err("[colors]"), "unknown setting \"colors\"");
+        assert_eq!(err("keys = 1"), "keys must be a table");
+        assert!(err("[keys]\nab = \"quit\"").starts_with("\"ab\" is not"));
+        assert!(err("[keys]\nx = \"fly\"").contains("unknown action \"fly\""));
+        // Bad syntax is the parser's to point at.
+        assert!(err("filters = [\"a\" \"b\"]").contains("line 1"));
+        assert!(err("\nfilters = [\"a]").contains("line 2"));
+
+        // Every action names a key that does something.
+        for &(name, byte) in KEY_ACTIONS {
+            assert!(key_message_from_byte(byte).is_some(), "{name}");
+        }
+    }
+
+    #[cfg(feature = "config")]
+    #[test]
+    fn config_file_filters_conflict_with_positional_ones() {
+        let path = std::env::temp_dir().join(format!("streamtabs-{}.toml", std::process::id()));
+        std::fs::write(&path, "filters = [\"error\"]\n[keys]\n\",\" = \"quit\"\n").unwrap();
+        let config_arg = path.display().to_string();
+
+        let mut options = super::parse_args(["--config", &config_arg].map(String::from)).unwrap();
+        super::apply_config_file(&mut options).unwrap();
+        assert_eq!(options.filters, ["error"]);
+        let mut parser = super::InputParser::with_keys(options.keys.clone());
+        assert!(matches!(parser.feed(b','), Some(UiMessage::Quit)));
+
+        let mut options =
+            super::parse_args(["--config", &config_arg, "warn"].map(String::from)).unwrap();
+        let err = super::apply_config_file(&mut options).unwrap_err();
+        assert!(err.starts_with("filters given both"), "{err}");
+
+        std::fs::remove_file(&path).unwrap();
+        let mut options = super::parse_args(["--config", &config_arg].map(String::from)).unwrap();
+        assert!(super::apply_config_file(&mut options).is_err());
+    }
+
+    #[test]
+    fn print_config_reports_tabs_and_settings_as_text_or_json() {
+        use super::{ConfigFormat, parse_args, render_config, startup_tabs};
+
+        let options = parse_args(["--print-config", "err~retry", "--tint"].map(String::from))
+            .expect("valid args");
+        assert_eq!(options.print_config, Some(ConfigFormat::Text));
+        assert_eq!(options.filters, vec!["err~retry"]);
+        let text = render_config(&options, &startup_tabs(&options), ConfigFormat::Text);
+        assert!(text.contains("   0  (all)      every line\n"));
+        assert!(text.contains("   1  err~retry  contains \"err\", hiding lines with \"retry\"\n"));
+        assert!(text.contains("\n  tint "));
+
+        let options =
+            parse_args(["--print-config", "エラー", "warn"].map(String::from)).expect("valid args");
+        let text = render_config(&options, &startup_tabs(&options), ConfigFormat::Text);
+        assert!(
+            text.contains("   1  エラー  contains \"エラー\"\n"),
+            "{text}"
+        );
+        assert!(text.contains("   2  warn    contains \"warn\"\n"), "{text}");
+
+        assert!(parse_args(["--print-config=yaml"].map(String::from)).is_err());
+        #[cfg(not(feature = "json"))]
+        assert!(parse_args(["--print-config=json"].map(String::from)).is_err());
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn print_config_as_json_is_one_object_of_tabs_and_settings() {
+        use super::{ConfigFormat, parse_args, render_config, startup_tabs};
+        use serde_json::{Value, json};
+
+        let args = [
+            "--print-config=json",
+            "--auto-levels",
+            "say \"hi\"\\\n\u{1}",
+        ];
+        let options = parse_args(args.map(String::from)).expect("valid args");
+        assert_eq!(options.print_config, Some(ConfigFormat::Json));
+        let text = render_config(&options, &startup_tabs(&options), ConfigFormat::Json);
+        assert!(text.ends_with("}\n"));
+        let report: Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(
+            report["tabs"][0],
+            json!({"number": 0, "label": "(all)", "match": "every line", "exclude": null})
+        );
+        let tabs = report["tabs"].as_array().unwrap();
+        assert!(tabs.iter().any(|tab| tab["match"] == "level WARN"));
+        assert!(tabs.iter().any(|tab| tab["label"] == "say \"hi\"\\\n\u{1}"));
+        assert_eq!(report["settings"]["history"], 5000);
+        assert_eq!(report["settings"]["auto-levels"], true);
+    }
+
+    #[test]
+    fn selection_timeout_takes_seconds_or_durations_and_zero_disables() {
+        let parse = |value: &str| {
+            super::parse_args(["--selection-timeout", value, "error"].map(String::from))
+                .map(|options| options.selection_timeout)
+        };
+        assert_eq!(parse("30"), Ok(Some(std::time::Duration::from_secs(30))));
+        assert_eq!(parse("2m"), Ok(Some(std::time::Duration::from_secs(120))));
+        assert_eq!(parse("0"), Ok(None));
+        assert!(parse("soon").is_err());
+    }
+
+    #[test]
+    fn an_untouched_selection_clears_once_the_timeout_passes() {
+        use super::{App, InputMessage, UiMessage};
+        use std::time::Duration;
+
+        let args = ["--selection-timeout", "30"].map(String::from);
+        let mut app = App::from_args(args).unwrap();
+        for n in 0..20 {
+            app.apply_input(InputMessage::Line(format!("line {n}"), None))
+                .unwrap();
+        }
+        app.render(40, 8);
+        app.handle_ui(UiMessage::MoveSelection(-5)).unwrap();
+        app.tick();
+        let selected = app.selected_seq().unwrap();
+        assert!(app.render(40, 8).visible_seqs().contains(&selected));
+
+        app.selection_touched_at -= Duration::from_secs(29);
+        app.tick();
+        assert_eq!(app.selected_seq(), Some(selected));
+
+        app.selection_touched_at -= Duration::from_secs(1);
+        app.tick();
+        let state = app.render(40, 8);
+        assert_eq!(app.selected_seq(), None);
+        assert!(!app.is_paused());
+        assert_eq!(state.visible_seqs().last(), Some(&19));
+    }
+
+    #[test]
+    fn args_after_double_dash_are_filters() {
+        let args = ["--", "--no-altscreen"].map(String::from);
+        let options = super::parse_args(args).expect("valid args");
+        assert!(options.alternate_screen);
+        assert_eq!(options.filters, vec!["--no-altscreen"]);
+
+        assert!(super::parse_args(["--bogus".to_owned()]).is_err());
+    }
+
+    #[cfg(feature = "time-filter")]
+    #[test]
+    fn time_window_bounds_lines_by_parsed_timestamp() {
+        use super::time_window::{DEFAULT_TIMESTAMP_PATTERN, TimeWindow, parse_absolute};
+
+        let window = TimeWindow {
+            since: parse_absolute("2026-02-06T10:00:00Z"),
+            until: parse_absolute("2026-02-06T11:00:00Z"),
+            timestamp_regex: regex::Regex::new(DEFAULT_TIMESTAMP_PATTERN).unwrap(),
+            keep_untimestamped: false,
+        };
+        assert!(!window.admits("2026-02-06T09:59:59Z early"));
+        assert!(window.admits("2026-02-06T10:30:00.123Z INFO inside"));
+        assert!(window.admits("2026-02-06 11:30:00+01:00 offset inside"));
+        assert!(!window.admits("2026-02-06T11:00:01Z late"));
+        assert!(!window.admits("no timestamp here"));
+    }
+
+    #[cfg(feature = "time-filter")]
+    #[test]
+    fn time_bounds_accept_relative_offsets() {
+        use super::time_window::{parse_absolute, parse_bound};
+
+        let now = parse_absolute("2026-02-06T12:00:00Z").unwrap();
+        assert_eq!(
+            parse_bound("15m", now),
+            parse_absolute("2026-02-06T11:45:00Z").ok_or(String::new())
+        );
+        assert_eq!(
+            parse_bound("-2h", now),
+            parse_absolute("2026-02-06T10:00:00Z").ok_or(String::new())
+        );
+        assert!(parse_bound("soon", now).is_err());
+    }
+
+    #[test]
+    fn env_filters_split_on_whitespace_or_lines() {
+        assert_eq!(
+            super::filters_from_env("  error warn\tre:GET\\s+/api "),
+            vec!["error", "warn", "re:GET\\s+/api"]
+        );
+        assert_eq!(
+            super::filters_from_env("connection refused\n\n  i:timeout \n"),
+            vec!["connection refused", "i:timeout"]
+        );
+        assert!(super::filters_from_env("  ").is_empty());
+    }
+
+    #[test]
+    fn followed_file_waits_for_appended_lines() {
+        use std::io::{BufRead, Write};
+
+        let path =
+            std::env::temp_dir().join(format!("streamtabs-{}-follow.log", std::process::id()));
+        std::fs::write(&path, "first\n").unwrap();
+        let mut reader = super::open_input_file(&path, super::Decompress::Auto, true).unwrap();
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        assert_eq!(line, "first\n");
+
+        let appender = {
+            let path = path.clone();
+            std::thread::spawn(move || {
+                std::thread::sleep(std::time::Duration::from_millis(100));
+                let mut file = std::fs::OpenOptions::new().append(true).open(path).unwrap();
+                file.write_all(b"sec").unwrap();
+                file.flush().unwrap();
+                std::thread::sleep(std::time::Duration::from_millis(100));
+                file.write_all(b"ond\n").unwrap();
+            })
+        };
+        line.clear();
+        reader.read_line(&mut line).unwrap();
+        assert_eq!(line, "second\n");
+        appender.join().unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let compressed = path.with_extension("log.gz");
+        std::fs::write(&compressed, "").unwrap();
+        let err = super::open_input_file(&compressed, super::Decompress::Auto, true)
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::Unsupported);
+        std::fs::remove_file(&compressed).unwrap();
+        let parse = |args: &[&str]| super::parse_args(args.iter().map(|arg| arg.to_string()));
+        assert!(parse(&["--follow", "x"]).is_err());
+        assert!(
+            parse(&["--follow", "--file", "app.log", "x"])
+                .unwrap()
+                .follow
+        );
+    }
+
+    #[test]
+    fn decompression_is_picked_by_extension_unless_overridden() {
+        use super::Decompress;
+        use std::path::Path;
+
+        assert_eq!(
+            Decompress::Auto.resolve(Path::new("app.log.gz")),
+            Decompress::Gzip
+        );
+        assert_eq!(
+            Decompress::Auto.resolve(Path::new("app.log.zst")),
+            Decompress::Zstd
+        );
+        assert_eq!(
+            Decompress::Auto.resolve(Path::new("app.log")),
+            Decompress::Plain
+        );
+        assert_eq!(
+            Decompress::Plain.resolve(Path::new("app.log.gz")),
+            Decompress::Plain
+        );
+        assert_eq!(
+            Decompress::Gzip.resolve(Path::new("archive")),
+            Decompress::Gzip
+        );
+        assert!(Decompress::parse("brotli").is_err());
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn gzip_file_input_is_decompressed_transparently() {
+        use std::io::{BufRead, Write};
+
+        let path = std::env::temp_dir().join(format!("streamtabs-{}.log.gz", std::process::id()));
+        let mut encoder = flate2::write::GzEncoder::new(
+            std::fs::File::create(&path).unwrap(),
+            flate2::Compression::default(),
+        );
+        encoder.write_all(b"first\nsecond\n").unwrap();
+        encoder.finish().unwrap();
+
+        let reader = super::open_input_file(&path, super::Decompress::Auto, false).unwrap();
+        let lines = reader.lines().collect::<Result<Vec<_>, _>>().unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(lines, vec!["first", "second"]);
+    }
+
+    #[test]
+    fn json_mode_turns_field_filters_into_field_matches() {
+        use super::MatchMode;
+
+        let tab = super::filter_tab("!level=debug".into(), false, true, 10);
+        assert!(matches!(
+            &tab.mode,
+            MatchMode::Not(inner) if matches!(&**inner, MatchMode::JsonField { path, value }
+                if path == "level" && value == "debug")
+        ));
+        assert_eq!(tab.label, "!level=debug");
+        // Lines that aren't JSON fall back to the filter's text.
+        let tab = super::filter_tab("service=auth".into(), false, true, 10);
+        assert!(tab.matches("plain service=auth-v2 line"));
+        assert!(!tab.matches("service: auth"));
+
+        for plain in ["a b=c", "=x", "re:a=b"] {
+            let tab = super::filter_tab(plain.into(), false, true, 10);
+            assert!(!matches!(tab.mode, MatchMode::JsonField { .. }), "{plain}");
+        }
+        let tab = super::filter_tab("level=error".into(), false, false, 10);
+        assert!(matches!(tab.mode, MatchMode::Contains(_)));
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn json_fields_are_matched_and_rendered_from_the_parsed_line() {
+        use super::json_input::render;
+
+        let line = r#"{"level":"error","msg":"disk full","ctx":{"code":28,"ok":false}}"#;
+        let tab = super::filter_tab("ctx.code=28".into(), false, true, 10);
+        assert!(tab.matches(line));
+        assert!(super::filter_tab("level=error".into(), false, true, 10).matches(line));
+        assert!(!super::filter_tab("level=err".into(), false, true, 10).matches(line));
+        assert!(!super::filter_tab("msg=level=error".into(), false, true, 10).matches(line));
+
+        assert_eq!(
+            render(line, "[{level}] {msg} ({ctx.ok}) {missing}|{{x}} {").as_deref(),
+            Some("[error] disk full (false) |{x} {")
+        );
+        assert_eq!(render("not json", "{msg}"), None);
+        assert_eq!(render("[1, 2]", "{msg}"), None);
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn json_templates_render_the_lines_in_view_however_it_scrolls() {
+        use super::{App, InputMessage, UiMessage};
+
+        let args = ["--json-template", "[{level}] {msg}", "level=error"];
+        let mut app = App::from_args(args.map(String::from)).unwrap();
+        for index in 0..30 {
+            let line = format!(r#"{{"level":"error","msg":"m{index:02}"}}"#);
+            app.apply_input(InputMessage::Line(line, None)).unwrap();
+        }
+        let shown = |app: &mut App| {
+            let frame = app.frame_text(40, 8);
+            (0..30)
+                .filter(|index| frame.contains(&format!("[error] m{index:02}")))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(shown(&mut app), [25, 26, 27, 28, 29]);
+        assert!(!app.frame_text(40, 8).contains("\"msg\""));
+
+        app.handle_ui(UiMessage::Scroll(-10)).unwrap();
+        assert_eq!(shown(&mut app), [15, 16, 17, 18, 19]);
+        // The status row saying so takes the bottom line.
+        app.handle_ui(UiMessage::ToggleWrap).unwrap();
+        assert_eq!(shown(&mut app), [15, 16, 17, 18]);
+    }
+
+    #[cfg(feature = "jsonl")]
+    #[test]
+    fn jsonl_export_writes_one_record_per_match() {
+        let path = std::env::temp_dir().join(format!("streamtabs-{}.jsonl", std::process::id()));
+        let (ui_tx, _ui_rx) = std::sync::mpsc::sync_channel(1);
+        let (writer, export) = super::jsonl_export::JsonlWriter::spawn(&path, ui_tx).unwrap();
+        let mut tabs = vec![Tab::unfiltered(), Tab::new("foo".into())];
+        tabs[1].export = Some(export);
+
+        apply_line_to_tabs(&mut tabs, &[0], false, 0, "foo \x1b[31mred\x1b[0m");
+        apply_line_to_tabs(&mut tabs, &[0], false, 1, "bar");
+        drop(tabs);
+        writer.finish();
+
+        let written = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let records: Vec<serde_json::Value> = written
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0]["seq"], 0);
+        assert_eq!(records[0]["tab"], "foo");
+        assert_eq!(records[0]["text"], "foo red");
+        assert!(records[0]["timestamp"].as_u64().is_some_and(|ms| ms > 0));
+    }
+
+    #[cfg(feature = "jsonl")]
+    #[test]
+    fn tabs_opened_while_running_join_the_jsonl_export() {
+        use super::{App, InputMessage};
+
+        let path =
+            std::env::temp_dir().join(format!("streamtabs-{}-added.jsonl", std::process::id()));
+        let (ui_tx, _ui_rx) = std::sync::mpsc::sync_channel(1);
+        let (writer, export) = super::jsonl_export::JsonlWriter::spawn(&path, ui_tx).unwrap();
+        let mut app = App::from_args(["foo".to_owned()]).unwrap();
+        app.set_export(export);
+        app.handle_ui(UiMessage::SelectTab(1)).unwrap();
+        app.handle_ui(UiMessage::InvertTab).unwrap();
+        assert_eq!(app.tabs().len(), 3);
+        app.apply_input(InputMessage::Line("foo".to_owned(), None))
+            .unwrap();
+        app.apply_input(InputMessage::Line("bar".to_owned(), None))
+            .unwrap();
+        drop(app);
+        writer.finish();
+
+        let written = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let records: Vec<(u64, String)> = written
+            .lines()
+            .map(|line| {
+                let record: serde_json::Value = serde_json::from_str(line).unwrap();
+                let tab = record["tab"].as_str().unwrap().to_owned();
+                (record["seq"].as_u64().unwrap(), tab)
+            })
+            .collect();
+        assert_eq!(records, [(0, "foo".to_owned()), (1, "!foo".to_owned())]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn exec_reads_stdout_and_stderr_then_reports_the_exit_code() {
+        use super::{describe_exit, exit_label, spawn_command};
+        use std::io::{BufRead, BufReader};
+
+        let (mut child, reader) = spawn_command("echo out; echo err >&2; exit 3").unwrap();
+        let lines = BufReader::new(reader)
+            .lines()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(lines, vec!["out", "err"]);
+
+        let status = child.wait().unwrap();
+        assert_eq!(describe_exit(status), "process exited with code 3");
+        assert_eq!(exit_label(status, false), " (exited 3)");
+        assert_eq!(exit_label(status, true), " ∎");
+
+        let (mut child, _reader) = spawn_command("kill -9 $$").unwrap();
+        let status = child.wait().unwrap();
+        assert_eq!(describe_exit(status), "process killed by signal 9");
+        assert_eq!(exit_label(status, false), " (killed)");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn attached_client_gets_filters_history_then_live_lines() {
+        use super::{History, session};
+        use std::io::BufRead;
+        use std::time::Duration;
+
+        let path = std::env::temp_dir().join(format!("streamtabs-{}.sock", std::process::id()));
+        let mut server = session::Server::bind(&path, vec!["error".into()]).unwrap();
+        let mut history = History::new(10);
+        history.push_line(0, "old");
+
+        let client_path = path.clone();
+        let client = std::thread::spawn(move || session::attach(&client_path).unwrap());
+        while server.client_count() == 0 {
+            server.admit(&history, &[]);
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        server.broadcast("live");
+        let (filters, reader) = client.join().unwrap();
+        assert_eq!(filters, vec!["error"]);
+        let lines: Vec<String> = reader.lines().take(2).map(Result::unwrap).collect();
+        assert_eq!(lines, vec!["old", "live"]);
+
+        assert!(session::Server::bind(&path, Vec::new()).is_err());
+        drop(server);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn zero_key_is_configurable_without_all_tab() {
+        use super::{DigitTarget, ZeroKey, digit_target};
+
+        let with_all = vec![
+            Tab::unfiltered(),
+            Tab::new("a".into()),
+            Tab::new("b".into()),
+        ];
+        assert_eq!(
+            digit_target(0, &with_all, ZeroKey::Unmapped),
+            DigitTarget::Tab(0)
+        );
+        assert_eq!(
+            digit_target(2, &with_all, ZeroKey::Unmapped),
+            DigitTarget::Tab(2)
+        );
+        assert_eq!(
+            digit_target(3, &with_all, ZeroKey::Unmapped),
+            DigitTarget::Nothing
+        );
+
+        let without_all = vec![Tab::new("a".into()), Tab::new("b".into())];
+        assert_eq!(
+            digit_target(1, &without_all, ZeroKey::Unmapped),
+            DigitTarget::Tab(0)
+        );
+        assert_eq!(
+            digit_target(2, &without_all, ZeroKey::Unmapped),
+            DigitTarget::Tab(1)
+        );
+        assert_eq!(
+            digit_target(0, &without_all, ZeroKey::AllFilters),
+            DigitTarget::AllTabs
+        );
+        assert_eq!(
+            digit_target(0, &without_all, ZeroKey::LastTab),
+            DigitTarget::Tab(1)
+        );
+        assert_eq!(
+            digit_target(0, &without_all, ZeroKey::Unmapped),
+            DigitTarget::Nothing
+        );
+    }
+
+    #[test]
+    fn filters_are_applied_independently() {
+        let mut tabs = vec![Tab::new("foo".into()), Tab::new("bar".into())];
+
+        apply_line_to_tabs(&mut tabs, &[0], false, 0, "foo only");
+        apply_line_to_tabs(&mut tabs, &[0], false, 1, "bar only");
+        apply_line_to_tabs(&mut tabs, &[0], false, 2, "foo and bar");
+
+        assert_eq!(tabs[0].total_matches, 2);
+        assert_eq!(tabs[1].total_matches, 2);
+        assert_eq!(
+            tabs[0].lines.back().map(|line| line.text.as_str()),
+            Some("foo and bar")
+        );
+        assert_eq!(
+            tabs[1].lines.back().map(|line| line.text.as_str()),
+            Some("foo and bar")
+        );
+        assert_eq!(tabs[1].unread_matches(), 2);
+        assert_eq!(tabs[0].unread_matches(), 0);
+    }
+
+    #[test]
+    fn blank_lines_are_skipped_without_counting() {
+        let mut tabs = vec![Tab::unfiltered(), Tab::new("foo".into())];
+
+        assert!(!super::ingest_line(
+            &mut tabs,
+            &[0],
+            false,
+            &super::LineRecord::new(0, "", None),
+            true,
+            false
+        ));
+        assert!(!super::ingest_line(
+            &mut tabs,
+            &[0],
+            false,
+            &super::LineRecord::new(0, " \t ", None),
+            true,
+            false
+        ));
+        assert!(!super::ingest_line(
+            &mut tabs,
+            &[0],
+            false,
+            &super::LineRecord::new(0, "\u{1b}[0m  ", None),
+            true,
+            false
+        ));
+        assert!(tabs.iter().all(|tab| tab.total_matches == 0));
+
+        assert!(super::ingest_line(
+            &mut tabs,
+            &[0],
+            false,
+            &super::LineRecord::new(0, "", None),
+            false,
+            false
+        ));
+        assert_eq!(tabs[0].total_matches, 1);
+    }
+
+    #[test]
+    fn whitespace_filter_still_receives_blank_lines() {
+        let mut tabs = vec![Tab::unfiltered(), Tab::new("  ".into())];
+
+        assert!(super::ingest_line(
+            &mut tabs,
+            &[0],
+            false,
+            &super::LineRecord::new(0, "    ", None),
+            true,
+            false
+        ));
+        assert_eq!(tabs[0].total_matches, 0);
+        assert_eq!(tabs[1].total_matches, 1);
+    }
+
+    #[test]
+    fn detect_level_finds_the_level_word_near_the_start() {
+        use super::{Level, detect_level};
+
+        assert_eq!(detect_level("ERROR disk full"), Some(Level::Error));
+        assert_eq!(detect_level("[warn] slow request"), Some(Level::Warn));
+        assert_eq!(detect_level("ts=12 level=info msg=ok"), Some(Level::Info));
+        assert_eq!(
+            detect_level("2024-05-01T12:00:00.123Z DEBUG cache miss"),
+            Some(Level::Debug)
+        );
+        assert_eq!(
+            detect_level("\u{1b}[31mFATAL\u{1b}[0m: out of memory"),
+            Some(Level::Error)
+        );
+        // The first level word wins, and words deep in the message don't count.
+        assert_eq!(detect_level("INFO retrying after error"), Some(Level::Info));
+        assert_eq!(
+            detect_level("request from the user failed with an error"),
+            None
+        );
+        assert_eq!(detect_level("errors: 0"), None);
+
+        let tab = Tab::for_level(Level::Warn);
+        assert_eq!(tab.label, "WARN");
+        assert!(tab.matches("W 12:00 warning: low disk"));
+        assert!(!tab.matches("ERROR: low disk"));
+    }
+
+    #[test]
+    fn all_tab_matches_every_line() {
+        let all = Tab::unfiltered();
+        assert!(all.matches("anything"));
+        assert!(all.matches(""));
+    }
+
+    #[test]
+    fn unread_count_clears_when_tab_is_seen() {
+        let mut tabs = vec![Tab::new("foo".into()), Tab::new("bar".into())];
+
+        apply_line_to_tabs(&mut tabs, &[0], false, 0, "foo and bar");
+        apply_line_to_tabs(&mut tabs, &[0], false, 1, "bar only");
+        assert_eq!(tabs[1].unread_matches(), 2);
+
+        mark_tab_seen_live(&mut tabs, 1);
+        assert_eq!(tabs[1].unread_matches(), 0);
+    }
+
+    #[test]
+    fn paused_switch_keeps_post_pause_unread() {
+        let mut tabs = vec![Tab::new("foo".into()), Tab::new("bar".into())];
+
+        apply_line_to_tabs(&mut tabs, &[0], false, 0, "bar before pause");
+        let pause_match_cutoffs = tabs.iter().map(|tab| tab.total_matches).collect::<Vec<_>>();
+
+        apply_line_to_tabs(&mut tabs, &[0], true, 1, "bar after pause");
+        assert_eq!(tabs[1].unread_matches(), 2);
+
+        mark_tab_seen_paused(&mut tabs, 1, &pause_match_cutoffs);
+        assert_eq!(tabs[1].unread_matches(), 1);
+    }
+
+    #[test]
+    fn gutter_composes_enabled_columns_and_reports_their_width() {
+        let line = RenderedLine {
+            seq: 42,
+            text: "hello".into(),
+            selected: false,
+            injected: false,
+            separator: None,
+        };
+        let gutter = super::GutterOptions {
+            seq_width: Some(4),
+            relative_to: None,
+            clock: None,
+            separator: "│ ",
+        };
+        assert_eq!(
+            super::render_gutter(&line, None, gutter),
+            ("  42│ ".to_owned(), 6)
+        );
+        let spaced = super::GutterOptions {
+            separator: "   ",
+            ..gutter
+        };
+        assert_eq!(super::render_gutter(&line, None, spaced).1, 7);
+
+        // Without any gutter the separator isn't drawn either.
+        let none = super::GutterOptions {
+            seq_width: None,
+            ..gutter
+        };
+        assert_eq!(super::render_gutter(&line, None, none), (String::new(), 0));
+
+        let display = super::DisplayOptions {
+            seq_gutter: true,
+            ..Default::default()
+        };
+        let lines = [7, 1234, 99].map(|seq| RenderedLine {
+            seq,
+            ..line.clone()
+        });
+        let sized = super::GutterOptions::for_lines(display, "│", &lines, None);
+        assert_eq!(sized.seq_width, Some(4));
+        assert_eq!(
+            super::render_gutter(&lines[0], None, sized),
+            ("   7│".to_owned(), 5)
+        );
+    }
+
+    #[test]
+    fn clock_gutter_shows_the_time_of_day_each_line_arrived() {
+        let line = RenderedLine {
+            seq: 7,
+            text: "hello".into(),
+            selected: false,
+            injected: false,
+            separator: None,
+        };
+        let now = super::WallClock::now();
+        let arrived = now.instant - std::time::Duration::from_secs(90);
+        assert_eq!(
+            now.at(arrived),
+            now.system - std::time::Duration::from_secs(90)
+        );
+
+        let gutter = super::GutterOptions {
+            seq_width: Some(2),
+            clock: Some(now),
+            separator: "│",
+            ..Default::default()
+        };
+        let (text, width) = super::render_gutter(&line, Some(arrived), gutter);
+        let label = super::clock_label(now.at(arrived));
+        assert_eq!(text, format!("{label}  7│"));
+        assert_eq!(width, super::CLOCK_WIDTH + 4);
+        assert!(label.chars().enumerate().all(|(index, ch)| match index {
+            2 | 5 => ch == ':',
+            _ => ch.is_ascii_digit(),
+        }));
+        // A line without a known arrival keeps the column, blank.
+        assert_eq!(super::render_gutter(&line, None, gutter).0, "          7│");
+    }
+
+    #[test]
+    fn relative_seq_gutter_counts_from_the_highlighted_line() {
+        assert_eq!(super::seq_label(12, None), "12");
+        assert_eq!(super::seq_label(12, Some(12)), "0");
+        assert_eq!(super::seq_label(17, Some(12)), "+5");
+        assert_eq!(super::seq_label(9, Some(12)), "-3");
+
+        let display = super::DisplayOptions {
+            seq_gutter: true,
+            ..Default::default()
+        };
+        let lines = [90, 100, 105].map(|seq| RenderedLine {
+            seq,
+            text: "".into(),
+            selected: false,
+            injected: false,
+            separator: None,
+        });
+        // Sized for the relative labels, not the absolute seqs.
+        let gutter = super::GutterOptions::for_lines(display, " ", &lines, Some(100));
+        assert_eq!(gutter.seq_width, Some(3));
+        assert_eq!(super::render_gutter(&lines[0], None, gutter).0, "-10 ");
+        assert_eq!(super::render_gutter(&lines[1], None, gutter).0, "  0 ");
+        assert_eq!(super::render_gutter(&lines[2], None, gutter).0, " +5 ");
+    }
+
+    #[test]
+    fn the_seq_gutter_numbers_lines_and_narrows_the_body() {
+        use super::{App, InputMessage, UiMessage};
+
+        let mut app = App::from_args(["even".to_owned()]).unwrap();
+        for n in 0..12 {
+            let parity = if n % 2 == 0 { "even" } else { "odd" };
+            let line = format!("{parity} {}", "x".repeat(40));
+            app.apply_input(InputMessage::Line(line, None)).unwrap();
+        }
+        let plain = app.frame_text(30, 6);
+        assert!(plain.contains(&format!("\x1b[6;1Hodd {}", "x".repeat(26))));
+
+        app.handle_ui(UiMessage::ToggleSeqGutter).unwrap();
+        let numbered = app.frame_text(30, 6);
+        // Sized for the widest seq in view, and the text loses those columns.
+        let body = format!("\x1b[5;5Hodd {}\x1b", "x".repeat(22));
+        assert!(
+            numbered.contains("\x1b[5;1H\x1b[38;5;8m11│ "),
+            "{numbered:?}"
+        );
+        assert!(numbered.contains(&body), "{numbered:?}");
+
+        // Seqs count every line, so a filtered tab shows the same numbers.
+        app.handle_ui(UiMessage::SelectTab(1)).unwrap();
+        let filtered = app.frame_text(30, 6);
+        assert!(
+            filtered.contains("\x1b[4;1H\x1b[38;5;8m 8│ "),
+            "{filtered:?}"
+        );
+        assert!(
+            filtered.contains("\x1b[5;1H\x1b[38;5;8m10│ "),
+            "{filtered:?}"
+        );
+    }
+
+    #[test]
+    fn marking_read_through_a_line_counts_matches_up_to_its_seq() {
+        let mut tabs = vec![Tab::new("foo".into()), Tab::new("bar".into())];
+
+        apply_line_to_tabs(&mut tabs, &[], false, 0, "bar one");
+        apply_line_to_tabs(&mut tabs, &[], false, 1, "foo only");
+        apply_line_to_tabs(&mut tabs, &[], false, 2, "bar two");
+        apply_line_to_tabs(&mut tabs, &[], false, 3, "bar three");
+        assert_eq!(tabs[1].match_index_through(0), 1);
+        assert_eq!(tabs[1].match_index_through(2), 2);
+        // seq 1 isn't in the bar tab; only the older line counts.
+        assert_eq!(tabs[1].match_index_through(1), 1);
+
+        mark_tab_seen_through(&mut tabs, 1, 2);
+        assert_eq!(tabs[1].unread_matches(), 1);
+        // Marking an older line never makes seen matches unread again.
+        mark_tab_seen_through(&mut tabs, 1, 0);
+        assert_eq!(tabs[1].unread_matches(), 1);
+
+        // Evicted lines still count toward the match index.
+        let first_stored = tabs[1].lines.pop_front().map(|line| line.seq);
+        assert_eq!(first_stored, Some(0));
+        assert_eq!(tabs[1].match_index_through(3), 3);
+    }
+
+    #[test]
+    fn active_tab_accumulates_unread_while_paused() {
+        let mut tabs = vec![Tab::new("foo".into()), Tab::new("bar".into())];
+
+        apply_line_to_tabs(&mut tabs, &[0], false, 0, "foo visible");
+        assert_eq!(tabs[0].unread_matches(), 0);
+
+        apply_line_to_tabs(&mut tabs, &[0], true, 1, "foo hidden while paused");
+        assert_eq!(tabs[0].unread_matches(), 1);
+    }
+
+    #[test]
+    fn i_prefix_matches_any_case_and_keeps_the_typed_label() {
+        use super::{MatchMode, contains_ignore_case};
+
+        let tab = Tab::new("i:Timeout~retry".into());
+        assert_eq!(tab.label, "i:Timeout~retry");
+        assert_eq!(tab.pattern, "Timeout");
+        assert!(
+            matches!(&tab.mode, MatchMode::ContainsCaseInsensitive(needle) if needle == "timeout")
+        );
+        assert!(tab.matches("request TIMEOUT"));
+        assert!(tab.matches("timeout"));
+        assert!(!tab.matches("Timeout, retry 2"));
+
+        assert!(contains_ignore_case("ÉCHEC: Disk", "échec"));
+        assert!(contains_ignore_case("anything", ""));
+        assert!(!contains_ignore_case("time", "timeout"));
+    }
+
+    #[test]
+    fn color_prefix_colors_the_tab_and_is_checked_at_startup() {
+        use crossterm::style::Color;
+
+        let tab = Tab::new("color=red:re:err(or)?".into());
+        assert_eq!(tab.color, Some(Color::Red));
+        assert_eq!(tab.label, "re:err(or)?");
+        assert!(tab.matches("an error"));
+        assert_eq!(tab.inverted().unwrap().color, Some(Color::Red));
+        assert_eq!(Tab::new("error".into()).color, None);
+        // Without the `:` the text is just a filter.
+        assert_eq!(Tab::new("color=red".into()).label, "color=red");
+
+        assert!(super::check_filter("color=dark_cyan:!x~y").is_ok());
+        let err = super::check_filter("color=teal:x").unwrap_err();
+        assert!(err.contains("\"teal\""), "{err}");
+        assert!(super::check_filter("color=red:re:(").is_err());
+    }
+
+    #[test]
+    fn bang_prefix_inverts_the_filter_after_it() {
+        let tab = Tab::new("!healthcheck".into());
+        assert_eq!(tab.label, "!healthcheck");
+        assert_eq!(tab.pattern, "healthcheck");
+        assert!(tab.matches("GET /users"));
+        assert!(!tab.matches("GET /healthcheck"));
+
+        let tab = Tab::new("!i:Debug".into());
+        assert!(!tab.matches("DEBUG: noise"));
+        assert!(tab.matches("error: disk"));
+        let tab = Tab::new("!re:^GET ".into());
+        assert!(tab.matches("POST /api"));
+        assert!(!tab.matches("GET /api"));
+        assert!(super::check_filter("!re:(").is_err());
+
+        // Inverting an inverted filter gives the original back.
+        let original = Tab::new("!healthcheck".into()).inverted().unwrap();
+        assert_eq!(original.label, "healthcheck");
+        assert!(Tab::new("!".into()).matches("wow!"));
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or_in_filter_expressions() {
+        use super::MatchMode;
+
+        let tab = Tab::new("auth && fail||refused".into());
+        assert_eq!(tab.label, "auth && fail||refused");
+        assert!(tab.matches("auth check failed"));
+        assert!(tab.matches("connection refused"));
+        assert!(!tab.matches("auth ok"));
+        assert!(!tab.matches("fail"));
+
+        let both = Tab::new("auth&&fail".into());
+        assert!(matches!(&both.mode, MatchMode::AllOf(terms) if terms == &["auth", "fail"]));
+        assert!(both.matches("fail: auth"));
+        let either = Tab::new("timeout||refused".into());
+        assert!(either.matches("read timeout"));
+        assert!(either.matches("refused"));
+        assert!(!either.matches("ok"));
+
+        assert!(Tab::new("!timeout||refused".into()).matches("ok"));
+        assert!(Tab::new("&&".into()).matches("a && b"));
+        assert_eq!(
+            Tab::new("a&&b||c".into()).mode.describe(),
+            "all of \"a\", \"b\", or all of \"c\""
+        );
+    }
+
+    #[test]
+    fn cycling_mode_walks_all_variants_and_updates_label() {
+        let mut tab = Tab::new("Err".into());
+        assert!(!tab.matches("ERROR here"));
+
+        assert!(tab.cycle_mode().is_none());
+        assert_eq!(tab.label, "i:Err");
+        assert!(tab.matches("ERROR here"));
+
+        assert!(tab.cycle_mode().is_none());
+        assert_eq!(tab.label, "re:Err");
+        assert!(tab.matches("Errno 5"));
+
+        assert!(tab.cycle_mode().is_none());
+        assert_eq!(tab.label, "w:Err");
+        assert!(!tab.matches("Errno 5"));
+        assert!(tab.matches("got Err: 5"));
+
+        assert!(tab.cycle_mode().is_none());
+        assert_eq!(tab.label, "Err");
+        assert!(tab.matches("Errno 5"));
+    }
+
+    #[test]
+    fn cycling_mode_skips_invalid_regex() {
+        let mut tab = Tab::new("(oops".into());
+        tab.cycle_mode();
+        let warning = tab.cycle_mode().expect("invalid regex should warn");
+        assert!(warning.starts_with("skipped regex mode"));
+        assert_eq!(tab.label, "w:(oops");
+        assert!(tab.matches("an (oops) happened"));
+    }
+
+    #[test]
+    fn rebuild_rescans_source_lines() {
+        let mut tabs = vec![Tab::unfiltered(), Tab::new("warn".into())];
+        apply_line_to_tabs(&mut tabs, &[0], false, 0, "WARN upper");
+        apply_line_to_tabs(&mut tabs, &[0], false, 1, "warn lower");
+        apply_line_to_tabs(&mut tabs, &[0], false, 2, "info");
+        assert_eq!(tabs[1].lines.len(), 1);
+
+        let (all, filters) = tabs.split_at_mut(1);
+        filters[0].cycle_mode();
+        filters[0].rebuild_from(&all[0].lines, false);
+
+        let seqs = filters[0]
+            .lines
+            .iter()
+            .map(|line| line.seq)
+            .collect::<Vec<_>>();
+        assert_eq!(seqs, vec![0, 1]);
+        assert_eq!(filters[0].total_matches, 2);
+        assert_eq!(filters[0].unread_matches(), 2);
+    }
+
+    #[test]
+    fn search_queries_of_shown_tabs_are_highlighted() {
+        use super::{HighlightStyle, search_highlight_terms};
+        use crossterm::style::Color;
+
+        let mut tabs = vec![Tab::unfiltered(), Tab::new("any:GET".into())];
+        tabs[0].search = Some(super::SearchState::new("users".into()));
+        tabs[1].search = Some(super::SearchState::new(String::new()));
+        assert!(search_highlight_terms(&tabs, &[1]).is_empty());
+
+        let search = search_highlight_terms(&tabs, &[0, 1]);
+        let mut terms = super::active_highlight_terms(&tabs, &[0, 1]);
+        terms.extend(&search);
+        let spans = super::highlight_spans("GET /users", &terms);
+        assert_eq!(
+            spans,
+            vec![
+                (0..3, HighlightStyle::Term(Color::Red)),
+                (5..10, HighlightStyle::Search)
+            ]
+        );
+    }
+
+    #[test]
+    fn substring_filters_are_emboldened_around_the_lines_own_colors() {
+        use super::{active_filter_needles, embolden_needles};
+
+        let tabs = vec![
+            Tab::unfiltered(),
+            Tab::new("error".into()),
+            Tab::new("i:WARN".into()),
+        ];
+        assert!(active_filter_needles(&tabs, &[0]).is_empty());
+        let needles = active_filter_needles(&tabs, &[0, 1, 2]);
+        assert_eq!(needles, vec![("error", false), ("warn", true)]);
+
+        assert_eq!(
+            embolden_needles("an error, Warn", &needles),
+            "an \x1b[1merror\x1b[22m, \x1b[1mWarn\x1b[22m"
+        );
+        // A color change inside the needle stays put, and a reset there
+        // doesn't end the bold early.
+        assert_eq!(
+            embolden_needles("\x1b[31mer\x1b[0mror", &needles[..1]),
+            "\x1b[31m\x1b[1mer\x1b[0m\x1b[1mror\x1b[22m"
+        );
+        assert_eq!(
+            embolden_needles("er\x1b[22;32mror", &needles[..1]),
+            "\x1b[1mer\x1b[22;32m\x1b[1mror\x1b[22m\x1b[22;32m"
+        );
+        // Ending the match's bold replays a bold line's own style.
+        assert_eq!(
+            embolden_needles("\x1b[1m\x1b[31man error!", &needles[..1]),
+            "\x1b[1m\x1b[31man \x1b[1merror\x1b[22m\x1b[1m\x1b[31m!"
+        );
+        assert_eq!(embolden_needles("ERROR", &needles[..1]), "ERROR");
+        assert_eq!(
+            super::strip_ansi(&embolden_needles("errorerror", &needles[..1])),
+            "errorerror"
+        );
+    }
+
+    #[test]
+    fn search_cursors_are_independent_per_tab() {
+        let mut tabs = vec![Tab::new("a".into()), Tab::new("b".into())];
+        for (seq, line) in ["a x1", "a b x2", "b x3", "a b x4"].iter().enumerate() {
+            apply_line_to_tabs(&mut tabs, &[0], false, seq as u64, line);
+        }
+        tabs[0].search = Some(super::SearchState::new("x".into()));
+        tabs[1].search = Some(super::SearchState::new("x".into()));
+
+        let step = |tab: &mut Tab, older| {
+            let len = tab.lines.len();
+            tab.advance_search(len, older).map(|line| line.seq)
+        };
+        assert_eq!(step(&mut tabs[0], true), Some(3));
+        assert_eq!(step(&mut tabs[0], true), Some(1));
+        // The other tab starts from its own bottom, not where tab 0 left off.
+        assert_eq!(step(&mut tabs[1], true), Some(3));
+        assert_eq!(step(&mut tabs[0], true), Some(0));
+        assert_eq!(step(&mut tabs[0], true), None);
+        assert_eq!(tabs[0].search.as_ref().unwrap().current_seq, Some(0));
+        assert_eq!(step(&mut tabs[1], true), Some(2));
+        assert_eq!(step(&mut tabs[1], false), Some(3));
+
+        // Paused views only search up to their cutoff.
+        tabs[1].search = Some(super::SearchState::new("x".into()));
+        assert_eq!(
+            tabs[1].advance_search(2, true).map(|line| line.seq),
+            Some(2)
+        );
+        assert!(Tab::new("c".into()).advance_search(0, true).is_none());
+    }
+
+    #[test]
+    fn console_events_map_like_the_bytes_of_the_same_keys() {
+        use crossterm::event::{
+            Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, MouseButton, MouseEvent,
+            MouseEventKind,
+        };
+
+        let mut keys = super::KeyRemap::default();
+        keys.bind(b'x', b'q');
+        let mut parser = super::InputParser::with_keys(keys);
+        let key = |code| Event::Key(KeyEvent::new(code, KeyModifiers::NONE));
+        let mut map = |events: &[Event]| {
+            events
+                .iter()
+                .filter_map(|event| parser.event_message(event))
+                .collect::<Vec<_>>()
+        };
+
+        let released = Event::Key(KeyEvent {
+            kind: KeyEventKind::Release,
+            ..KeyEvent::new(KeyCode::Char('j'), KeyModifiers::NONE)
+        });
+        assert!(matches!(
+            map(&[key(KeyCode::Char('j')), released, key(KeyCode::Char('x'))]).as_slice(),
+            [UiMessage::MoveSelection(1), UiMessage::Quit]
+        ));
+        assert!(matches!(
+            map(&[
+                key(KeyCode::Char('/')),
+                key(KeyCode::Char('q')),
+                key(KeyCode::Backspace),
+                key(KeyCode::Enter),
+                key(KeyCode::PageDown),
+            ])
+            .as_slice(),
+            [
+                UiMessage::StartSearch,
+                UiMessage::PromptInput('q'),
+                UiMessage::PromptBackspace,
+                UiMessage::PromptSubmit,
+                UiMessage::ScrollPage(1),
+            ]
+        ));
+        let mouse = |kind| {
+            Event::Mouse(MouseEvent {
+                kind,
+                column: 4,
+                row: 2,
+                modifiers: KeyModifiers::SHIFT,
+            })
+        };
+        assert!(matches!(
+            map(&[
+                mouse(MouseEventKind::Down(MouseButton::Left)),
+                mouse(MouseEventKind::Down(MouseButton::Right)),
+                mouse(MouseEventKind::ScrollDown),
+            ])
+            .as_slice(),
+            [
+                UiMessage::MouseLeftDown {
+                    column: 4,
+                    row: 2,
+                    shift: true,
+                },
+                UiMessage::MouseRightDown { row: 2 },
+                UiMessage::Scroll(super::WHEEL_SCROLL_ROWS),
+            ]
+        ));
+    }
+
+    #[test]
+    fn the_app_takes_lines_and_keys_without_a_terminal() {
+        use super::{App, TerminalAction};
+
+        let mut app = App::from_args(["error".to_owned()]).unwrap();
+        for line in ["boot", "error: disk full", "ok"] {
+            assert!(app.apply_line(line));
+        }
+        assert_eq!(app.tabs().len(), 2);
+        assert_eq!(
+            app.tabs()[1].lines().collect::<Vec<_>>(),
+            [(1, "error: disk full")]
+        );
+
+        app.handle_ui(UiMessage::NextTab).unwrap();
+        assert_eq!(app.active_tab(), 1);
+        app.handle_ui(UiMessage::StartSearch).unwrap();
+        for ch in "disk".chars() {
+            app.handle_ui(UiMessage::PromptInput(ch)).unwrap();
+        }
+        app.handle_ui(UiMessage::PromptSubmit).unwrap();
+        assert_eq!(app.selected_seq(), Some(1));
+        assert!(app.is_paused());
+
+        assert_eq!(
+            app.handle_ui(UiMessage::CopySelection).unwrap(),
+            Some(TerminalAction::Copy("error: disk full".to_owned()))
+        );
+        assert_eq!(
+            app.handle_ui(UiMessage::Quit).unwrap(),
+            Some(TerminalAction::Quit)
+        );
+        assert!(App::from_args(["re:(".to_owned()]).is_err());
+    }
+
+    #[test]
+    fn an_unconfirmed_ctrl_c_leaves_the_prompt_as_it_was() {
+        use super::{App, InputParser, TerminalAction};
+
+        let mut parser = InputParser::new();
+        let mut app = App::from_args(["--confirm-ctrl-c", "error"].map(String::from)).unwrap();
+        let mut keys = |app: &mut App, bytes: &[u8]| {
+            bytes
+                .iter()
+                .filter_map(|&byte| parser.feed(byte))
+                .map(|message| app.handle_ui(message).unwrap())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(keys(&mut app, b"/di\x03"), [None, None, None, None]);
+        let status = app.status.as_ref().map(|status| status.text.as_str());
+        assert_eq!(status, Some("Press Ctrl+C again to quit"));
+        // Still typing into the same prompt, not mapping keys.
+        keys(&mut app, b"sk");
+        assert_eq!(app.search_prompt.as_deref(), Some("disk"));
+        assert_eq!(keys(&mut app, b"\x03"), [Some(TerminalAction::Quit)]);
+
+        let mut app = App::from_args(["--confirm-quit", "error"].map(String::from)).unwrap();
+        assert_eq!(app.handle_ui(super::UiMessage::Quit).unwrap(), None);
+        let status = app.status.as_ref().map(|status| status.text.as_str());
+        assert_eq!(status, Some("Press q again to quit"));
+    }
+
+    #[test]
+    fn prompts_keep_the_tail_of_wide_text_within_the_row() {
+        use super::{App, UiMessage};
+
+        let mut app = App::from_args(["error".to_owned()]).unwrap();
+        app.handle_ui(UiMessage::StartSearch).unwrap();
+        for ch in "abcdef日本語日本日本".chars() {
+            app.handle_ui(UiMessage::PromptInput(ch)).unwrap();
+        }
+        // Fourteen chars fit 19 columns by count, but take 21.
+        let frame = app.frame_text(20, 6);
+        assert!(
+            frame.contains("Hbcdef日本語日本日本\x1b[7m \x1b[0m"),
+            "{frame:?}"
+        );
+    }
+
+    #[test]
+    fn scripted_input_renders_at_a_given_size() {
+        use super::{App, InputMessage};
+
+        let mut app = App::from_args(["even".to_owned(), "7".to_owned()]).unwrap();
+        for n in 0..100 {
+            let parity = if n % 2 == 0 { "even" } else { "odd" };
+            let line = InputMessage::Line(format!("{parity} {n}"), None);
+            app.apply_input(line).unwrap();
+        }
+        let state = app.render(60, 12);
+        assert_eq!(state.visible_seqs(), (91..100).collect::<Vec<_>>());
+        assert_eq!(state.seq_at_row(3), Some(91));
+        assert_eq!(state.seq_at_row(11), Some(99));
+        assert_eq!(state.tab_at(1, 1), Some(0));
+        assert_eq!(app.tabs()[2].unread_matches(), 19);
+
+        app.handle_ui(UiMessage::SelectTab(2)).unwrap();
+        let state = app.render(60, 12);
+        assert_eq!(state.visible_seqs(), [73, 74, 75, 76, 77, 78, 79, 87, 97]);
+        assert_eq!(app.tabs()[2].unread_matches(), 0);
+        assert_eq!(app.tabs()[1].unread_matches(), 50);
+    }
+
+    #[test]
+    fn split_panes_show_their_own_tabs_and_scroll_apart() {
+        use super::{App, InputMessage, RenderState};
+
+        let mut app = App::from_args(["even".to_owned(), "odd".to_owned()]).unwrap();
+        for n in 0..40 {
+            let parity = if n % 2 == 0 { "even" } else { "odd" };
+            let line = InputMessage::Line(format!("{parity} {n}"), None);
+            app.apply_input(line).unwrap();
+        }
+        let panes = |state: &RenderState| {
+            let divider = state.split_first_row.expect("split is drawn");
+            let seqs = |rows: std::ops::Range<u16>| {
+                let mut seqs = rows
+                    .filter_map(|row| state.seq_at_row(row))
+                    .collect::<Vec<_>>();
+                seqs.dedup();
+                seqs
+            };
+            (seqs(0..divider), seqs(divider..20))
+        };
+
+        app.handle_ui(UiMessage::SelectTab(1)).unwrap();
+        app.handle_ui(UiMessage::ToggleSplit).unwrap();
+        let (top, bottom) = panes(&app.render(40, 20));
+        assert_eq!(top.last(), Some(&38));
+        assert!(top.iter().all(|seq| seq % 2 == 0), "{top:?}");
+        assert_eq!(bottom.last(), Some(&39));
+        assert!(bottom.iter().all(|seq| seq % 2 == 1), "{bottom:?}");
+
+        // Scrolling goes to the focused pane only.
+        app.handle_ui(UiMessage::Scroll(-2)).unwrap();
+        let (scrolled_top, same_bottom) = panes(&app.render(40, 20));
+        assert_eq!(scrolled_top.last(), Some(&34));
+        assert_eq!(same_bottom, bottom);
+
+        app.handle_ui(UiMessage::SwitchSplitFocus).unwrap();
+        app.handle_ui(UiMessage::Scroll(-3)).unwrap();
+        let (same_top, scrolled_bottom) = panes(&app.render(40, 20));
+        assert_eq!(same_top, scrolled_top);
+        assert_eq!(scrolled_bottom.last(), Some(&33));
+
+        // So do tab picks: the lower pane moves on to `(all)`.
+        app.handle_ui(UiMessage::NextTab).unwrap();
+        let (same_top, all) = panes(&app.render(40, 20));
+        assert_eq!(same_top, scrolled_top);
+        assert_eq!(all.last(), Some(&39));
+        assert_eq!(all.len(), bottom.len());
+        assert!(all.windows(2).all(|pair| pair[1] == pair[0] + 1), "{all:?}");
+    }
+
+    #[test]
+    fn narrow_tab_strip_frame_matches_its_golden_bytes() {
+        use super::{App, InputMessage};
+
+        let mut app =
+            App::from_args(["error".to_owned(), "disconnected from upstream".to_owned()]).unwrap();
+        for line in ["ok", "error: disk full", "disconnected from upstream"] {
+            app.apply_input(InputMessage::Line(line.to_owned(), None))
+                .unwrap();
+        }
+        // Split at each cursor move so a regression diff points at one cell
+        // run. A frame is written a row at a time, top to bottom.
+        let golden = [
+            "\x1b[1;1H\x1b[2J",
+            "\x1b[1;1H\x1b[38;5;15m╭─────────────────╮\x1b[0m",
+            "\x1b[1;21H\x1b[38;5;8m╭───────────────╮\x1b[0m",
+            "\x1b[2;1H\x1b[38;5;15m│\x1b[0m",
+            "\x1b[2;2H\x1b[38;5;8m 0 \x1b[0m",
+            "\x1b[2;5H\x1b[38;5;8m (all) \x1b[0m",
+            "\x1b[2;12H\x1b[38;5;6m      \x1b[0m",
+            "\x1b[2;18H ",
+            "\x1b[2;19H\x1b[38;5;15m│\x1b[0m",
+            "\x1b[2;21H\x1b[38;5;8m│\x1b[0m",
+            "\x1b[2;22H\x1b[38;5;8m 1 \x1b[0m",
+            "\x1b[2;25H ... ",
+            "\x1b[2;30H\x1b[38;5;6m    •1\x1b[0m",
+            "\x1b[2;36H ",
+            "\x1b[2;37H\x1b[38;5;8m│\x1b[0m",
+            "\x1b[2;38H\x1b[38;5;8m›1\x1b[0m",
+            "\x1b[3;1H\x1b[38;5;15m╰─────────────────╯\x1b[0m",
+            "\x1b[3;21H\x1b[38;5;8m╰───────────────╯\x1b[0m",
+            "\x1b[4;1Herror: disk full",
+            "\x1b[5;1Hdisconnected from upstream",
+        ];
+        assert_eq!(app.frame_text(40, 5), golden.concat());
+    }
+
+    #[test]
+    fn searching_after_scrolling_up_stays_paused_on_the_match() {
+        use super::{App, InputMessage};
+
+        let mut app = App::from_args(Vec::new()).unwrap();
+        for n in 0..100 {
+            let text = if n == 40 {
+                "needle".to_owned()
+            } else {
+                format!("line {n}")
+            };
+            app.apply_input(InputMessage::Line(text, None)).unwrap();
+        }
+        app.render(60, 12);
+        app.handle_ui(UiMessage::Scroll(-3)).unwrap();
+        app.render(60, 12);
+        assert!(app.is_paused());
+
+        app.handle_ui(UiMessage::StartSearch).unwrap();
+        for ch in "needle".chars() {
+            app.handle_ui(UiMessage::PromptInput(ch)).unwrap();
+        }
+        app.handle_ui(UiMessage::PromptSubmit).unwrap();
+        let state = app.render(60, 12);
+        assert_eq!(app.selected_seq(), Some(40));
+        assert!(state.visible_seqs().contains(&40));
+        // A second frame must not take the jump for a return to the bottom.
+        let state = app.render(60, 12);
+        assert!(app.is_paused());
+        assert!(state.visible_seqs().contains(&40));
+    }
+
+    #[test]
+    fn parser_types_into_open_prompt() {
+        let mut parser = super::InputParser::new();
+        let feed = |parser: &mut super::InputParser, bytes: &[u8]| {
+            let mut messages = bytes
+                .iter()
+                .filter_map(|byte| parser.feed(*byte))
+                .collect::<Vec<_>>();
+            messages.extend(parser.finish_read());
+            messages
+        };
+
+        let messages = feed(&mut parser, "/né\x7f\r".as_bytes());
+        assert!(matches!(
+            messages.as_slice(),
+            [
+                UiMessage::StartSearch,
+                UiMessage::PromptInput('n'),
+                UiMessage::PromptInput('é'),
+                UiMessage::PromptBackspace,
+                UiMessage::PromptSubmit,
+            ]
+        ));
+        assert!(matches!(
+            feed(&mut parser, b"n").as_slice(),
+            [UiMessage::SearchNext]
+        ));
+
+        assert!(matches!(
+            feed(&mut parser, b"/q\x1b").as_slice(),
+            [
+                UiMessage::StartSearch,
+                UiMessage::PromptInput('q'),
+                UiMessage::PromptCancel
+            ]
+        ));
+        assert!(matches!(
+            feed(&mut parser, b"\x1b").as_slice(),
+            [UiMessage::Escape]
+        ));
+        assert!(matches!(
+            feed(&mut parser, b"q").as_slice(),
+            [UiMessage::Quit]
+        ));
+    }
+
+    #[test]
+    fn and_tokens_match_every_word_in_any_order() {
+        let tab = Tab::new("error timeout".into()).with_and_tokens();
+        assert_eq!(tab.label, "error timeout");
+        assert!(tab.matches("error: request timeout"));
+        assert!(tab.matches("timeout, then error"));
+        assert!(!tab.matches("error only"));
+
+        // Without the flag the space is part of a literal filter.
+        let literal = Tab::new("error timeout".into());
+        assert!(literal.matches("an error timeout here"));
+        assert!(!literal.matches("timeout, then error"));
+
+        // A single word, even padded, and whitespace filters stay literal.
+        assert!(
+            !Tab::new(" error ".into())
+                .with_and_tokens()
+                .matches("error:")
+        );
+        assert!(Tab::new("  ".into()).with_and_tokens().targets_whitespace());
+        assert!(Tab::new("any:a|b".into()).with_and_tokens().matches("b"));
+    }
+
+    #[test]
+    fn re_prefix_compiles_the_filter_once_into_a_regex_tab() {
+        use super::{MatchMode, check_filter};
+
+        let tab = Tab::new("re:GET\\s+/api~health".into());
+        assert_eq!(tab.label, "re:GET\\s+/api~health");
+        assert_eq!(tab.pattern, "GET\\s+/api");
+        assert!(matches!(tab.mode, MatchMode::Regex(_)));
+        assert!(tab.matches("GET   /api/users"));
+        assert!(!tab.matches("GET /api/health"));
+        assert!(!tab.matches("POST /api/users"));
+        assert!(Tab::new("re:error|warn".into()).matches("warn: disk"));
+
+        assert!(check_filter("re:GET\\s+/api").is_ok());
+        assert!(check_filter("plain (text").is_ok());
+        let err = check_filter("re:(oops").unwrap_err();
+        assert!(
+            err.starts_with("invalid regex in filter \"re:(oops\": "),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn any_of_tab_matches_each_term_and_colors_spans() {
+        use super::HighlightStyle::Term;
+        use crossterm::style::Color;
+
+        let tab = Tab::new("any:error|warn||timeout".into());
+        assert_eq!(tab.label, "any:error|warn||timeout");
+        assert!(tab.matches("warn: disk"));
+        assert!(tab.matches("request timeout"));
+        assert!(!tab.matches("info"));
+        // Without any terms, the prefix is just part of a plain filter.
+        assert!(Tab::new("any:".into()).matches("any: thing"));
+
+        let tabs = vec![Tab::unfiltered(), tab];
+        assert!(super::active_highlight_terms(&tabs, &[0]).is_empty());
+        let terms = super::active_highlight_terms(&tabs, &[0, 1]);
+        let styles = terms.iter().map(|term| term.style).collect::<Vec<_>>();
+        assert_eq!(
+            styles,
+            [Color::Red, Color::Yellow, Color::Magenta].map(Term)
+        );
+
+        let spans = super::highlight_spans("warn then timeout error", &terms);
+        assert_eq!(
+            spans,
+            vec![
+                (0..4, Term(Color::Yellow)),
+                (10..17, Term(Color::Magenta)),
+                (18..23, Term(Color::Red))
+            ]
+        );
+    }
+
+    #[test]
+    fn any_of_tabs_leave_the_colors_of_lines_without_a_term() {
+        use super::{App, InputMessage, UiMessage};
+
+        let mut app = App::from_args(["any:warn".to_owned()]).unwrap();
+        // Shift+click the any: tab into the view beside (all).
+        app.frame_text(30, 5);
+        app.handle_ui(UiMessage::MouseLeftDown {
+            column: 23,
+            row: 1,
+            shift: true,
+        })
+        .unwrap();
+        for line in ["\x1b[32mok\x1b[0m", "\x1b[32mwarn: disk\x1b[0m"] {
+            app.apply_input(InputMessage::Line(line.to_owned(), None))
+                .unwrap();
+        }
+        let frame = app.frame_text(30, 5);
+        assert!(frame.contains("\x1b[32mok"), "{frame:?}");
+        assert!(!frame.contains("\x1b[32mwarn"), "{frame:?}");
+    }
+
+    #[test]
+    fn regex_capture_groups_color_by_index_within_the_shown_width() {
+        use super::HighlightStyle::{Search, Term};
+        use super::{active_group_regexes, group_spans, merge_spans};
+        use crossterm::style::Color;
+
+        let tabs = vec![
+            Tab::new("re:error".into()),
+            Tab::new("re:(\\d+) (\\w+) (.*)".into()),
+        ];
+        assert!(active_group_regexes(&tabs, &[0]).is_empty());
+        let regexes = active_group_regexes(&tabs, &[0, 1]);
+        let line = "200 GET /users/42";
+        assert_eq!(
+            group_spans(line, &regexes, line.len()),
+            vec![
+                (0..3, Term(Color::Red)),
+                (4..7, Term(Color::Yellow)),
+                (8..line.len(), Term(Color::Magenta))
+            ]
+        );
+        // Clipped at "200 GE": the method is cut short and the path is gone.
+        assert_eq!(
+            group_spans(line, &regexes, 6),
+            vec![(0..3, Term(Color::Red)), (4..6, Term(Color::Yellow))]
+        );
+        // A nested group gives way to the one around it.
+        let nested = Tab::new("re:(a(b))c".into());
+        let regexes = active_group_regexes(std::slice::from_ref(&nested), &[0]);
+        assert_eq!(
+            group_spans("abc", &regexes, 3),
+            vec![(0..2, Term(Color::Red))]
+        );
+
+        // Search hits and terms already highlighted keep their spans.
+        let merged = merge_spans(
+            vec![(4..7, Search)],
+            vec![(0..3, Term(Color::Red)), (4..7, Term(Color::Yellow))],
+        );
+        assert_eq!(merged, vec![(0..3, Term(Color::Red)), (4..7, Search)]);
+    }
+
+    #[test]
+    fn closing_a_tab_keeps_tab_indexed_state_aligned() {
+        use super::{close_tab, matched_tab_labels, pause_view};
+
+        let mut tabs = vec![
+            Tab::unfiltered(),
+            Tab::new("a".into()),
+            Tab::new("b".into()),
+            Tab::new("c".into()),
+        ];
+        for (seq, line) in ["a", "b", "c"].into_iter().enumerate() {
+            apply_line_to_tabs(&mut tabs, &[0], false, seq as u64, line);
+        }
+        let mut active_index = 2;
+        let mut active = vec![2];
+        let mut scrolled = vec![2];
+        let mut snapshot = pause_view(&mut tabs, &active, 3);
+
+        let closed = close_tab(
+            &mut tabs,
+            &mut active_index,
+            &mut active,
+            &mut scrolled,
+            2,
+            true,
+            Some(&mut snapshot),
+        )
+        .unwrap();
+        assert_eq!(closed.label, "b");
+        let labels: Vec<_> = tabs.iter().map(|tab| tab.label.as_str()).collect();
+        assert_eq!(labels, vec!["(all)", "a", "c"]);
+        assert_eq!((active_index, active.clone()), (2, vec![2]));
+        // "c" now sits where "b" was, but doesn't take over its scrolling.
+        assert!(scrolled.is_empty());
+        assert_eq!(snapshot.line_cutoffs, vec![3, 1, 1]);
+        assert_eq!(matched_tab_labels(&tabs, 2), Some(vec!["2 c".to_owned()]));
+        assert_eq!(matched_tab_labels(&tabs, 1), Some(Vec::new()));
+
+        // Closing one tab of several shown leaves the others.
+        let mut active = vec![1, 2];
+        let mut active_index = 1;
+        let mut scrolled = vec![0];
+        let mut close = |tabs: &mut Vec<Tab>, index| {
+            close_tab(
+                tabs,
+                &mut active_index,
+                &mut active,
+                &mut scrolled,
+                index,
+                false,
+                None,
+            )
+        };
+        close(&mut tabs, 1).unwrap();
+        assert!(close(&mut tabs, 0).is_err());
+        assert_eq!((active_index, active.clone()), (1, vec![1]));
+        // Another view's scroll position stays with it.
+        assert_eq!(scrolled, vec![0]);
+
+        // Closing a tab left of the scrolled one shifts it down.
+        tabs.push(Tab::new("d".into()));
+        let mut active = vec![2];
+        let mut scrolled = vec![2];
+        close_tab(
+            &mut tabs,
+            &mut 2,
+            &mut active,
+            &mut scrolled,
+            1,
+            false,
+            None,
+        )
+        .unwrap();
+        assert_eq!((active, scrolled), (vec![1], vec![1]));
+
+        let mut lone = vec![Tab::new("a".into())];
+        let (mut index, mut shown, mut scrolled) = (0, vec![0], vec![0]);
+        assert!(
+            close_tab(
+                &mut lone,
+                &mut index,
+                &mut shown,
+                &mut scrolled,
+                0,
+                false,
+                None
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn all_tab_records_which_tabs_each_line_matched() {
+        use super::{History, TabSet, add_tab, matched_tab_labels};
+
+        let mut tabs = vec![
+            Tab::unfiltered(),
+            Tab::new("foo".into()),
+            Tab::new("bar".into()),
+        ];
+        let mut history = History::new(10);
+        for (seq, line) in ["foo bar", "foo", "baz"].into_iter().enumerate() {
+            apply_line_to_tabs(&mut tabs, &[0], false, seq as u64, line);
+            history.push_line(seq as u64, line);
+        }
+        assert_eq!(
+            matched_tab_labels(&tabs, 0),
+            Some(vec!["1 foo".to_owned(), "2 bar".to_owned()])
+        );
+        assert_eq!(matched_tab_labels(&tabs, 1), Some(vec!["1 foo".to_owned()]));
+        assert_eq!(matched_tab_labels(&tabs, 2), Some(Vec::new()));
+        assert_eq!(matched_tab_labels(&tabs, 9), None);
+        assert!(
+            tabs[1]
+                .lines
+                .iter()
+                .all(|line| line.matched_tabs == TabSet::default())
+        );
+
+        add_tab(&mut tabs, &history, Tab::new("ba".into()), None);
+        assert_eq!(matched_tab_labels(&tabs, 2), Some(vec!["3 ba".to_owned()]));
+
+        let mut wide = TabSet::default();
+        wide.set(130, true);
+        wide.set(3, true);
+        assert_eq!(wide.iter().collect::<Vec<_>>(), vec![3, 130]);
+        wide.set(130, false);
+        assert_eq!(wide.iter().collect::<Vec<_>>(), vec![3]);
+    }
+
+    #[test]
+    fn gap_separators_mark_slow_arrivals() {
+        use std::time::{Duration, Instant};
+
+        let start = Instant::now();
+        let mut tab = Tab::unfiltered();
+        for (seq, offset_ms) in [(0, 0), (1, 100), (2, 9_000), (3, 9_200)] {
+            tab.push_record(super::LineRecord {
+                seq,
+                text: format!("line {}", seq),
+                arrived: start + Duration::from_millis(offset_ms),
+                source: None,
+                matched_tabs: Default::default(),
+            });
+        }
+        let tabs = vec![tab];
+        let lines = prepare_visible_lines_for_tabs(&tabs, &[0], None, None, false);
+        let rows = super::insert_gap_separators(lines, &tabs, &[0], Duration::from_secs(5));
+
+        let gaps = rows.iter().map(|row| row.separator).collect::<Vec<_>>();
+        assert_eq!(
+            gaps,
+            vec![
+                None,
+                None,
+                Some(super::Separator::Gap(Duration::from_millis(8_900))),
+                None,
+                None
+            ]
+        );
+        assert_eq!(rows[2].seq, 2);
+        assert_eq!(super::format_gap(Duration::from_millis(8_900)), "8.9s");
+        assert_eq!(super::format_gap(Duration::from_secs(125)), "2m05s");
+        assert_eq!(super::format_gap(Duration::from_secs(3_720)), "1h02m");
+    }
+
+    #[test]
+    fn durations_parse_with_units() {
+        use super::parse_duration;
+        use std::time::Duration;
+
+        assert_eq!(parse_duration("500ms"), Ok(Duration::from_millis(500)));
+        assert_eq!(parse_duration("5s"), Ok(Duration::from_secs(5)));
+        assert_eq!(parse_duration("2m"), Ok(Duration::from_secs(120)));
+        assert!(parse_duration("5").is_err());
+        assert!(parse_duration("s").is_err());
+    }
+
+    #[test]
+    fn hook_spec_names_tab_by_label_or_number() {
+        use super::resolve_hook_spec;
+
+        let tabs = vec![
+            Tab::unfiltered(),
+            Tab::new("level".into()),
+            Tab::new("level:error".into()),
+        ];
+        assert_eq!(
+            resolve_hook_spec(&tabs, "level:error:notify-send alert"),
+            Ok((2, "notify-send alert".to_owned()))
+        );
+        assert_eq!(
+            resolve_hook_spec(&tabs, "level:wc -l"),
+            Ok((1, "wc -l".to_owned()))
+        );
+        assert_eq!(resolve_hook_spec(&tabs, "0:cat"), Ok((0, "cat".to_owned())));
+        assert!(resolve_hook_spec(&tabs, "3:cat").is_err());
+        assert!(resolve_hook_spec(&tabs, "level:").is_err());
+        assert!(resolve_hook_spec(&tabs, "warn:cat").is_err());
+    }
+
+    #[test]
+    fn hook_command_reports_failures() {
+        let running = std::sync::Mutex::new(None);
+        assert_eq!(
+            super::run_hook_command("read line && test \"$line\" = hello", "hello", &running),
+            Ok(())
+        );
+        let err = super::run_hook_command("exit 3", "hello", &running).unwrap_err();
+        assert!(err.contains('3'), "{}", err);
+        assert!(running.lock().unwrap().is_none());
+    }
+
+    #[test]
+    fn stored_bytes_track_pushes_evictions_and_rebuilds() {
+        let mut tabs = vec![Tab::unfiltered(), Tab::new("b".into())];
+        let mut history = super::History::new(10);
+        for (seq, line) in ["abc", "ab"].iter().enumerate() {
+            apply_line_to_tabs(&mut tabs, &[0], false, seq as u64, line);
+            history.push_line(seq as u64, line);
+        }
+        assert_eq!(tabs[0].stored_bytes, 5);
+        assert_eq!(tabs[1].stored_bytes, 5);
+        assert_eq!(super::memory_label(&tabs, &history), " mem: 15B");
+
+        tabs[1].exclude = Some("c".into());
+        assert!(super::rebuild_tab(&mut tabs, &history, 1));
+        assert_eq!(tabs[1].stored_bytes, 2);
+
+        let mut full = Tab::unfiltered();
+        for seq in 0..super::MAX_STORED_LINES_PER_TAB as u64 + 3 {
+            full.push_line(seq, "xyz");
+        }
+        assert_eq!(full.stored_bytes, 3 * super::MAX_STORED_LINES_PER_TAB);
+
+        assert_eq!(super::format_bytes(512), "512B");
+        assert_eq!(super::format_bytes(13_002_342), "12.4MB");
+    }
+
+    #[test]
+    fn scrollback_limits_every_tab_and_rejects_zero() {
+        let parse = |args: &[&str]| super::parse_args(args.iter().map(|arg| arg.to_string()));
+        let options = parse(&["--scrollback", "2", "--auto-levels", "x"]).unwrap();
+        let mut tabs = super::startup_tabs(&options);
+        assert!(tabs.iter().all(|tab| tab.max_lines == 2));
+        for seq in 0..5 {
+            tabs[0].push_line(seq, "x");
+        }
+        assert_eq!(
+            tabs[0]
+                .lines
+                .iter()
+                .map(|line| line.seq)
+                .collect::<Vec<_>>(),
+            vec![3, 4]
+        );
+        let last = tabs.len() - 1;
+        assert_eq!(tabs[last].inverted().unwrap().max_lines, 2);
+        assert!(parse(&["--scrollback", "0", "x"]).is_err());
+    }
+
+    #[test]
+    fn saved_scroll_position_is_restored_while_its_line_is_held() {
+        let mut tab = super::Tab::new("x".into()).with_scrollback(3);
+        for seq in 0..3 {
+            tab.push_line(seq, "x");
+        }
+        tab.saved_scroll_top = Some(1);
+        assert_eq!(tab.restore_scroll_top(), Some(1));
+        // Restoring uses it up.
+        assert_eq!(tab.restore_scroll_top(), None);
+
+        tab.saved_scroll_top = Some(1);
+        tab.push_line(3, "x");
+        tab.push_line(4, "x");
+        assert_eq!(tab.restore_scroll_top(), None);
+    }
+
+    #[test]
+    fn history_keeps_the_most_recent_lines_up_to_its_capacity() {
+        let mut history = super::History::new(2);
+        for seq in 0..4 {
+            history.push_line(seq, "line");
+        }
+        let seqs = history
+            .lines
+            .iter()
+            .map(|line| line.seq)
+            .collect::<Vec<_>>();
+        assert_eq!(seqs, vec![2, 3]);
+        assert_eq!(history.stored_bytes, 8);
+
+        let mut off = super::History::new(0);
+        off.push_line(0, "line");
+        assert!(off.lines.is_empty());
+    }
+
+    #[test]
+    fn exclude_hides_lines_matching_both_patterns() {
+        let mut tab = Tab::new("error~ignored".into());
+        assert_eq!(tab.label, "error~ignored");
+        assert!(tab.matches("error: disk full"));
+        assert!(!tab.matches("error: ignored retry"));
+        assert!(!tab.matches("ignored"));
+
+        tab.cycle_mode();
+        assert_eq!(tab.label, "i:error~ignored");
+        assert!(tab.matches("ERROR: disk full"));
+        assert!(!tab.matches("ERROR: ignored retry"));
+        assert!(tab.inverted().is_none());
+
+        let everything_but = Tab::new("~healthcheck".into());
+        assert!(everything_but.matches("GET /users"));
+        assert!(!everything_but.matches("GET /healthcheck"));
+        // A trailing `~` has nothing to exclude, so it's part of the filter.
+        assert!(Tab::new("home~".into()).matches("cd home~"));
+
+        let mut home = Tab::new(r"cd \~/src~tmp".into());
+        assert_eq!(home.exclude.as_deref(), Some("tmp"));
+        assert!(home.matches("cd ~/src/app"));
+        assert!(!home.matches("cd ~/src/tmp"));
+        assert!(!home.matches("cd /src"));
+        home.cycle_mode();
+        assert_eq!(home.label, r"i:cd \~/src~tmp");
+        assert!(home.matches("CD ~/SRC"));
+        let inverse = Tab::new(r"ba\~ck".into()).inverted().unwrap();
+        assert_eq!(inverse.label, r"!ba\~ck");
+        assert!(inverse.matches("back"));
+        assert!(!inverse.matches("ba~ck"));
+    }
+
+    #[test]
+    fn inverted_tab_matches_what_the_filter_drops() {
+        let mut tab = Tab::new("Err".into());
+        tab.cycle_mode();
+        let inverse = tab.inverted().expect("filter tabs invert");
+        assert_eq!(inverse.label, "!i:Err");
+        assert!(inverse.matches("info"));
+        assert!(!inverse.matches("ERROR here"));
+
+        let mut cycled = tab.inverted().expect("filter tabs invert");
+        cycled.cycle_mode();
+        assert_eq!(cycled.label, "!re:Err");
+
+        let original = inverse.inverted().expect("inverse tabs invert back");
+        assert_eq!(original.label, "i:Err");
+        assert!(original.matches("ERROR here"));
+        assert!(Tab::unfiltered().inverted().is_none());
+    }
+
+    #[test]
+    fn added_tab_backfills_from_history_and_keeps_post_pause_unread() {
+        // No (all) tab: the history alone is the backfill source.
+        let mut tabs = vec![Tab::new("warn".into())];
+        let mut history = super::History::new(10);
+        let mut push = |tabs: &mut Vec<Tab>, seq: u64, line: &str, paused| {
+            apply_line_to_tabs(tabs, &[0], paused, seq, line);
+            history.push_line(seq, line);
+        };
+        push(&mut tabs, 0, "warn one", false);
+        push(&mut tabs, 1, "info before", false);
+        let mut snapshot = super::PauseSnapshot {
+            seq_cutoff: 2,
+            line_cutoffs: tabs.iter().map(|tab| tab.lines.len()).collect(),
+            match_cutoffs: tabs.iter().map(|tab| tab.total_matches).collect(),
+            paused_at: std::time::Instant::now(),
+        };
+        push(&mut tabs, 2, "info after", true);
+
+        let inverse = tabs[0].inverted().expect("filter tabs invert");
+        let (index, backfilled) = super::add_tab(&mut tabs, &history, inverse, Some(&mut snapshot));
+        assert_eq!(index, 1);
+        assert!(backfilled);
+        let seqs = tabs[1]
+            .lines
+            .iter()
+            .map(|line| line.seq)
+            .collect::<Vec<_>>();
+        assert_eq!(seqs, vec![1, 2]);
+        assert_eq!(snapshot.line_cutoffs[1], 1);
+        assert_eq!(tabs[1].unread_matches(), 1);
+
+        // New lines keep flowing into the added tab.
+        apply_line_to_tabs(&mut tabs, &[0], true, 3, "debug");
+        assert_eq!(tabs[1].lines.len(), 3);
+
+        let mut no_history = vec![Tab::new("warn".into())];
+        let inverse = no_history[0].inverted().expect("filter tabs invert");
+        let (_, backfilled) =
+            super::add_tab(&mut no_history, &super::History::new(0), inverse, None);
+        assert!(!backfilled);
+    }
+
+    #[test]
+    fn preview_shows_latest_line_up_to_pause_cutoff() {
+        let mut tab = Tab::new("foo".into());
+        assert!(super::latest_line_preview(&tab, None).is_none());
+
+        tab.push_line(0, "\u{1b}[31mfoo first\u{1b}[0m");
+        tab.push_line(1, "  foo second  ");
+        assert_eq!(
+            super::latest_line_preview(&tab, None).as_deref(),
+            Some("foo second")
+        );
+        assert_eq!(
+            super::latest_line_preview(&tab, Some(1)).as_deref(),
+            Some("foo first")
+        );
+        assert!(super::latest_line_preview(&tab, Some(0)).is_none());
+    }
+
+    #[test]
+    fn clip_limits_char_count() {
+        assert_eq!(clip_to_width("abcdef", 0), "");
+        assert_eq!(clip_to_width("abcdef", 3), "abc");
+        assert_eq!(clip_to_width("abc", 10), "abc");
+    }
+
+    #[test]
+    fn ansi_clip_uses_visible_width() {
+        let text = "\u{1b}[2m2026-02-06\u{1b}[0m INFO module message";
+        let clipped = clip_ansi_to_visible_width(text, 10);
+        assert_eq!(
+            clipped.replace("\u{1b}[2m", "").replace("\u{1b}[0m", ""),
+            "2026-02-06"
+        );
+    }
+
+    #[test]
+    fn ansi_clip_counts_wide_chars_by_display_width() {
+        let text = "\u{1b}[31m好A\u{1b}[0m";
+        let clipped = clip_ansi_to_visible_width(text, 2);
+        assert_eq!(strip_ansi(&clipped), "好");
+    }
+
+    #[test]
+    fn wide_chars_are_clipped_whole_and_padded() {
+        assert_eq!(clip_to_width("日本語", 4), "日本");
+        assert_eq!(clip_to_width("日本語", 5), "日本 ");
+        assert_eq!(clip_to_width("a🎉b", 2), "a ");
+        assert_eq!(clip_to_width("cafe\u{301}!", 5), "cafe\u{301}!");
+        assert_eq!(
+            strip_ansi(&clip_ansi_to_visible_width(
+                "\u{1b}[31m日本語🎉\u{1b}[0m",
+                7
+            )),
+            "日本語 "
+        );
+        assert_eq!(super::clip_with_ellipsis("日本語のログ", 7), "日本...");
+        assert_eq!(super::fit_tab_title("日本語のログ", 9), " 日本... ");
+        assert_eq!(
+            super::display_width(&super::fit_tab_title("🎉 party", 8)),
+            8
+        );
+    }
+
+    #[test]
+    fn ansi_clip_resets_if_cut_mid_styled_content() {
+        let text = "\u{1b}[31mERROR something happened\u{1b}[0m";
+        let clipped = clip_ansi_to_visible_width(text, 5);
+        assert!(clipped.ends_with("\u{1b}[0m"));
+    }
+
+    #[test]
+    fn osc_hyperlinks_and_charset_escapes_are_not_visible_text() {
+        let text = "\u{1b}(BGET \u{1b}]8;;https://example.com/a\u{7}/a\u{1b}]8;;\u{7} \
+                    see \u{1b}]8;id=1;https://example.com/b\u{1b}\\docs\u{1b}]8;;\u{1b}\\";
+        assert_eq!(strip_ansi(text), "GET /a see docs");
+        assert_eq!(strip_ansi(&clip_ansi_to_visible_width(text, 6)), "GET /a");
+        assert_eq!(
+            super::expand_tabs("\u{1b}]8;;x\ty\u{7}a\tb", 4),
+            "\u{1b}]8;;x\ty\u{7}a   b"
+        );
+        // Cut off before its terminator, an OSC hides the rest of the line.
+        assert_eq!(strip_ansi("ok \u{1b}]0;title"), "ok ");
+    }
+
+    #[test]
+    fn sgr_state_tracks_nested_styles_until_a_reset() {
+        let state = super::SgrState::after("\u{1b}[1m\u{1b}[31mbold red");
+        assert_eq!(state.opening(), "\u{1b}[1m\u{1b}[31m");
+        assert_eq!(state.closing(), "\u{1b}[0m");
+        let state = super::SgrState::after("\u{1b}[1m\u{1b}[31mx\u{1b}[0;32my");
+        assert_eq!(state.opening(), "\u{1b}[0;32m");
+        let state = super::SgrState::after("\u{1b}[1m\u{1b}[31mx\u{1b}[39my");
+        assert_eq!(state.opening(), "\u{1b}[1m\u{1b}[39m");
+        let state = super::SgrState::after("\u{1b}[44m\u{1b}[31mx\u{1b}[39my");
+        assert_eq!(state.opening(), "\u{1b}[44m");
+        let state = super::SgrState::after("\u{1b}[1m\u{1b}[31mx\u{1b}[mplain");
+        assert!(!state.is_active());
+        assert_eq!(state.closing(), "");
+    }
+
+    #[test]
+    fn frames_split_into_rows_that_redraw_alone() {
+        use std::io::Write;
+
+        let mut screen = super::Screen::default();
+        write!(screen, "dropped").unwrap();
+        write!(screen.at(0, 0), "\u{1b}[31mtab").unwrap();
+        write!(screen.at(0, 2), "body\u{1b}[0m").unwrap();
+        write!(screen.at(9, 0), "x").unwrap();
+        let rows: Vec<String> = screen
+            .into_rows()
+            .into_iter()
+            .map(|row| String::from_utf8(row).unwrap())
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                "\u{1b}[1;1H\u{1b}[31mtab\u{1b}[0m\u{1b}[1;10Hx",
+                "",
+                "\u{1b}[3;1H\u{1b}[31mbody\u{1b}[0m"
+            ]
+        );
+    }
+
+    #[test]
+    fn repaints_rewrite_only_the_rows_that_changed() {
+        let drawn = vec![b"\x1b[1;1Ha".to_vec(), b"\x1b[2;1Hb".to_vec()];
+        let rows = vec![b"\x1b[1;1Ha".to_vec(), b"\x1b[2;1Hc".to_vec()];
+
+        let mut out = Vec::new();
+        super::repaint(&mut out, &rows, Some(&drawn)).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "\u{1b}[2;1H\u{1b}[2K\u{1b}[2;1Hc"
+        );
+
+        let mut out = Vec::new();
+        super::repaint(&mut out, &rows[..1], Some(&drawn)).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "\u{1b}[2;1H\u{1b}[2K");
+
+        let mut out = Vec::new();
+        super::repaint(&mut out, &rows, None).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "\u{1b}[1;1H\u{1b}[2J\u{1b}[1;1Ha\u{1b}[2;1Hc"
+        );
+    }
+
+    #[test]
+    fn redrawing_writes_only_the_rows_whose_content_changed() {
+        use super::{App, InputMessage, UiMessage};
+
+        let mut app = App::from_args(["error".to_owned()]).unwrap();
+        app.apply_input(InputMessage::Line("error: disk full".to_owned(), None))
+            .unwrap();
+        app.handle_ui(UiMessage::SelectTab(1)).unwrap();
+        let drawn = app.draw_screen((40, 6)).unwrap().into_rows();
+
+        // Only the badge on `(all)` changes for a line the shown tab skips.
+        app.apply_input(InputMessage::Line("ok".to_owned(), None))
+            .unwrap();
+        assert!(app.needs_redraw());
+        let rows = app.draw_screen((40, 6)).unwrap().into_rows();
+        let mut out = Vec::new();
+        super::repaint(&mut out, &rows, Some(&drawn)).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.starts_with("\x1b[2;1H\x1b[2K"), "{out:?}");
+        assert_eq!(out.matches("\x1b[2K").count(), 1, "{out:?}");
+        assert!(out.contains("•1"), "{out:?}");
+
+        let again = app.draw_screen((40, 6)).unwrap().into_rows();
+        let mut out = Vec::new();
+        super::repaint(&mut out, &again, Some(&rows)).unwrap();
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn ctrl_l_and_regaining_focus_repaint_the_whole_screen() {
+        use super::{App, TerminalAction, UiMessage};
+
+        let mut app = App::from_args(["error".to_owned()]).unwrap();
+        app.render(40, 5);
+        assert!(!app.needs_redraw());
+        assert_eq!(
+            app.handle_ui(UiMessage::Redraw).unwrap(),
+            Some(TerminalAction::Redraw)
+        );
+        assert!(app.needs_redraw());
+        assert_eq!(
+            app.handle_ui(UiMessage::FocusGained).unwrap(),
+            Some(TerminalAction::Redraw)
+        );
+    }
+
+    #[test]
+    fn ansi_slices_replay_the_styles_in_effect_at_their_start() {
+        let text = "ab\u{1b}[1mcd\u{1b}[31mef\u{1b}[0mgh";
+        assert_eq!(
+            super::ansi_slice(text, 3, 3),
+            "\u{1b}[1md\u{1b}[31mef\u{1b}[0m"
+        );
+        assert_eq!(
+            super::ansi_slice(text, 4, 1),
+            "\u{1b}[1m\u{1b}[31me\u{1b}[0m"
+        );
+        assert_eq!(
+            super::ansi_slice(text, 6, 5),
+            "\u{1b}[1m\u{1b}[31m\u{1b}[0mgh"
+        );
+        assert_eq!(
+            super::ansi_slice(text, 0, 4),
+            "ab\u{1b}[1mcd\u{1b}[31m\u{1b}[0m"
+        );
+        assert_eq!(
+            super::ansi_slice("\u{1b}[32m日本", 1, 2),
+            "\u{1b}[32m  \u{1b}[0m"
+        );
+    }
+
+    #[test]
+    fn strip_ansi_removes_escape_sequences() {
+        let text = "\u{1b}[2m2026-02-06\u{1b}[0m \u{1b}[31mERROR\u{1b}[0m line";
+        assert_eq!(strip_ansi(text), "2026-02-06 ERROR line");
+    }
+
+    #[test]
+    fn wrapped_rows_reopen_nested_styles() {
+        assert_eq!(
+            super::wrap_ansi("\u{1b}[1m\u{1b}[31mabcdef\u{1b}[0m", 3),
+            vec![
+                "\u{1b}[1m\u{1b}[31mabc\u{1b}[0m",
+                "\u{1b}[1m\u{1b}[31mdef\u{1b}[0m"
+            ]
+        );
+    }
+
+    #[test]
+    fn wrap_words_breaks_on_spaces_and_splits_long_words() {
+        assert_eq!(
+            wrap_words("alpha beta gamma", 10),
+            vec!["alpha beta", "gamma"]
+        );
+        assert_eq!(wrap_words("abcdefghij", 4), vec!["abcd", "efgh", "ij"]);
+        assert_eq!(wrap_words("ab  cd", 10), vec!["ab  cd"]);
+        assert_eq!(wrap_words("", 10), vec![""]);
+    }
+
+    #[test]
+    fn wrapped_lines_break_at_words_and_carry_their_colors() {
+        assert_eq!(
+            super::wrap_ansi("alpha beta gamma", 11),
+            vec!["alpha beta ", "gamma"]
+        );
+        assert_eq!(
+            super::wrap_ansi("abcdefghij", 4),
+            vec!["abcd", "efgh", "ij"]
+        );
+        assert_eq!(
+            super::wrap_ansi("\u{1b}[31mred words\u{1b}[0m plain", 6),
+            vec![
+                "\u{1b}[31mred \u{1b}[0m",
+                "\u{1b}[31mwords\u{1b}[0m ",
+                "plain"
+            ]
+        );
+
+        let line = |seq: u64, text: &'static str| RenderedLine {
+            seq,
+            text: text.into(),
+            selected: false,
+            injected: false,
+            separator: None,
+        };
+        let (rows, wrapped) =
+            super::wrap_rendered_lines(vec![line(1, "short"), line(2, "one two three")], 8);
+        assert_eq!(
+            rows.iter().map(|row| row.seq).collect::<Vec<_>>(),
+            vec![1, 2, 2]
+        );
+        assert_eq!(rows[2].text, "one two three");
+        assert_eq!(wrapped[0], None);
+        assert_eq!(
+            wrapped[2],
+            Some(super::WrappedRow {
+                text: "three".to_owned(),
+                continuation: true,
+            })
+        );
+
+        // Scrolling lands on whole lines, up or down.
+        assert_eq!(super::snap_to_line_start((2, 1, 0), &wrapped, 1), None);
+        assert_eq!(
+            super::snap_to_line_start((2, 1, 0), &wrapped, -1),
+            Some((1, 1, 0))
+        );
+    }
+
+    #[test]
+    fn detail_pane_leaves_room_for_body() {
+        let selected = SelectedLine {
+            seq: 1,
+            text: "\u{1b}[31mone two three four five six\u{1b}[0m".to_owned(),
+        };
+        let rows = super::detail_pane_text_rows(Some(&selected), 12, 20);
+        assert_eq!(rows, vec!["one two", "three four", "five six"]);
+
+        let capped = super::detail_pane_text_rows(Some(&selected), 12, 5);
+        assert_eq!(capped.len(), 2);
+        assert!(super::detail_pane_text_rows(Some(&selected), 12, 3).is_empty());
+        assert!(super::detail_pane_text_rows(None, 12, 20).is_empty());
+    }
+
+    #[test]
+    fn strip_cr_removes_every_carriage_return() {
+        let options = super::CaptureOptions {
+            strip_cr: true,
+            ..Default::default()
+        };
+        let (tx, rx) = std::sync::mpsc::sync_channel(4);
+        super::read_input_lines(
+            std::io::Cursor::new("a\rb\r\nc\r\n"),
+            &tx,
+            &options,
+            super::Overflow::Block,
+        );
+
+        let lines = rx
+            .try_iter()
+            .filter_map(|message| match message {
+                super::InputMessage::Line(line, _) => Some(line),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(lines, vec!["ab", "c"]);
+    }
+
+    #[test]
+    fn carriage_returns_leave_what_a_terminal_would_show() {
+        let (tx, rx) = std::sync::mpsc::sync_channel(4);
+        super::read_input_lines(
+            std::io::Cursor::new("10%\r50%\r100%\ndownloading...\rdone\r\nplain\r\n"),
+            &tx,
+            &super::CaptureOptions::default(),
+            super::Overflow::Block,
+        );
+
+        let lines = rx
+            .try_iter()
+            .filter_map(|message| match message {
+                super::InputMessage::Line(line, _) => Some(line),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(lines, vec!["100%", "doneloading...", "plain"]);
+        assert_eq!(
+            super::collapse_carriage_returns("\u{1b}[32mabcd\u{1b}[0m\rXY"),
+            "XY\u{1b}[32mcd\u{1b}[0m"
+        );
+    }
+
+    #[test]
+    fn several_files_are_merged_with_a_tag_per_file() {
+        use std::path::PathBuf;
+
+        let paths = [PathBuf::from("/var/log/app.log"), PathBuf::from("db.log")];
+        assert_eq!(super::source_labels(&paths), ["app.log", "db.log"]);
+        let clash = [PathBuf::from("a/app.log"), PathBuf::from("b/app.log")];
+        assert_eq!(super::source_labels(&clash), ["a/app.log", "b/app.log"]);
+
+        let app = super::source_tag("app", 0, 4);
+        let db = super::source_tag("db", 1, 4);
+        assert_eq!(app, "\x1b[36mapp \x1b[39m ");
+        assert_eq!(db, "\x1b[33mdb  \x1b[39m ");
+        let sources = ["app".to_owned(), "db".to_owned()];
+        assert_eq!(
+            super::tagged_text("ready", Some(1), &sources),
+            "\x1b[33mdb \x1b[39m ready"
+        );
+        assert_eq!(super::tagged_text("ready", None, &sources), "ready");
+
+        let (tx, rx) = std::sync::mpsc::sync_channel(8);
+        let reader = |text: &'static str| -> Box<dyn std::io::BufRead + Send> {
+            Box::new(std::io::Cursor::new(text))
+        };
+        super::spawn_input_reader(
+            tx,
+            super::CaptureOptions::default(),
+            super::Overflow::Block,
+            super::InputSource::Tagged(vec![reader("up\n\n"), reader("ready\n")]),
+        );
+        let mut lines = Vec::new();
+        let mut closed = 0;
+        while let Ok(message) = rx.recv_timeout(std::time::Duration::from_secs(2)) {
+            match message {
+                super::InputMessage::Line(line, source) => lines.push((line, source)),
+                super::InputMessage::Closed => closed += 1,
+                super::InputMessage::Skipped(_) => panic!("nothing should be dropped"),
+                super::InputMessage::Partial(_) => panic!("lines should arrive whole"),
+                super::InputMessage::Error(err) => panic!("{err}"),
+            }
+        }
+        // Sorted, since the files are read at the same time.
+        lines.sort();
+        let line = |text: &str, source| (text.to_owned(), Some(source));
+        assert_eq!(lines, [line("", 0), line("ready", 1), line("up", 0)]);
+        assert_eq!(closed, 1);
+    }
+
+    #[test]
+    fn source_tags_are_drawn_beside_lines_rather_than_in_them() {
+        use super::{App, InputMessage};
+
+        let args = ["--file", "api.log", "--file", "db.log", "re:^up", "db"];
+        let mut app = App::from_args(args.map(String::from)).unwrap();
+        app.apply_input(InputMessage::Line("up".to_owned(), Some(0)))
+            .unwrap();
+        app.apply_input(InputMessage::Line("down".to_owned(), Some(1)))
+            .unwrap();
+        // Neither the anchor nor the other file's name sees the tag.
+        assert_eq!(app.tabs()[1].lines().collect::<Vec<_>>(), [(0, "up")]);
+        assert_eq!(app.tabs()[2].lines().count(), 0);
+
+        let frame = app.frame_text(30, 5);
+        assert!(
+            frame.contains("\x1b[38;5;6mapi.log \x1b[39m\x1b[4;9Hup"),
+            "{frame:?}"
+        );
+        assert!(
+            frame.contains("\x1b[38;5;3mdb.log  \x1b[39m\x1b[5;9Hdown"),
+            "{frame:?}"
+        );
+    }
+
+    #[test]
+    fn transforms_trim_and_expand_tabs_in_order() {
+        use super::Transform;
+
+        assert_eq!(
+            Transform::Trim.apply("  keep lead \t ".into()),
+            "  keep lead"
+        );
+        assert_eq!(Transform::ExpandTabs(8).apply("a\tb".into()), "a       b");
+        assert_eq!(
+            Transform::ExpandTabs(8).apply("\tx\ty".into()),
+            "        x       y"
+        );
+        // Color codes don't move the tab stops.
+        assert_eq!(
+            Transform::ExpandTabs(8).apply("\u{1b}[31mab\u{1b}[0m\tc".into()),
+            "\u{1b}[31mab\u{1b}[0m      c"
+        );
+        assert_eq!(Transform::Lowercase.apply("ERROR".into()), "ERROR");
+
+        let options = super::CaptureOptions {
+            transforms: vec![Transform::ExpandTabs(8), Transform::Trim],
+            ..Default::default()
+        };
+        assert_eq!(options.apply("a\t\t".into()).as_deref(), Some("a"));
+        assert!(Transform::parse("upper").is_err());
+    }
+
+    #[test]
+    fn tabstop_sets_the_expansion_width_and_implies_expand_tabs() {
+        use super::Transform;
+
+        let parse = |args: &[&str]| super::parse_args(args.iter().map(|arg| arg.to_string()));
+        let options = parse(&["--transform", "trim", "--tabstop", "4", "x"]).unwrap();
+        assert_eq!(
+            options.capture.transforms,
+            vec![Transform::ExpandTabs(4), Transform::Trim]
+        );
+        assert_eq!(
+            options.capture.apply("ab\tc\t".into()).as_deref(),
+            Some("ab  c")
+        );
+
+        let options = parse(&["--tabstop", "2", "--transform", "expand-tabs", "x"]).unwrap();
+        assert_eq!(options.capture.transforms, vec![Transform::ExpandTabs(2)]);
+        assert!(parse(&["--tabstop", "0", "x"]).is_err());
+    }
+
+    #[test]
+    fn lowercase_transform_matches_folded_but_stores_original() {
+        let mut tabs = vec![Tab::new("error".into())];
+
+        super::ingest_line(
+            &mut tabs,
+            &[0],
+            false,
+            &super::LineRecord::new(0, "ERROR: disk", None),
+            false,
+            true,
+        );
+        super::ingest_line(
+            &mut tabs,
+            &[0],
+            false,
+            &super::LineRecord::new(1, "ERROR: disk", None),
+            false,
+            false,
+        );
+        assert_eq!(tabs[0].total_matches, 1);
+        assert_eq!(tabs[0].lines[0].text, "ERROR: disk");
+
+        let mut history = super::History::new(10);
+        history.push_line(0, "Error again");
+        history.fold_case = true;
+        assert!(super::rebuild_tab(&mut tabs, &history, 0));
+        assert_eq!(tabs[0].lines[0].text, "Error again");
+    }
+
+    #[test]
+    fn cursor_movement_codes_are_removed_but_colors_kept() {
+        let text = "\u{1b}[2K\u{1b}[1A\u{1b}[31mERROR\u{1b}[0m done\u{1b}[10;4H!";
+        assert_eq!(
+            super::strip_non_sgr_csi(text),
+            "\u{1b}[31mERROR\u{1b}[0m done!"
+        );
+        assert_eq!(super::strip_non_sgr_csi("\u{1b}[?25lplain"), "plain");
+        assert_eq!(super::csi_kind('m'), super::CsiKind::Sgr);
+        assert_eq!(super::csi_kind('J'), super::CsiKind::CursorMovement);
+    }
+
+    #[test]
+    fn column_range_maps_screen_columns_past_ansi() {
+        let text = "\u{1b}[2mid=\u{1b}[0m\u{1b}[33mabc123\u{1b}[0m rest";
+        assert_eq!(super::column_range_text(text, 3, 8), "abc123");
+        assert_eq!(super::column_range_text(text, 8, 3), "");
+        assert_eq!(super::column_range_text(text, 12, 40), "st");
+        assert_eq!(super::column_range_text(text, 40, 50), "");
+    }
+
+    #[test]
+    fn column_range_includes_wide_chars_partially_covered() {
+        assert_eq!(super::column_range_text("a好b", 2, 2), "好");
+        assert_eq!(super::column_range_text("a好b", 1, 3), "好b");
+    }
+
+    #[test]
+    fn base64_matches_reference_encoding() {
+        assert_eq!(super::base64_encode(b""), "");
+        assert_eq!(super::base64_encode(b"f"), "Zg==");
+        assert_eq!(super::base64_encode(b"fo"), "Zm8=");
+        assert_eq!(super::base64_encode(b"foobar"), "Zm9vYmFy");
+    }
+
+    #[test]
+    fn dumped_tab_is_named_for_its_label_and_time() {
+        use std::time::{Duration, UNIX_EPOCH};
+
+        let mut tab = Tab::new("re:a/b".into());
+        tab.push_line(0, "\u{1b}[31mone\u{1b}[0m");
+        tab.push_line(4, "two");
+        let dir = std::env::temp_dir().join(format!("streamtabs-dump-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let now = UNIX_EPOCH + Duration::from_secs(1_760_400_000);
+        let (_, _, _, hour, minute, second) = super::civil_time(now);
+
+        let (path, count) = super::dump_tab(&tab, &dir, false, now).unwrap();
+        assert_eq!(count, 2);
+        let name = path.file_name().unwrap().to_string_lossy().into_owned();
+        assert!(name.starts_with("streamtabs-re_a_b-2025101"), "{name}");
+        assert!(name.ends_with(&format!("{hour:02}{minute:02}{second:02}.log")));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "one\ntwo\n");
+
+        // Dumps within the same second keep the earlier files.
+        let (colored, _) = super::dump_tab(&tab, &dir, true, now).unwrap();
+        assert_eq!(colored, dir.join(name.replace(".log", "-1.log")));
+        assert!(
+            std::fs::read_to_string(&colored)
+                .unwrap()
+                .starts_with("\u{1b}[31m")
+        );
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "one\ntwo\n");
+        let (third, _) = super::dump_tab(&tab, &dir, false, now).unwrap();
+        assert_eq!(third, dir.join(name.replace(".log", "-2.log")));
+
+        // A label too long for a file name is cut short rather than failing.
+        let long = Tab::new(format!("re:{}", "x".repeat(300)));
+        let (path, _) = super::dump_tab(&long, &dir, false, now).unwrap();
+        let name = path.file_name().unwrap().to_string_lossy().into_owned();
+        let label = format!("re_{}", "x".repeat(45));
+        assert!(
+            name.starts_with(&format!("streamtabs-{label}-2025")),
+            "{name}"
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(super::dump_tab(&tab, &dir, false, now).is_err());
+    }
+
+    #[test]
+    fn a_key_pause_waits_for_the_reader_to_stop_reading() {
+        use super::{KeyHandoff, TerminalInput, forward_terminal_input};
+        use std::sync::Arc;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::time::Duration;
+
+        /// A terminal with a key always waiting, counting the reads.
+        struct Busy(Arc<AtomicUsize>);
+        impl TerminalInput for Busy {
+            fn wait(&mut self, _: Duration) -> bool {
+                std::thread::sleep(Duration::from_millis(1));
+                true
+            }
+            fn read(&mut self) -> std::io::Result<Option<Vec<UiMessage>>> {
+                self.0.fetch_add(1, Ordering::SeqCst);
+                Ok(Some(vec![UiMessage::NextTab]))
+            }
+        }
+
+        let reads = Arc::new(AtomicUsize::new(0));
+        let handoff = Arc::new(KeyHandoff::default());
+        let (tx, rx) = std::sync::mpsc::sync_channel(1024);
+        let reader = {
+            let (reads, handoff) = (Arc::clone(&reads), Arc::clone(&handoff));
+            std::thread::spawn(move || forward_terminal_input(Busy(reads), &tx, &handoff))
+        };
+        rx.recv().unwrap();
+
+        handoff.pause();
+        let paused_at = reads.load(Ordering::SeqCst);
+        std::thread::sleep(Duration::from_millis(30));
+        assert_eq!(reads.load(Ordering::SeqCst), paused_at);
+        while rx.try_recv().is_ok() {}
+
+        handoff.resume();
+        rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert!(reads.load(Ordering::SeqCst) > paused_at);
+        // A second pause doesn't take the first one's park for its own.
+        handoff.pause();
+        let paused_at = reads.load(Ordering::SeqCst);
+        std::thread::sleep(Duration::from_millis(30));
+        assert_eq!(reads.load(Ordering::SeqCst), paused_at);
+
+        handoff.resume();
+        drop(rx);
+        reader.join().unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn the_pager_gets_the_text_and_a_missing_one_is_reported() {
+        let path = std::env::temp_dir().join(format!("streamtabs-pager-{}", std::process::id()));
+        let pager = format!("cat > '{}'", path.display());
+        assert_eq!(super::run_pager(&pager, "one\ntwo\n"), Ok(()));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "one\ntwo\n");
+        std::fs::remove_file(&path).unwrap();
+
+        // `LESS` is filled in only when unset, so colors survive `less`.
+        let path = path.with_extension("env");
+        let pager = format!("echo \"$LESS\" > '{}'", path.display());
+        super::run_pager(&pager, "").unwrap();
+        let less = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        if std::env::var_os("LESS").is_none() {
+            assert_eq!(less, "R\n");
+        }
+
+        assert_eq!(
+            super::run_pager("streamtabs-no-such-pager", "text"),
+            Err("pager not found: streamtabs-no-such-pager (set $PAGER)".to_owned())
+        );
+        // A pager that quits early is fine, even with text left unread.
+        assert_eq!(super::run_pager("exit 0", &"x\n".repeat(100_000)), Ok(()));
+    }
+
+    #[test]
+    fn clipboard_payload_stops_at_the_limit_on_a_char_boundary() {
+        use super::{CLIPBOARD_MAX_BYTES, clipboard_payload};
+
+        assert_eq!(clipboard_payload("short"), "short");
+        let long = format!("a{}", "é".repeat(CLIPBOARD_MAX_BYTES));
+        let payload = clipboard_payload(&long);
+        // "a" then two-byte chars: the limit falls mid-char and backs off.
+        assert_eq!(payload.len(), CLIPBOARD_MAX_BYTES - 1);
+        assert!(long.starts_with(payload));
+
+        let status = super::copied_message(payload, &long);
+        assert!(status.text.starts_with("copied the first"));
+        assert_eq!(super::copied_message("abc", "abc").text, "copied 3 chars");
+    }
+
+    #[test]
+    fn clip_with_ellipsis_marks_truncation() {
+        assert_eq!(clip_with_ellipsis("abcdef", 6), "abcdef");
+        assert_eq!(clip_with_ellipsis("abcdef", 5), "ab...");
+        assert_eq!(clip_with_ellipsis("abcdef", 3), "...");
+    }
+
+    #[test]
+    fn tab_title_fits_budget() {
+        assert_eq!(fit_tab_title("hello", 8), " hello  ");
+        assert_eq!(fit_tab_title("very-long-label", 8), " ver... ");
+        assert_eq!(fit_tab_title("ignored", 2), "  ");
+    }
+
+    #[test]
+    fn tab_strip_scrolls_to_keep_the_followed_tab_in_view() {
+        let tabs: Vec<super::Tab> = (0..6).map(|_| super::Tab::new("aaaa".into())).collect();
+        let layout = super::BadgeLayout::Unread;
+        let tab_width = super::drawn_tab_width(&tabs[0], 0, 0, layout) + 1;
+        let limit = tab_width * 3;
+
+        assert_eq!(super::tab_strip_start(&tabs, 0, layout, limit, 9, None), 5);
+        assert_eq!(super::tab_strip_start(&tabs, 0, layout, limit, 2, None), 2);
+        // Already in view, so the offset stays put.
+        assert_eq!(
+            super::tab_strip_start(&tabs, 0, layout, limit, 0, Some(1)),
+            0
+        );
+        // Behind the offset, so the bar scrolls back to it.
+        assert_eq!(
+            super::tab_strip_start(&tabs, 0, layout, limit, 4, Some(1)),
+            1
+        );
+        // Past the end, so it scrolls on until the tab and both markers fit.
+        assert_eq!(
+            super::tab_strip_start(&tabs, 0, layout, limit, 0, Some(3)),
+            2
+        );
+        assert_eq!(
+            super::tab_strip_start(&tabs, 0, layout, limit, 0, Some(5)),
+            4
+        );
+
+        assert_eq!(super::tab_overflow_marker(3, true), "‹3");
+        assert_eq!(super::tab_overflow_marker(12, false), "›12");
+        assert_eq!(super::tab_overflow_width(0), 0);
+        assert_eq!(super::tab_overflow_width(12), 4);
+    }
+
+    #[test]
+    fn without_follow_the_view_holds_while_lines_keep_arriving() {
+        use super::{App, InputMessage, UiMessage};
+
+        let mut app = App::from_args(["error".to_owned()]).unwrap();
+        let line = |app: &mut App, text: String| {
+            app.apply_input(InputMessage::Line(text, None)).unwrap();
+        };
+        for n in 0..10 {
+            line(&mut app, format!("info {n}"));
+        }
+        app.render(40, 8);
+        app.handle_ui(UiMessage::ToggleFollow).unwrap();
+        let held = app.render(40, 8).visible_seqs();
+        assert_eq!(held, [6, 7, 8, 9]);
+
+        line(&mut app, "info 10".to_owned());
+        line(&mut app, "error 11".to_owned());
+        assert_eq!(app.render(40, 8).visible_seqs(), held);
+        // Unlike a pause, the shown tab keeps up and the others count on.
+        assert!(!app.is_paused());
+        assert_eq!(app.tabs()[0].unread_matches(), 0);
+        assert_eq!(app.tabs()[1].unread_matches(), 1);
+
+        app.handle_ui(UiMessage::ToggleFollow).unwrap();
+        assert_eq!(app.render(40, 8).visible_seqs(), [8, 9, 10, 11]);
+    }
+
+    #[test]
+    fn help_hints_follow_the_view_state() {
+        let keys = |hints: Vec<(&'static str, &'static str)>| {
+            hints.into_iter().map(|(key, _)| key).collect::<Vec<_>>()
+        };
+
+        let live = keys(super::help_hints(false, false, false, false));
+        assert_eq!(live[..3], ["Tab", "Space", "s"]);
+        assert!(!live.contains(&"PgUp/PgDn"));
+
+        let paused = super::help_hints(false, false, true, true);
+        assert!(paused.contains(&("Space", "resume")));
+        assert!(keys(paused.clone()).contains(&"PgUp/PgDn"));
+        assert!(keys(paused).contains(&"y"));
+
+        assert_eq!(keys(super::help_hints(true, false, true, true))[0], "Enter");
+        assert!(keys(super::help_hints(false, true, false, false)).contains(&"o"));
+    }
+
+    #[test]
+    fn mode_label_uses_glyphs_in_corner_when_compact() {
+        use super::{mode_label_column, tab_columns_limit, view_mode_label};
+        use std::time::Duration;
+
+        let paused = Some(Duration::ZERO);
+        assert_eq!(view_mode_label(None, false, false, false), "");
+        assert_eq!(
+            view_mode_label(paused, false, true, false),
+            " (paused) (unread only)"
+        );
+        assert_eq!(view_mode_label(paused, false, true, true), " ⏸ •");
+        assert_eq!(view_mode_label(None, true, false, false), " (input held)");
+        assert_eq!(view_mode_label(paused, true, true, true), " ⏸ ⏹ •");
+        // The paused time shows from the first full second, and not with glyphs.
+        let paused = |secs| Some(Duration::from_secs(secs));
+        assert_eq!(
+            view_mode_label(paused(42), false, false, false),
+            " (paused 00:42)"
+        );
+        assert_eq!(
+            view_mode_label(paused(3725), false, false, false),
+            " (paused 1:02:05)"
+        );
+        assert_eq!(view_mode_label(paused(42), false, false, true), " ⏸");
+        let paused = Some(Duration::ZERO);
+
+        let text = view_mode_label(paused, false, false, false);
+        assert_eq!(tab_columns_limit(80, &text), 71);
+        assert_eq!(mode_label_column(30, 80, &text, false), 31);
+
+        let glyphs = view_mode_label(None, false, true, true);
+        assert_eq!(tab_columns_limit(80, &glyphs), 78);
+        assert_eq!(mode_label_column(30, 80, &glyphs, true), 78);
+    }
+
+    #[test]
+    fn body_is_bottom_anchored_when_not_full() {
+        assert_eq!(super::first_body_row(3, 10, 1), 12);
+        assert_eq!(super::first_body_row(3, 10, 10), 3);
+    }
+
+    #[test]
+    fn unread_slot_is_fixed_width_and_caps() {
+        let unread = super::BadgeLayout::Unread;
+        assert_eq!(super::format_badge(5, 0, unread), "      ");
+        assert_eq!(super::format_badge(7, 7, unread), "    •7");
+        assert_eq!(super::format_badge(999, 999, unread), "  •999");
+        assert_eq!(super::format_badge(1000, 1000, unread), " •999+");
+    }
+
+    #[test]
+    fn badge_flashes_only_shortly_after_a_live_match() {
+        use std::time::Instant;
+
+        let mut tabs = vec![Tab::new("error".into())];
+        let mut history = super::History::new(10);
+        history.push_line(0, "error from history");
+        assert!(super::rebuild_tab(&mut tabs, &history, 0));
+        assert_eq!(tabs[0].badge_flash_until(Instant::now()), None);
+
+        apply_line_to_tabs(&mut tabs, &[], false, 1, "error live");
+        let matched = tabs[0].last_match_at.expect("live match is timed");
+        assert_eq!(
+            tabs[0].badge_flash_until(matched),
+            Some(matched + super::BADGE_FLASH)
+        );
+        assert_eq!(
+            tabs[0].badge_flash_until(matched + super::BADGE_FLASH),
+            None
+        );
+    }
+
+    #[test]
+    fn total_badge_is_fixed_width_and_falls_back_when_narrow() {
+        let total = super::BadgeLayout::TotalAndUnread;
+        assert_eq!(super::format_badge(123, 4, total), "  123 •4   ");
+        assert_eq!(super::format_badge(0, 0, total), "    0      ");
+        assert_eq!(super::format_badge(45_678, 1000, total), "  45k •999+");
+        assert_eq!(super::format_badge(2_000_000, 0, total), "  1M+      ");
+
+        let tabs = vec![Tab::unfiltered(), Tab::new("warn".into())];
+        // "│ 0  (all)     0       │", a gap, then "│ 1  warn     0       │".
+        let needed = super::full_tab_bar_width(&tabs, 0, total);
+        assert_eq!(needed, 24 + 1 + 23);
+        assert_eq!(
+            super::effective_badge_layout(&tabs, 0, needed, total),
+            total
+        );
+        assert_eq!(
+            super::effective_badge_layout(&tabs, 0, needed - 1, total),
+            super::BadgeLayout::Unread
+        );
+    }
+
+    #[test]
+    fn key_mapping_handles_supported_keys() {
+        assert!(matches!(
+            key_message_from_byte(b'\t'),
+            Some(UiMessage::NextTab)
+        ));
+        assert!(matches!(
+            key_message_from_byte(b'5'),
+            Some(UiMessage::SelectTab(5))
+        ));
+        assert!(matches!(
+            key_message_from_byte(b'0'),
+            Some(UiMessage::SelectTab(0))
+        ));
+        assert!(matches!(
+            key_message_from_byte(b' '),
+            Some(UiMessage::TogglePause)
+        ));
+        assert!(matches!(
+            key_message_from_byte(b'd'),
+            Some(UiMessage::ClearSelection)
+        ));
+        assert!(matches!(
+            key_message_from_byte(b'D'),
+            Some(UiMessage::ClearSelection)
+        ));
+        assert!(matches!(
+            key_message_from_byte(b's'),
+            Some(UiMessage::SelectMiddleVisibleLine)
+        ));
+        assert!(matches!(
+            key_message_from_byte(b'S'),
+            Some(UiMessage::SelectMiddleVisibleLine)
+        ));
+        assert!(matches!(
+            key_message_from_byte(b'v'),
+            Some(UiMessage::ToggleDetailPane)
+        ));
+        assert!(matches!(
+            key_message_from_byte(b'm'),
+            Some(UiMessage::CycleMatchMode)
+        ));
+        assert!(matches!(
+            key_message_from_byte(b'!'),
+            Some(UiMessage::InvertTab)
+        ));
+        assert!(matches!(
+            key_message_from_byte(b'h'),
+            Some(UiMessage::ToggleInputHold)
+        ));
+        assert!(matches!(
+            key_message_from_byte(b'g'),
+            Some(UiMessage::ToggleGaps)
+        ));
+        assert!(matches!(
+            key_message_from_byte(b'/'),
+            Some(UiMessage::StartSearch)
+        ));
+        assert!(matches!(
+            key_message_from_byte(b'n'),
+            Some(UiMessage::SearchNext)
+        ));
+        assert!(matches!(
+            key_message_from_byte(b'N'),
+            Some(UiMessage::SearchPrevious)
+        ));
+        assert!(matches!(
+            key_message_from_byte(b'p'),
+            Some(UiMessage::TogglePreview)
+        ));
+        assert!(matches!(
+            key_message_from_byte(b'r'),
+            Some(UiMessage::OpenPalette)
+        ));
+        assert!(matches!(
+            key_message_from_byte(b'c'),
+            Some(UiMessage::CycleFrequencyView)
+        ));
+        assert!(matches!(
+            key_message_from_byte(b'l'),
+            Some(UiMessage::OpenPager)
+        ));
+        assert!(matches!(
+            key_message_from_byte(b'+'),
+            Some(UiMessage::ToggleRelativeSeq)
+        ));
+        assert!(matches!(
+            key_message_from_byte(b'-'),
+            Some(UiMessage::CloseTab)
+        ));
+        assert!(matches!(
+            key_message_from_byte(b'#'),
+            Some(UiMessage::ToggleSeqGutter)
+        ));
+        assert!(matches!(
+            key_message_from_byte(b'y'),
+            Some(UiMessage::CopySelection)
+        ));
+        assert!(matches!(
+            key_message_from_byte(b'b'),
+            Some(UiMessage::DumpActiveTab)
+        ));
+        assert!(matches!(
+            key_message_from_byte(b'?'),
+            Some(UiMessage::ToggleHelp)
+        ));
+        assert!(matches!(
+            key_message_from_byte(b'<'),
+            Some(UiMessage::ScrollTabs(-1))
+        ));
+        assert!(matches!(
+            key_message_from_byte(b'>'),
+            Some(UiMessage::ScrollTabs(1))
+        ));
+        assert!(matches!(
+            key_message_from_byte(b'i'),
+            Some(UiMessage::ToggleFollow)
+        ));
+        assert!(matches!(
+            key_message_from_byte(b'f'),
+            Some(UiMessage::ToggleWrap)
+        ));
+        assert!(matches!(
+            key_message_from_byte(b't'),
+            Some(UiMessage::ToggleClock)
+        ));
+        assert!(matches!(
+            key_message_from_byte(b'a'),
+            Some(UiMessage::MarkRead)
+        ));
+        assert!(matches!(
+            key_message_from_byte(b'w'),
+            Some(UiMessage::ToggleSplit)
+        ));
+        assert!(matches!(
+            key_message_from_byte(b'e'),
+            Some(UiMessage::SwitchSplitFocus)
+        ));
+        assert!(matches!(
+            key_message_from_byte(b'x'),
+            Some(UiMessage::ToggleMarker)
+        ));
+        assert!(matches!(
+            key_message_from_byte(b'z'),
+            Some(UiMessage::CycleMarkerSlice)
+        ));
+        assert!(matches!(
+            key_message_from_byte(b'o'),
+            Some(UiMessage::ToggleOverview)
+        ));
+        assert!(matches!(
+            key_message_from_byte(b'\r'),
+            Some(UiMessage::Activate)
+        ));
+        assert!(matches!(
+            key_message_from_byte(b'j'),
+            Some(UiMessage::MoveSelection(1))
+        ));
+        assert!(matches!(
+            key_message_from_byte(b'k'),
+            Some(UiMessage::MoveSelection(-1))
+        ));
+        assert!(matches!(
+            key_message_from_byte(b'u'),
+            Some(UiMessage::ToggleUnreadOnly)
+        ));
+        assert!(matches!(key_message_from_byte(b'q'), Some(UiMessage::Quit)));
+        assert!(matches!(
+            key_message_from_byte(0x03),
+            Some(UiMessage::Interrupt)
+        ));
+        assert!(matches!(
+            key_message_from_byte(0x0c),
+            Some(UiMessage::Redraw)
+        ));
+        assert!(key_message_from_byte(b'\n').is_none());
+    }
+
+    #[test]
+    fn sgr_mouse_parser_decodes_left_click() {
+        assert!(matches!(
+            try_parse_sgr_mouse_message(b"<0;12;7M"),
+            Some(UiMessage::MouseLeftDown {
+                column: 11,
+                row: 6,
+                shift: false
+            })
+        ));
+        assert!(matches!(
+            try_parse_sgr_mouse_message(b"<4;12;7M"),
+            Some(UiMessage::MouseLeftDown {
+                column: 11,
+                row: 6,
+                shift: true
+            })
+        ));
+        assert!(matches!(
+            try_parse_sgr_mouse_message(b"<32;15;7M"),
+            Some(UiMessage::MouseLeftDrag { column: 14 })
+        ));
+        assert!(matches!(
+            try_parse_sgr_mouse_message(b"<0;15;7m"),
+            Some(UiMessage::MouseLeftUp { column: 14 })
+        ));
+        assert!(matches!(
+            try_parse_sgr_mouse_message(b"<35;12;7M"),
+            Some(UiMessage::MouseMove { row: 6 })
+        ));
+        assert!(try_parse_sgr_mouse_message(b"<34;12;7M").is_none());
+        assert!(matches!(
+            try_parse_sgr_mouse_message(b"<2;12;7M"),
+            Some(UiMessage::MouseRightDown { row: 6 })
+        ));
+        assert!(try_parse_sgr_mouse_message(b"<2;12;7m").is_none());
+        // Wheel notches scroll rather than click.
+        assert!(matches!(
+            try_parse_sgr_mouse_message(b"<64;12;7M"),
+            Some(UiMessage::Scroll(_))
+        ));
+    }
+
+    #[test]
+    fn selected_line_is_injected_into_non_matching_tabs() {
+        let mut tab = Tab::new("foo".into());
+        tab.push_line(1, "foo first");
+        tab.push_line(3, "foo second");
+        let selected = SelectedLine {
+            seq: 2,
+            text: "picked elsewhere".to_owned(),
+        };
+
+        let visible = prepare_visible_lines(&tab, tab.lines.len(), Some(&selected));
+        assert_eq!(visible.len(), 3);
+        assert_eq!(visible[0].seq, 1);
+        assert_eq!(visible[1].seq, 2);
+        assert_eq!(visible[1].text, "picked elsewhere");
+        assert!(visible[1].selected);
+        assert!(visible[1].injected);
+        assert_eq!(visible[2].seq, 3);
+
+        // A selection the tab holds itself is styled as usual.
+        let held = SelectedLine {
+            seq: 3,
+            text: "foo second".to_owned(),
+        };
+        let visible = prepare_visible_lines(&tab, tab.lines.len(), Some(&held));
+        assert!(visible[1].selected && !visible[1].injected);
+
+        use super::{SelectionStyle, selected_line_text};
+        for style in [
+            SelectionStyle::Recolor,
+            SelectionStyle::Reverse,
+            SelectionStyle::Margin,
+        ] {
+            assert_ne!(
+                selected_line_text("x", 10, style, true, false),
+                selected_line_text("x", 10, style, false, false)
+            );
+        }
+    }
+
+    #[test]
+    fn or_view_merges_matching_tabs_without_duplicates() {
+        let mut tabs = vec![Tab::new("foo".into()), Tab::new("bar".into())];
+
+        apply_line_to_tabs(&mut tabs, &[0], false, 0, "foo only");
+        apply_line_to_tabs(&mut tabs, &[0], false, 1, "bar only");
+        apply_line_to_tabs(&mut tabs, &[0], false, 2, "foo and bar");
+
+        let visible = prepare_visible_lines_for_tabs(&tabs, &[0, 1], None, None, false);
+        let seqs = visible.iter().map(|line| line.seq).collect::<Vec<_>>();
+        assert_eq!(seqs, vec![0, 1, 2]);
+        // The text is the tab's own, not a copy.
+        assert!(
+            visible
+                .iter()
+                .all(|line| matches!(line.text, std::borrow::Cow::Borrowed(_)))
+        );
+    }
+
+    #[test]
+    fn unread_only_view_keeps_lines_unread_at_switch_time() {
+        let mut tabs = vec![Tab::new("foo".into()), Tab::new("bar".into())];
+        let mut active_index = 0usize;
+        let mut active_tab_indices = vec![0usize];
+
+        apply_line_to_tabs(&mut tabs, &active_tab_indices, false, 0, "bar old");
+        super::select_tab(
+            &mut tabs,
+            &mut active_index,
+            &mut active_tab_indices,
+            1,
+            false,
+            None,
+        );
+        super::select_tab(
+            &mut tabs,
+            &mut active_index,
+            &mut active_tab_indices,
+            0,
+            false,
+            None,
+        );
+        apply_line_to_tabs(&mut tabs, &active_tab_indices, false, 1, "bar new");
+        apply_line_to_tabs(&mut tabs, &active_tab_indices, false, 2, "bar newer");
+
+        super::select_tab(
+            &mut tabs,
+            &mut active_index,
+            &mut active_tab_indices,
+            1,
+            false,
+            None,
+        );
+        assert_eq!(tabs[1].unread_matches(), 0);
+
+        let visible = prepare_visible_lines_for_tabs(&tabs, &[1], None, None, true);
+        let seqs = visible.iter().map(|line| line.seq).collect::<Vec<_>>();
+        assert_eq!(seqs, vec![1, 2]);
+
+        let everything = prepare_visible_lines_for_tabs(&tabs, &[1], None, None, false);
+        assert_eq!(everything.len(), 3);
+    }
+
+    #[test]
+    fn shift_click_toggles_tab_membership_when_multiple_tabs_active() {
+        let mut tabs = vec![
+            Tab::unfiltered(),
+            Tab::new("foo".into()),
+            Tab::new("bar".into()),
+        ];
+        let mut active_index = 1usize;
+        let mut active_tab_indices = vec![0usize, 1usize];
+
+        include_tab_in_or_view(
+            &mut tabs,
+            &mut active_index,
+            &mut active_tab_indices,
+            1,
+            false,
+            None,
+        );
+
+        assert_eq!(active_tab_indices, vec![0]);
+        assert_eq!(active_index, 0);
+
+        include_tab_in_or_view(
+            &mut tabs,
+            &mut active_index,
+            &mut active_tab_indices,
+            0,
+            false,
+            None,
+        );
+
+        assert_eq!(active_tab_indices, vec![0]);
+        assert_eq!(active_index, 0);
+    }
+
+    #[test]
+    fn paused_viewport_centers_selected_line() {
+        let lines = (0..20)
+            .map(|idx| RenderedLine {
+                seq: idx as u64,
+                text: idx.to_string().into(),
+                selected: idx == 10,
+                injected: false,
+                separator: None,
+            })
+            .collect::<Vec<_>>();
+        let (start, count, first_row) = viewport_for_lines(3, 10, &lines, true, None);
+        assert_eq!(start, 5);
+        assert_eq!(count, 10);
+        assert_eq!(first_row, 3);
+    }
+
+    #[test]
+    fn paused_viewport_stays_put_when_selection_is_visible() {
+        let lines_with_selection = |selected: u64| {
+            (0..30)
+                .map(|idx| RenderedLine {
+                    seq: idx,
+                    text: idx.to_string().into(),
+                    selected: idx == selected,
+                    injected: false,
+                    separator: None,
+                })
+                .collect::<Vec<_>>()
+        };
+        let previous = super::ViewportAnchor {
+            start_seq: 20,
+            first_row: 3,
+        };
+
+        let visible = lines_with_selection(27);
+        assert_eq!(
+            viewport_for_lines(3, 10, &visible, true, Some(previous)),
+            (20, 10, 3)
+        );
+
+        let offscreen = lines_with_selection(5);
+        assert_eq!(
+            viewport_for_lines(3, 10, &offscreen, true, Some(previous)),
+            (0, 10, 3)
+        );
+    }
+
+    #[test]
+    fn scrolling_moves_from_top_line_and_ends_at_bottom() {
+        use super::scrolled_viewport;
+
+        let lines = (0..30)
+            .map(|idx| RenderedLine {
+                seq: idx * 2,
+                text: idx.to_string().into(),
+                selected: false,
+                injected: false,
+                separator: None,
+            })
+            .collect::<Vec<_>>();
+        // From the bottom (start 20), up three rows.
+        assert_eq!(
+            scrolled_viewport(3, 10, &lines, None, -3, 20),
+            Some((17, 10, 3))
+        );
+        // Anchored on seq 34 (index 17), so new lines below don't move it.
+        assert_eq!(
+            scrolled_viewport(3, 10, &lines, Some(34), 0, 20),
+            Some((17, 10, 3))
+        );
+        assert_eq!(
+            scrolled_viewport(3, 10, &lines, Some(34), -40, 20),
+            Some((0, 10, 3))
+        );
+        assert_eq!(scrolled_viewport(3, 10, &lines, Some(34), 3, 20), None);
+        // Nothing to scroll when every line fits.
+        assert_eq!(scrolled_viewport(3, 40, &lines, None, -3, 0), None);
+    }
+
+    #[test]
+    fn focus_reports_are_parsed() {
+        let mut parser = super::InputParser::new();
+        let messages = b"\x1b[O\x1b[I"
+            .iter()
+            .filter_map(|byte| parser.feed(*byte))
+            .collect::<Vec<_>>();
+        assert!(matches!(
+            messages.as_slice(),
+            [UiMessage::FocusLost, UiMessage::FocusGained]
+        ));
+    }
+
+    #[test]
+    fn scroll_keys_and_wheel_are_parsed() {
+        assert!(matches!(
+            super::csi_key_message(b"A"),
+            Some(UiMessage::Scroll(-1))
+        ));
+        assert!(matches!(
+            super::csi_key_message(b"6~"),
+            Some(UiMessage::ScrollPage(1))
+        ));
+        assert!(super::csi_key_message(b"1;2A").is_none());
+        assert!(matches!(
+            super::csi_key_message(b"Z"),
+            Some(UiMessage::PrevTab)
+        ));
+        assert!(matches!(
+            try_parse_sgr_mouse_message(b"<64;10;5M"),
+            Some(UiMessage::Scroll(-3))
+        ));
+        assert!(matches!(
+            try_parse_sgr_mouse_message(b"<65;10;5M"),
+            Some(UiMessage::Scroll(3))
+        ));
+    }
+
+    #[test]
+    fn clicking_selected_line_toggles_selection_off() {
+        let clicked = RenderedLine {
+            seq: 42,
+            text: "selected".into(),
+            selected: false,
+            injected: false,
+            separator: None,
+        };
+        let mut selected = Some(SelectedLine {
+            seq: 42,
+            text: "selected".to_owned(),
+        });
+
+        toggle_selected_line(&mut selected, &clicked);
+        assert!(selected.is_none());
+
+        toggle_selected_line(&mut selected, &clicked);
+        assert_eq!(selected.as_ref().map(|line| line.seq), Some(42));
+    }
+
+    #[test]
+    fn right_clicks_pin_several_lines_in_seq_order() {
+        let line = |seq: u64| RenderedLine {
+            seq,
+            text: format!("line {}", seq).into(),
+            selected: false,
+            injected: false,
+            separator: None,
+        };
+        let mut pinned = Vec::new();
+        for seq in [9, 3, 5] {
+            super::toggle_pinned_line(&mut pinned, &line(seq));
+        }
+        let seqs = |pinned: &[SelectedLine]| pinned.iter().map(|pin| pin.seq).collect::<Vec<_>>();
+        assert_eq!(seqs(&pinned), vec![3, 5, 9]);
+        assert_eq!(pinned[0].text, "line 3");
+
+        let status = super::toggle_pinned_line(&mut pinned, &line(5));
+        assert_eq!(status.text, "unpinned line 5 (2 pinned)");
+        assert_eq!(seqs(&pinned), vec![3, 9]);
+    }
+
+    #[test]
+    fn quit_needs_second_press_within_window() {
+        use std::time::{Duration, Instant};
+
+        let start = Instant::now();
+        let mut pending = None;
+        assert!(!super::quit_confirmed(&mut pending, start));
+        assert!(super::quit_confirmed(
+            &mut pending,
+            start + Duration::from_millis(500)
+        ));
+
+        let mut pending = None;
+        assert!(!super::quit_confirmed(&mut pending, start));
+        let late = start + super::QUIT_CONFIRM_WINDOW + Duration::from_millis(1);
+        assert!(!super::quit_confirmed(&mut pending, late));
+        assert!(super::quit_confirmed(
+            &mut pending,
+            late + Duration::from_millis(1)
+        ));
+    }
+
+    #[test]
+    fn margin_selection_keeps_the_line_colors() {
+        use super::{SelectionStyle, selected_line_text};
+
+        let line = "\x1b[31merror\x1b[0m: disk";
+        let margin = selected_line_text(line, 20, SelectionStyle::Margin, false, false);
+        assert_eq!(margin, "\x1b[38;5;11m▌\x1b[39m\x1b[31merror\x1b[0m: disk");
+        assert_eq!(
+            selected_line_text(line, 20, SelectionStyle::Margin, false, true),
+            "▌\x1b[31merror\x1b[0m: disk"
+        );
+        // The bar takes a column from the text.
+        assert_eq!(
+            strip_ansi(&selected_line_text(
+                line,
+                6,
+                SelectionStyle::Margin,
+                false,
+                false
+            )),
+            "▌error"
+        );
+
+        assert_eq!(
+            selected_line_text(line, 20, SelectionStyle::Recolor, false, false),
+            "\x1b[38;5;11merror: disk\x1b[39m"
+        );
+        assert_eq!(
+            selected_line_text(line, 20, SelectionStyle::Reverse, false, false),
+            "\x1b[7merror: disk\x1b[27m"
+        );
+        assert!(SelectionStyle::parse("underline").is_err());
+    }
+
+    #[test]
+    fn background_is_restored_after_line_resets() {
+        use crossterm::style::Color;
+
+        assert_eq!(
+            super::reapply_background("plain", Color::AnsiValue(17)),
+            "plain"
+        );
+        assert_eq!(
+            super::reapply_background("\x1b[31mred\x1b[0m rest", Color::AnsiValue(17)),
+            "\x1b[31mred\x1b[0m\x1b[48;5;17m rest"
+        );
+        assert_eq!(
+            super::reapply_background("\x1b[1;49mbold\x1b[m", Color::AnsiValue(17)),
+            "\x1b[1;49m\x1b[48;5;17mbold\x1b[m\x1b[48;5;17m"
+        );
+    }
+
+    #[test]
+    fn recent_filters_are_deduped_newest_first_and_capped() {
+        let mut recent = super::RecentFilters::default();
+        recent.remember("error");
+        recent.remember("warn");
+        recent.remember("error");
+        recent.remember("");
+        assert_eq!(recent.entries, vec!["error", "warn"]);
+
+        for index in 0..super::RECENT_FILTERS_CAP + 5 {
+            recent.remember(&format!("filter {}", index));
+        }
+        assert_eq!(recent.entries.len(), super::RECENT_FILTERS_CAP);
+        assert_eq!(
+            recent.entries[0],
+            format!("filter {}", super::RECENT_FILTERS_CAP + 4)
+        );
+    }
+
+    #[test]
+    fn palette_narrows_by_query_and_falls_back_to_typed_text() {
+        let mut palette =
+            super::Palette::new(vec!["error".into(), "warn".into(), "error timeout".into()]);
+        palette.move_cursor(5);
+        assert_eq!(palette.choice().as_deref(), Some("error timeout"));
+
+        palette.edit_query(|query| query.push_str("err"));
+        assert_eq!(palette.matching(), vec!["error", "error timeout"]);
+        assert_eq!(palette.choice().as_deref(), Some("error"));
+        palette.move_cursor(-1);
+        assert_eq!(palette.cursor, 0);
+
+        palette.edit_query(|query| query.push('x'));
+        assert!(palette.matching().is_empty());
+        assert_eq!(palette.choice().as_deref(), Some("errx"));
+        assert_eq!(super::Palette::new(Vec::new()).choice(), None);
+    }
+
+    #[test]
+    fn repeat_counts_scale_navigation_and_moves_stop_at_the_ends() {
+        assert!(matches!(
+            UiMessage::Scroll(-1).repeated(5),
+            UiMessage::Scroll(-5)
+        ));
+        assert!(matches!(
+            UiMessage::ScrollPage(1).repeated(10),
+            UiMessage::ScrollPage(10)
+        ));
+        assert!(matches!(UiMessage::NextTab.repeated(3), UiMessage::NextTab));
+        assert!(UiMessage::MouseMove { row: 1 }.is_background());
+        assert!(!UiMessage::MoveSelection(1).is_background());
+
+        let lines: Vec<RenderedLine> = (0..5)
+            .map(|seq| RenderedLine {
+                seq: seq * 10,
+                text: format!("line {}", seq).into(),
+                selected: false,
+                injected: false,
+                separator: None,
+            })
+            .collect();
+        let moved = super::moved_selection(&lines, 10, 2).expect("line 10 is visible");
+        assert_eq!(moved.seq, 30);
+        assert_eq!(super::moved_selection(&lines, 10, 50).unwrap().seq, 40);
+        assert_eq!(super::moved_selection(&lines, 10, -50).unwrap().seq, 0);
+        assert!(super::moved_selection(&lines, 11, 1).is_none());
+    }
+
+    #[test]
+    fn overview_lists_each_tab_with_counts_and_latest_line() {
+        let mut tabs = vec![Tab::unfiltered(), Tab::new("error".into())];
+        apply_line_to_tabs(&mut tabs, &[0], false, 0, "error one");
+        apply_line_to_tabs(&mut tabs, &[0], false, 1, "info");
+        apply_line_to_tabs(&mut tabs, &[0], false, 2, "error two");
+
+        let rows = super::overview_rows(&tabs, None);
+        assert_eq!(rows[0], "  #  tab      total   unread  latest");
+        assert_eq!(rows[1], "  0  (all)        3        0  error two");
+        assert_eq!(rows[2], "  1  error        2        2  error two");
+
+        let paused = super::overview_rows(&tabs, Some(&[2, 1]));
+        assert!(paused[2].ends_with("error one"));
+
+        tabs.push(Tab::new("エラー".into()));
+        let rows = super::overview_rows(&tabs, None);
+        assert_eq!(rows[0], "  #  tab       total   unread  latest");
+        assert_eq!(rows[1], "  0  (all)         3        0  error two");
+        assert_eq!(rows[3], "  2  エラー        0        0  ");
+    }
+
+    #[test]
+    fn marker_slices_the_view_and_gets_its_own_separator() {
+        use super::{Marker, MarkerSlice, Separator};
+
+        let mut tabs = vec![Tab::unfiltered(), Tab::new("foo".into())];
+        for (seq, line) in ["foo a", "bar", "foo b", "foo c"].iter().enumerate() {
+            apply_line_to_tabs(&mut tabs, &[0], false, seq as u64, line);
+        }
+        let marker = |slice| Some(Marker { seq: 1, slice });
+        let seqs_of = |lines: &[RenderedLine]| {
+            lines
+                .iter()
+                .map(|line| (line.seq, line.separator.is_some()))
+                .collect::<Vec<_>>()
+        };
+
+        let mut after = prepare_visible_lines_for_tabs(&tabs, &[1], None, None, false);
+        super::slice_at_marker(&mut after, marker(MarkerSlice::After));
+        assert_eq!(seqs_of(&after), vec![(2, false), (3, false)]);
+
+        // The marked line isn't in this tab, so the separator goes above the next one.
+        let shown = super::insert_marker_separator(after, marker(MarkerSlice::After));
+        assert_eq!(seqs_of(&shown), vec![(1, true), (2, false), (3, false)]);
+        assert_eq!(
+            shown[0].separator,
+            Some(Separator::Marker(MarkerSlice::After))
+        );
+
+        let mut before = prepare_visible_lines_for_tabs(&tabs, &[0], None, None, false);
+        super::slice_at_marker(&mut before, marker(MarkerSlice::Before));
+        let shown = super::insert_marker_separator(before, marker(MarkerSlice::Before));
+        assert_eq!(seqs_of(&shown), vec![(0, false), (1, true), (1, false)]);
+        assert_eq!(MarkerSlice::Before.next(), MarkerSlice::Off);
+    }
+
+    #[test]
+    fn dropped_input_is_counted_and_reported_before_the_next_line() {
+        use super::InputMessage;
+
+        let (tx, rx) = std::sync::mpsc::sync_channel(2);
+        let mut skipped = 0;
+        for line in ["a", "b", "c", "d"] {
+            assert!(super::offer_line(&tx, line.to_owned(), None, &mut skipped));
+        }
+        assert_eq!(skipped, 2);
+        assert!(matches!(rx.recv(), Ok(InputMessage::Line(line, _)) if line == "a"));
+        // Room for the count, but not yet for the line after it.
+        assert!(super::offer_line(&tx, "e".to_owned(), None, &mut skipped));
+        assert_eq!(skipped, 1);
+        rx.recv().unwrap();
+        rx.recv().unwrap();
+        assert!(super::offer_line(&tx, "f".to_owned(), None, &mut skipped));
+        assert!(matches!(rx.recv(), Ok(InputMessage::Skipped(1))));
+        assert!(matches!(rx.recv(), Ok(InputMessage::Line(line, _)) if line == "f"));
+        assert_eq!(super::Overflow::parse("drop"), Ok(super::Overflow::Drop));
+        assert!(super::Overflow::parse("spill").is_err());
+    }
+
+    #[test]
+    fn partial_lines_are_sent_until_their_newline_completes_them() {
+        let (chunk_tx, chunk_rx) = std::sync::mpsc::sync_channel(4);
+        for chunk in ["a\npro", "gress", " 50%\nb\n", "tail"] {
+            chunk_tx.send(chunk.as_bytes().to_vec()).unwrap();
+        }
+        drop(chunk_tx);
+        let sent = std::cell::RefCell::new(Vec::new());
+        // With no delay, every chunk that leaves a line unfinished shows it.
+        assert!(super::assemble_lines(
+            chunk_rx,
+            std::time::Duration::ZERO,
+            |line| {
+                sent.borrow_mut().push(format!("line {line:?}"));
+                true
+            },
+            |partial| {
+                sent.borrow_mut().push(format!("partial {partial:?}"));
+                true
+            },
+        ));
+        assert_eq!(
+            sent.into_inner(),
+            [
+                r#"line "a\n""#,
+                r#"partial "pro""#,
+                r#"partial "progress""#,
+                r#"line "progress 50%\n""#,
+                r#"line "b\n""#,
+                r#"partial "tail""#,
+                r#"line "tail""#,
+            ]
+        );
+
+        let tabs = vec![super::filter_tab("pro".into(), false, false, 10)];
+        let mut lines = Vec::new();
+        super::push_partial_line(&mut lines, Some((7, "progress")), &tabs, &[0], false, None);
+        super::push_partial_line(&mut lines, Some((7, "tail")), &tabs, &[0], false, None);
+        assert_eq!(lines.len(), 1);
+        assert_eq!((lines[0].seq, lines[0].text.as_ref()), (7, "progress"));
+
+        // One slot holds the unfinished line, so several files can't share it.
+        let parse = |args: &[&str]| super::parse_args(args.iter().map(|arg| arg.to_string()));
+        assert!(parse(&["--flush-partial", "--file", "a.log"]).is_ok());
+        assert!(parse(&["--flush-partial", "--file", "a.log", "--file", "b.log"]).is_err());
+    }
+
+    #[test]
+    fn skipped_runs_get_a_separator_above_the_line_after_them() {
+        let mut tabs = vec![Tab::unfiltered()];
+        for seq in [0, 1, 5, 6, 9] {
+            apply_line_to_tabs(&mut tabs, &[0], false, seq, "line");
+        }
+        let lines = prepare_visible_lines_for_tabs(&tabs, &[0], None, None, false);
+        // Runs ending before seqs 5 and 9, one before the first line and
+        // one past the last.
+        let shown = super::insert_skip_separators(lines, &[(0, 4), (5, 3), (9, 2), (12, 1)]);
+        let rows = shown
+            .iter()
+            .map(|line| (line.seq, line.separator))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            rows,
+            vec![
+                (0, None),
+                (1, None),
+                (5, Some(super::Separator::Skipped(3))),
+                (5, None),
+                (6, None),
+                (9, Some(super::Separator::Skipped(2))),
+                (9, None),
+                (12, Some(super::Separator::Skipped(1))),
+            ]
+        );
+    }
+
+    #[test]
+    fn skipped_runs_are_forgotten_once_no_tab_holds_the_line_after_them() {
+        use super::{App, InputMessage};
+
+        let args = ["--no-all", "--scrollback", "2", "x"].map(String::from);
+        let mut app = App::from_args(args).unwrap();
+        let inputs = [
+            InputMessage::Line("x 0".to_owned(), None),
+            InputMessage::Skipped(1),
+            InputMessage::Line("x 1".to_owned(), None),
+            InputMessage::Skipped(1),
+            InputMessage::Line("x 2".to_owned(), None),
+            InputMessage::Line("x 3".to_owned(), None),
+            InputMessage::Skipped(1),
+        ];
+        for input in inputs {
+            app.apply_input(input).unwrap();
+        }
+        assert_eq!(app.tabs()[0].lines().next(), Some((4, "x 2")));
+        assert_eq!(app.skipped_lines, [(4, 1), (7, 1)]);
+    }
+
+    #[test]
+    fn frequency_counts_distinct_lines_by_count_or_recency() {
+        use super::FrequencySort;
+
+        let mut tab = Tab::new("err".into());
+        tab.push_line(0, "err disk");
+        tab.count_lines();
+        tab.push_line(1, "err net");
+        tab.push_line(2, "\x1b[31merr disk\x1b[0m");
+        tab.push_line(3, "err cpu");
+
+        let counts = tab.line_counts.as_ref().expect("counting is on");
+        assert_eq!(
+            counts.top(FrequencySort::Count, 2),
+            vec![(2, "err disk"), (1, "err cpu")]
+        );
+        assert_eq!(
+            counts.top(FrequencySort::Recency, 10),
+            vec![(1, "err cpu"), (2, "err disk"), (1, "err net")]
+        );
+
+        tab.rebuild_from(&std::collections::VecDeque::new(), false);
+        assert!(tab.line_counts.is_none());
+    }
+
+    #[test]
+    fn tiny_terminal_gets_a_clipped_notice() {
+        assert_eq!(super::too_small_notice(80, 24), None);
+        assert_eq!(
+            super::too_small_notice(80, 3),
+            Some(vec![
+                "terminal too small".to_owned(),
+                "need 20x4".to_owned()
+            ])
+        );
+        assert_eq!(super::too_small_notice(4, 1), Some(vec!["term".to_owned()]));
+    }
+
+    #[test]
+    fn bottom_tab_bar_takes_last_rows_for_layout_and_clicks() {
+        assert_eq!(super::screen_layout(24, false), (0, 3..24));
+        assert_eq!(super::screen_layout(24, true), (21, 0..21));
+        assert_eq!(super::screen_layout(2, true), (0, 2..2));
+
+        let render_state = super::RenderState {
+            tab_hitboxes: vec![super::TabHitbox {
+                index: 1,
+                left: 0,
+                right: 9,
+            }],
+            bar_top: 21,
+            ..Default::default()
+        };
+        assert_eq!(super::tab_index_at_position(&render_state, 4, 0), None);
+        assert_eq!(super::tab_index_at_position(&render_state, 4, 20), None);
+        assert_eq!(super::tab_index_at_position(&render_state, 4, 21), Some(1));
+        assert_eq!(super::tab_index_at_position(&render_state, 4, 23), Some(1));
+    }
+
+    #[test]
+    fn middle_visible_line_picks_middle_rendered_row() {
+        let mut render_state = super::RenderState {
+            tab_hitboxes: Vec::new(),
+            line_rows: vec![None; 8],
+            viewport: None,
+            scroll_top: None,
+            bar_top: 0,
+            overview_rows: Vec::new(),
+            split_first_row: None,
+            split_viewport: None,
+            split_scroll_top: None,
+            gutter_width: 0,
+            split_gutter_width: 0,
+            tab_scroll: 0,
+        };
+        render_state.line_rows[2] = Some(RenderedLine {
+            seq: 10,
+            text: "a".into(),
+            selected: false,
+            injected: false,
+            separator: None,
+        });
+        render_state.line_rows[3] = Some(RenderedLine {
+            seq: 20,
+            text: "b".into(),
+            selected: false,
+            injected: false,
+            separator: None,
+        });
+        render_state.line_rows[4] = Some(RenderedLine {
+            seq: 30,
+            text: "c".into(),
+            selected: false,
+            injected: false,
+            separator: None,
+        });
+
+        let picked = middle_visible_line(&render_state).expect("middle line should exist");
+        assert_eq!(picked.seq, 20);
+    }
+}
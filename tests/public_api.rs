@@ -0,0 +1,23 @@
+use streamtabs::{App, Tab, UiMessage};
+
+#[test]
+fn tabs_filter_lines_through_the_public_api() {
+    let tab = Tab::new("re:^ERR~retry".to_owned());
+    assert!(tab.matches("ERR disk full"));
+    assert!(!tab.matches("ERR disk full, retry"));
+    assert!(!tab.matches("ok"));
+    assert!(Tab::unfiltered().matches("anything"));
+
+    let mut app = App::from_args(["error".to_owned()]).unwrap();
+    for line in ["info start", "error disk", "info done", "error net"] {
+        assert!(app.apply_line(line));
+    }
+    assert_eq!(app.tabs()[1].label(), "error");
+    assert_eq!(app.tabs()[1].unread_matches(), 2);
+
+    app.handle_ui(UiMessage::SelectTab(1)).unwrap();
+    assert_eq!(app.active_tab(), 1);
+    let state = app.render(40, 8);
+    assert_eq!(state.visible_seqs(), [1, 3]);
+    assert_eq!(app.tabs()[1].unread_matches(), 0);
+}